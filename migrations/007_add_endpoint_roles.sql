@@ -0,0 +1,14 @@
+-- Group membership for local users (comma-separated)
+ALTER TABLE users ADD COLUMN groups TEXT;
+
+-- Role per endpoint for a user or a group ('viewer', 'operator', 'admin')
+CREATE TABLE IF NOT EXISTS endpoint_roles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    endpoint_id INTEGER NOT NULL,
+    subject_type TEXT NOT NULL, -- 'user' or 'group'
+    subject TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (endpoint_id, subject_type, subject),
+    FOREIGN KEY (endpoint_id) REFERENCES endpoints(id) ON DELETE CASCADE
+);
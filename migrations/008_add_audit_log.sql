@@ -0,0 +1,17 @@
+-- Audit log of write actions (maintenance, console, blueprint, import, export)
+-- Bez FK na endpoints, záznamy musí přežít smazání connection
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    username TEXT NOT NULL,
+    endpoint_id INTEGER,
+    endpoint_name TEXT,
+    database_name TEXT,
+    action TEXT NOT NULL,
+    command TEXT NOT NULL,
+    success INTEGER NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_log_endpoint ON audit_log(endpoint_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- SSH tunnel (bastion) settings per endpoint
+ALTER TABLE endpoints ADD COLUMN ssh_host TEXT;
+ALTER TABLE endpoints ADD COLUMN ssh_port INTEGER;
+ALTER TABLE endpoints ADD COLUMN ssh_user TEXT;
+ALTER TABLE endpoints ADD COLUMN ssh_key_path TEXT;
+ALTER TABLE endpoints ADD COLUMN ssh_passphrase_encrypted TEXT;
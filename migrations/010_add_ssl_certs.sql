@@ -0,0 +1,4 @@
+-- Custom CA and client certificate per endpoint (file path or inline PEM)
+ALTER TABLE endpoints ADD COLUMN ssl_root_cert TEXT;
+ALTER TABLE endpoints ADD COLUMN ssl_cert TEXT;
+ALTER TABLE endpoints ADD COLUMN ssl_key_encrypted TEXT;
@@ -0,0 +1,4 @@
+-- Grouping and classification of endpoints (group folder, comma-separated tags, dev/test/staging/prod)
+ALTER TABLE endpoints ADD COLUMN group_name TEXT;
+ALTER TABLE endpoints ADD COLUMN tags TEXT;
+ALTER TABLE endpoints ADD COLUMN environment TEXT;
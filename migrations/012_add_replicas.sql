@@ -0,0 +1,3 @@
+-- Read replicas per endpoint (one connection string per line) and max. accepted replication lag in seconds
+ALTER TABLE endpoints ADD COLUMN replica_urls TEXT;
+ALTER TABLE endpoints ADD COLUMN replica_max_lag INTEGER;
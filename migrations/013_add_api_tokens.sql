@@ -0,0 +1,21 @@
+-- API tokens for automation clients (token is stored as SHA-256 hash)
+-- kind: 'personal' (acts as the user) or 'service' (own identity 'svc:<name>' with groups)
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    user_id INTEGER,
+    username TEXT NOT NULL,
+    groups TEXT,
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    scopes TEXT NOT NULL, -- comma-separated: read, maintenance, export, console
+    token_hash TEXT NOT NULL UNIQUE,
+    token_prefix TEXT NOT NULL,
+    expires_at TEXT,
+    last_used_at TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_username ON api_tokens(username);
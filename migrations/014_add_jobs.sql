@@ -0,0 +1,20 @@
+-- Background jobs (export, import, console) - history survives restarts
+-- Bez FK na endpoints, záznamy musí přežít smazání connection
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY, -- job_id, e.g. export_<uuid>
+    kind TEXT NOT NULL, -- export, import, console
+    endpoint_id INTEGER,
+    endpoint_name TEXT,
+    database_name TEXT,
+    username TEXT NOT NULL,
+    params TEXT NOT NULL, -- JSON request used to start the job (for re-run)
+    status TEXT NOT NULL, -- running, completed, failed, interrupted
+    command TEXT,
+    error TEXT,
+    artifact_path TEXT,
+    log_path TEXT,
+    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+    completed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_started ON jobs(started_at DESC);
@@ -0,0 +1,21 @@
+-- Scheduled recurring exports: cron expression (UTC) + ExportRequest options as JSON
+CREATE TABLE IF NOT EXISTS export_schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    endpoint_id INTEGER NOT NULL,
+    cron TEXT NOT NULL,
+    options TEXT NOT NULL, -- JSON ExportRequest
+    keep_last INTEGER NOT NULL, -- number of dumps to keep, older ones are deleted
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_by TEXT NOT NULL,
+    last_run_at TEXT,
+    last_job_id TEXT,
+    last_status TEXT, -- running, completed, failed, cancelled
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (endpoint_id) REFERENCES endpoints(id) ON DELETE CASCADE
+);
+
+-- Dumps created by a schedule (for its keep_last retention)
+ALTER TABLE jobs ADD COLUMN schedule_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_jobs_schedule ON jobs(schedule_id, started_at DESC);
@@ -0,0 +1,18 @@
+-- S3-compatible upload targets for export dumps (secret key encrypted like endpoint passwords)
+CREATE TABLE IF NOT EXISTS storage_targets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    endpoint_url TEXT NOT NULL, -- e.g. https://s3.eu-central-1.amazonaws.com, http://minio:9000
+    region TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    prefix TEXT,
+    access_key TEXT NOT NULL,
+    secret_key_encrypted TEXT NOT NULL,
+    path_style INTEGER NOT NULL DEFAULT 1,
+    delete_local INTEGER NOT NULL DEFAULT 0, -- remove the local dump after a verified upload
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Where the dump of a job was uploaded
+ALTER TABLE jobs ADD COLUMN target_id INTEGER;
+ALTER TABLE jobs ADD COLUMN remote_key TEXT;
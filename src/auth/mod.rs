@@ -0,0 +1,468 @@
+pub mod tokens;
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::{ConnectInfo, FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderName, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
+use ipnet::IpNet;
+use rand::TryRngCore;
+use sha2::{Digest, Sha256};
+
+use crate::handlers::{base_path_url, AppState};
+
+pub const SESSION_COOKIE: &str = "pg_session";
+pub const SESSION_TTL_SECS: i64 = 12 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AuthMode {
+    /// No authentication (everyone is admin)
+    None,
+    /// Local user accounts stored in SQLite
+    Local,
+    /// Identity headers set by a trusted reverse proxy (SSO)
+    Proxy,
+}
+
+/// Nastavení pro --auth proxy
+#[derive(Debug, Clone)]
+pub struct ProxyAuth {
+    pub trusted: Vec<IpNet>,
+    pub user_header: HeaderName,
+    pub groups_header: HeaderName,
+    pub admin_group: Option<String>,
+}
+
+impl ProxyAuth {
+    pub fn new(
+        trusted: &str,
+        user_header: &str,
+        groups_header: &str,
+        admin_group: Option<String>,
+    ) -> Result<Self> {
+        let trusted = parse_trusted_proxies(trusted)?;
+        if trusted.is_empty() {
+            anyhow::bail!("--auth proxy requires at least one --trusted-proxies address");
+        }
+        Ok(Self {
+            trusted,
+            user_header: HeaderName::from_bytes(user_header.as_bytes())
+                .with_context(|| format!("Invalid header name: {}", user_header))?,
+            groups_header: HeaderName::from_bytes(groups_header.as_bytes())
+                .with_context(|| format!("Invalid header name: {}", groups_header))?,
+            admin_group: admin_group.filter(|g| !g.trim().is_empty()),
+        })
+    }
+
+    pub fn is_trusted(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        self.trusted.iter().any(|net| net.contains(&addr))
+    }
+}
+
+/// Seznam adres/CIDR oddělený čárkami (např. "127.0.0.1,10.0.0.0/8")
+pub fn parse_trusted_proxies(value: &str) -> Result<Vec<IpNet>> {
+    value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse::<IpNet>()
+                .or_else(|_| item.parse::<IpAddr>().map(IpNet::from))
+                .with_context(|| format!("Invalid trusted proxy address: {}", item))
+        })
+        .collect()
+}
+
+/// Role per endpoint, seřazené od nejslabší po nejsilnější
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Role {
+    /// No access to the endpoint
+    None,
+    /// Browse only
+    Viewer,
+    /// Maintenance actions, exports and read-only console queries
+    Operator,
+    /// Destructive SQL, blueprint and import
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::None => "none",
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        match value.trim().to_lowercase().as_str() {
+            "none" => Some(Role::None),
+            "viewer" => Some(Role::Viewer),
+            "operator" => Some(Role::Operator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Přihlášený uživatel aktuálního requestu
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: Option<i64>,
+    pub username: String,
+    pub is_admin: bool,
+    pub groups: Vec<String>,
+    /// Request přišel s API tokenem místo session
+    pub api_token: Option<tokens::TokenGrant>,
+}
+
+impl CurrentUser {
+    pub fn anonymous() -> Self {
+        Self {
+            id: None,
+            username: "anonymous".to_string(),
+            is_admin: true,
+            groups: Vec::new(),
+            api_token: None,
+        }
+    }
+
+    /// Jméno do audit logu (u tokenu i s jeho názvem)
+    pub fn audit_name(&self) -> String {
+        match &self.api_token {
+            Some(token) => format!("{} (token {})", self.username, token.name),
+            None => self.username.clone(),
+        }
+    }
+}
+
+pub fn parse_groups(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|g| g.trim().to_string())
+        .filter(|g| !g.is_empty())
+        .collect()
+}
+
+/// Nejvyšší role uživatele na endpointu (přímé přidělení nebo přes skupinu)
+pub fn resolve_role(state: &AppState, user: &CurrentUser, endpoint_id: i64) -> Role {
+    if state.auth_mode == AuthMode::None || user.is_admin {
+        return Role::Admin;
+    }
+    let granted = state
+        .endpoint_roles
+        .read()
+        .ok()
+        .and_then(|roles| {
+            roles
+                .iter()
+                .filter(|r| r.endpoint_id == endpoint_id)
+                .filter(|r| match r.subject_type.as_str() {
+                    "user" => r.subject == user.username,
+                    "group" => user.groups.iter().any(|g| g == &r.subject),
+                    _ => false,
+                })
+                .filter_map(|r| Role::parse(&r.role))
+                .max()
+        });
+    granted.unwrap_or(state.default_role)
+}
+
+/// Ověří, že uživatel má na endpointu alespoň požadovanou roli
+pub fn require_role(
+    state: &AppState,
+    user: &CurrentUser,
+    endpoint_id: i64,
+    required: Role,
+    action: &str,
+) -> Result<(), String> {
+    let role = resolve_role(state, user, endpoint_id);
+    if role >= required {
+        Ok(())
+    } else {
+        tracing::warn!(
+            "User {} ({}) is not allowed to {} on endpoint {}",
+            user.username,
+            role.as_str(),
+            action,
+            endpoint_id
+        );
+        Err(format!(
+            "Permission denied: {} requires the {} role on this connection (you have {})",
+            action,
+            required.as_str(),
+            role.as_str()
+        ))
+    }
+}
+
+/// Načte přidělené role ze SQLite do paměti (volá se při startu a po každé změně)
+pub async fn reload_endpoint_roles(state: &AppState) -> Result<()> {
+    let Some(db) = state.db.as_ref() else {
+        return Ok(());
+    };
+    let roles = db.get_endpoint_roles().await?;
+    if let Ok(mut guard) = state.endpoint_roles.write() {
+        *guard = roles;
+    }
+    Ok(())
+}
+
+tokio::task_local! {
+    /// Uživatel pro build_ctx (navbar), nastavuje ho require_auth
+    pub static REQUEST_USER: CurrentUser;
+}
+
+/// Vrátí uživatele aktuálního requestu (jen v rámci handleru, ne ve spawnutých jobech)
+pub fn request_user() -> Option<CurrentUser> {
+    REQUEST_USER.try_with(|u| u.clone()).ok()
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Not authenticated".to_string()))
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String> {
+    let mut salt = [0u8; 16];
+    rand::rngs::OsRng
+        .try_fill_bytes(&mut salt)
+        .map_err(|e| anyhow::anyhow!("Failed to generate salt: {}", e))?;
+    let salt = SaltString::encode_b64(&salt)
+        .map_err(|e| anyhow::anyhow!("Failed to encode salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Náhodný token (hex) pro session cookie
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to generate token: {}", e))?;
+    Ok(hex::encode(bytes))
+}
+
+/// V databázi ukládáme jen hash tokenu
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn session_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+fn is_public_path(path: &str) -> bool {
+    path == "/login" || path == "/setup" || path.starts_with("/static/")
+}
+
+/// Middleware: každý request (kromě login stránky a statických souborů) musí mít platnou session
+pub async fn require_auth(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // API token má přednost před session i proxy hlavičkami
+    if let Some(token) = tokens::bearer_token(&req) {
+        let Some(user) = tokens::token_user(&state, &token).await else {
+            return token_rejected(&req, StatusCode::UNAUTHORIZED, "Invalid or expired API token".to_string());
+        };
+        let scopes = user.api_token.as_ref().map(|t| t.scopes.clone()).unwrap_or_default();
+        match tokens::required_scope(req.method(), req.uri().path()) {
+            Some(scope) if scopes.contains(&scope) => {}
+            Some(scope) => {
+                return token_rejected(
+                    &req,
+                    StatusCode::FORBIDDEN,
+                    format!("API token does not have the {} scope", scope.as_str()),
+                );
+            }
+            None => {
+                return token_rejected(&req, StatusCode::FORBIDDEN, "Not available with API tokens".to_string());
+            }
+        }
+        req.extensions_mut().insert(user.clone());
+        return REQUEST_USER.scope(user, next.run(req)).await;
+    }
+
+    let user = match state.auth_mode {
+        AuthMode::None => Some(CurrentUser::anonymous()),
+        AuthMode::Local => session_user(&state, &jar).await,
+        AuthMode::Proxy => match proxy_user(&state, &req) {
+            Ok(user) => Some(user),
+            Err(rejection) => return rejection.into_response(),
+        },
+    };
+
+    let user = match user {
+        Some(user) => user,
+        None if is_public_path(req.uri().path()) => {
+            return next.run(req).await;
+        }
+        None => return unauthenticated(&state, &req),
+    };
+
+    req.extensions_mut().insert(user.clone());
+    REQUEST_USER.scope(user, next.run(req)).await
+}
+
+async fn session_user(state: &Arc<AppState>, jar: &CookieJar) -> Option<CurrentUser> {
+    let token = jar.get(SESSION_COOKIE)?.value().to_string();
+    let db = state.db.as_ref()?;
+    match db.get_session_user(&hash_token(&token)).await {
+        Ok(Some(user)) => Some(CurrentUser {
+            id: Some(user.id),
+            groups: parse_groups(user.groups.as_deref()),
+            username: user.username,
+            is_admin: user.is_admin,
+            api_token: None,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Failed to load session: {}", e);
+            None
+        }
+    }
+}
+
+/// Identita z hlaviček reverse proxy; bez hlavičky nebo z nedůvěryhodné adresy request odmítneme
+fn proxy_user(state: &AppState, req: &Request) -> Result<CurrentUser, (StatusCode, &'static str)> {
+    let Some(proxy) = state.proxy_auth.as_ref() else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Proxy authentication is not configured"));
+    };
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    match peer {
+        Some(ip) if proxy.is_trusted(ip) => {}
+        _ => {
+            tracing::warn!(
+                "Rejected request to {} from untrusted address {:?}",
+                req.uri().path(),
+                peer
+            );
+            return Err((StatusCode::FORBIDDEN, "Request did not come from a trusted proxy"));
+        }
+    }
+
+    let username = req
+        .headers()
+        .get(&proxy.user_header)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .ok_or((StatusCode::UNAUTHORIZED, "Missing identity header from proxy"))?;
+    let groups = parse_groups(
+        req.headers()
+            .get(&proxy.groups_header)
+            .and_then(|v| v.to_str().ok()),
+    );
+    let is_admin = proxy
+        .admin_group
+        .as_ref()
+        .map(|admin| groups.iter().any(|g| g == admin))
+        .unwrap_or(false);
+    Ok(CurrentUser {
+        id: None,
+        username: username.to_string(),
+        is_admin,
+        groups,
+        api_token: None,
+    })
+}
+
+fn token_rejected(req: &Request, status: StatusCode, message: String) -> Response {
+    tracing::warn!("API token request to {} rejected: {}", req.uri().path(), message);
+    let challenge = [(header::WWW_AUTHENTICATE, "Bearer")];
+    if req.uri().path().starts_with("/api/") {
+        return (status, challenge, axum::Json(serde_json::json!({ "error": message }))).into_response();
+    }
+    (status, challenge, message).into_response()
+}
+
+fn unauthenticated(state: &Arc<AppState>, req: &Request) -> Response {
+    let next = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| "/".to_string());
+    let login_url = format!(
+        "{}?next={}",
+        base_path_url(state, "/login"),
+        urlencoding::encode(&next)
+    );
+
+    if req.uri().path().starts_with("/api/") {
+        return (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({ "error": "Not authenticated" })),
+        )
+            .into_response();
+    }
+
+    // htmx requesty přesměrujeme přes HX-Redirect, ostatní (fetch/SSE) dostanou 401
+    if req.headers().contains_key("HX-Request") {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [("HX-Redirect", login_url)],
+            "Not authenticated",
+        )
+            .into_response();
+    }
+    let wants_html = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("text/html"))
+        .unwrap_or(false);
+    if req.method() == Method::GET && wants_html {
+        return Redirect::to(&login_url).into_response();
+    }
+    (StatusCode::UNAUTHORIZED, "Not authenticated").into_response()
+}
+
+/// Povolí jen relativní cesty v aplikaci (ochrana proti open redirect)
+pub fn safe_next_path(next: Option<&str>) -> String {
+    match next {
+        Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.contains('\\') => {
+            path.to_string()
+        }
+        _ => "/".to_string(),
+    }
+}
@@ -0,0 +1,152 @@
+//! API tokeny pro automatizaci (CI, chatops): `Authorization: Bearer pgx_...`
+
+use axum::http::{header, Method};
+use axum::extract::Request;
+
+use crate::auth::{generate_token, hash_token, parse_groups, CurrentUser};
+use crate::db::models::ApiToken;
+use crate::handlers::AppState;
+
+pub const TOKEN_PREFIX: &str = "pgx_";
+pub const KIND_PERSONAL: &str = "personal";
+pub const KIND_SERVICE: &str = "service";
+/// Service tokeny vystupují pod tímto prefixem (role se přidělují na `svc:<name>` nebo skupiny)
+pub const SERVICE_USER_PREFIX: &str = "svc:";
+
+/// Oblasti, na které token smí; role uživatele na endpointu platí navíc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Prohlížení a JSON API
+    Read,
+    /// Reindex, vacuum, analyze, autovacuum
+    Maintenance,
+    /// Export a import dat
+    Export,
+    /// Dev console a blueprint
+    Console,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Read, Scope::Maintenance, Scope::Export, Scope::Console];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Maintenance => "maintenance",
+            Scope::Export => "export",
+            Scope::Console => "console",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|s| s.as_str() == value.trim())
+    }
+}
+
+/// Token, kterým se request autentizoval
+#[derive(Debug, Clone)]
+pub struct TokenGrant {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Nový token: (hash do DB, prefix pro seznam, celý token pro uživatele)
+pub fn new_token() -> anyhow::Result<(String, String, String)> {
+    let token = format!("{}{}", TOKEN_PREFIX, generate_token()?);
+    let prefix = token[..TOKEN_PREFIX.len() + 8].to_string();
+    Ok((hash_token(&token), prefix, token))
+}
+
+pub fn bearer_token(req: &Request) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    Some(token.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// Scope potřebný pro request; None = s tokenem nepřístupné (uživatelé, tokeny, správa endpointů)
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let read_only = method == Method::GET || method == Method::HEAD;
+    let under = |prefix: &str| path == prefix || path.starts_with(&format!("{}/", prefix));
+
+    if under("/users") || under("/tokens") || under("/login") || under("/logout") || under("/setup") {
+        return None;
+    }
+    if under("/dev") || under("/blueprint") {
+        return Some(Scope::Console);
+    }
+    if under("/export") || under("/import") || under("/maintenance/export") || under("/maintenance/import") || under("/schedules") {
+        return Some(Scope::Export);
+    }
+    if under("/maintenance") || (under("/analyze") && !read_only) {
+        return Some(Scope::Maintenance);
+    }
+    if under("/endpoints") && !read_only {
+        // Výběr a test spojení nic nemění, ostatní POST/PUT/DELETE spravují katalog
+        return (path.ends_with("/select") || path.ends_with("/test")).then_some(Scope::Read);
+    }
+    if under("/databases") || read_only {
+        return Some(Scope::Read);
+    }
+    None
+}
+
+/// Uživatel, za kterého token vystupuje (None = token neplatný, vypršelý nebo bez vlastníka)
+pub async fn token_user(state: &AppState, token: &str) -> Option<CurrentUser> {
+    let db = state.db.as_ref()?;
+    let record: ApiToken = match db.use_api_token(&hash_token(token)).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load API token: {}", e);
+            return None;
+        }
+    };
+    let grant = TokenGrant {
+        name: record.name.clone(),
+        scopes: record.scope_list().into_iter().filter_map(Scope::parse).collect(),
+    };
+
+    // Osobní token lokálního účtu přebírá aktuální skupiny a admin práva uživatele
+    if let Some(user_id) = record.user_id {
+        let user = db.get_user(user_id).await.ok().flatten()?;
+        return Some(CurrentUser {
+            id: Some(user.id),
+            groups: parse_groups(user.groups.as_deref()),
+            username: user.username,
+            is_admin: user.is_admin,
+            api_token: Some(grant),
+        });
+    }
+    Some(CurrentUser {
+        id: None,
+        username: record.username,
+        is_admin: record.is_admin,
+        groups: parse_groups(record.groups.as_deref()),
+        api_token: Some(grant),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(required_scope(&Method::GET, "/api/v1/endpoints"), Some(Scope::Read));
+        assert_eq!(required_scope(&Method::GET, "/tables/public/x"), Some(Scope::Read));
+        assert_eq!(required_scope(&Method::POST, "/maintenance/vacuum/public/x"), Some(Scope::Maintenance));
+        assert_eq!(required_scope(&Method::POST, "/maintenance/export"), Some(Scope::Export));
+        assert_eq!(required_scope(&Method::GET, "/maintenance/export/abc/download"), Some(Scope::Export));
+        assert_eq!(required_scope(&Method::POST, "/dev/execute"), Some(Scope::Console));
+        assert_eq!(required_scope(&Method::POST, "/dev/abc/cancel"), Some(Scope::Console));
+        assert_eq!(required_scope(&Method::POST, "/maintenance/import/abc/cancel"), Some(Scope::Export));
+        assert_eq!(required_scope(&Method::POST, "/schedules/3/run"), Some(Scope::Export));
+        assert_eq!(required_scope(&Method::POST, "/endpoints/3/select"), Some(Scope::Read));
+        assert_eq!(required_scope(&Method::DELETE, "/endpoints/3"), None);
+        assert_eq!(required_scope(&Method::GET, "/tokens"), None);
+        assert_eq!(required_scope(&Method::POST, "/users"), None);
+    }
+}
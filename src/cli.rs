@@ -0,0 +1,488 @@
+//! Headless příkazy (cron, terminál) nad stejnými dotazy a uloženými endpointy jako web
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::auth::{CurrentUser, REQUEST_USER};
+use crate::db::models::{Endpoint, StorageTarget};
+use crate::handlers::audit::{self, csv_field};
+use crate::handlers::export::{run_export, ExportRequest};
+use crate::handlers::indices::fetch_indices_from_db;
+use crate::handlers::patroni::fetch_cluster_status;
+use crate::handlers::tables::fetch_tables_from_db;
+use crate::handlers::tuning::{fetch_tuning_report, TuningReport};
+use crate::handlers::{all_endpoints, connect_pg, patroni_urls, AppState, JobStatus};
+use crate::utils::filter::{matches_expression, matches_pattern, parse_pattern_expression};
+use crate::utils::format::bytes_to_human;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List tables of a connection
+    Tables(TablesArgs),
+    /// List indices of a connection
+    Indices(IndicesArgs),
+    /// Tuning findings and health score of a connection
+    TuningReport(TuningArgs),
+    /// Dump a connection with pg_dump
+    Export(ExportArgs),
+    /// Members of the Patroni cluster
+    PatroniStatus(PatroniArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TablesArgs {
+    /// Connection name or ID
+    #[arg(long, short = 'e')]
+    endpoint: String,
+
+    /// Table name filter: wildcards, `,` for OR, `-name` to exclude (e.g. 'user_* -tmp')
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Schema pattern (e.g. public, app_*)
+    #[arg(long)]
+    schema: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct IndicesArgs {
+    /// Connection name or ID
+    #[arg(long, short = 'e')]
+    endpoint: String,
+
+    /// Index name filter (same syntax as for tables)
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Schema pattern
+    #[arg(long)]
+    schema: Option<String>,
+
+    /// Table pattern
+    #[arg(long)]
+    table: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TuningArgs {
+    /// Connection name or ID
+    #[arg(long, short = 'e')]
+    endpoint: String,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PatroniArgs {
+    /// Connection name or ID (for Patroni URLs from the config file; default: --patroni-urls)
+    #[arg(long, short = 'e')]
+    endpoint: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// Connection name or ID
+    #[arg(long, short = 'e')]
+    endpoint: String,
+
+    /// What to dump
+    #[arg(long, value_parser = ["full", "schema", "data"], default_value = "full")]
+    scope: String,
+
+    /// pg_dump format
+    #[arg(long, value_parser = ["custom", "plain", "directory", "tar"], default_value = "custom")]
+    format: String,
+
+    /// Dump only these tables (schema.table, repeatable)
+    #[arg(long = "table", short = 't')]
+    tables: Vec<String>,
+
+    /// Skip data of tables matching the pattern (repeatable)
+    #[arg(long)]
+    exclude_table_data: Vec<String>,
+
+    /// Destination of the dump (default: the export directory of the web UI)
+    #[arg(long, short = 'o')]
+    output: Option<PathBuf>,
+
+    /// Upload the dump to this storage target (name or ID, see /targets)
+    #[arg(long)]
+    target: Option<String>,
+
+    #[arg(long)]
+    compress: bool,
+
+    /// Keep ownership (without it pg_dump runs with --no-owner)
+    #[arg(long)]
+    include_ownership: bool,
+
+    /// Add DROP statements (--clean)
+    #[arg(long)]
+    clean: bool,
+
+    /// Add CREATE DATABASE (--create)
+    #[arg(long)]
+    create: bool,
+
+    /// Verbose pg_dump output, printed to stderr
+    #[arg(long)]
+    verbose: bool,
+}
+
+pub async fn run(state: &Arc<AppState>, command: Command) -> Result<()> {
+    // Role a audit berou uživatele z requestu; příkazy běží za lokálního uživatele OS
+    let user = CurrentUser {
+        username: format!("cli:{}", std::env::var("USER").unwrap_or_else(|_| "unknown".to_string())),
+        ..CurrentUser::anonymous()
+    };
+    REQUEST_USER
+        .scope(user, async {
+            match command {
+                Command::Tables(args) => tables(state, args).await,
+                Command::Indices(args) => indices(state, args).await,
+                Command::TuningReport(args) => tuning_report(state, args).await,
+                Command::Export(args) => export(state, args).await,
+                Command::PatroniStatus(args) => patroni_status(state, args).await,
+            }
+        })
+        .await
+}
+
+/// Endpoint podle ID nebo jména
+async fn resolve_endpoint(state: &Arc<AppState>, key: &str) -> Result<Endpoint> {
+    let endpoints = all_endpoints(state).await?;
+    let names: Vec<&str> = endpoints.iter().map(|e| e.name.as_str()).collect();
+    endpoints
+        .iter()
+        .find(|e| e.id.to_string() == key || e.name == key)
+        .cloned()
+        .with_context(|| format!("Unknown connection '{}' (available: {})", key, names.join(", ")))
+}
+
+/// Pattern bez omezení (`*` nebo prázdný) se ignoruje
+fn pattern(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty() && *v != "*")
+}
+
+async fn tables(state: &Arc<AppState>, args: TablesArgs) -> Result<()> {
+    let endpoint = resolve_endpoint(state, &args.endpoint).await?;
+    let mut rows = fetch_tables_from_db(state, &endpoint).await.map_err(anyhow::Error::msg)?;
+    if let Some(schema) = pattern(&args.schema) {
+        rows.retain(|t| matches_pattern(&t.schema, schema));
+    }
+    if let Some(filter) = pattern(&args.filter) {
+        let (includes, excludes) = parse_pattern_expression(filter);
+        rows.retain(|t| matches_expression(&t.name, &includes, &excludes));
+    }
+    print_rows(args.format, &rows, &["schema", "table", "size", "rows", "indexes", "partitions"], |t, human| {
+        vec![
+            t.schema.clone(),
+            t.name.clone(),
+            size(t.size_bytes, human),
+            t.row_estimate.to_string(),
+            t.index_count.to_string(),
+            t.partitions.as_ref().map(Vec::len).unwrap_or(0).to_string(),
+        ]
+    })
+}
+
+async fn indices(state: &Arc<AppState>, args: IndicesArgs) -> Result<()> {
+    let endpoint = resolve_endpoint(state, &args.endpoint).await?;
+    let mut rows = fetch_indices_from_db(state, &endpoint).await.map_err(anyhow::Error::msg)?;
+    if let Some(schema) = pattern(&args.schema) {
+        rows.retain(|i| matches_pattern(&i.schema, schema));
+    }
+    if let Some(table) = pattern(&args.table) {
+        rows.retain(|i| matches_pattern(&i.table_name, table));
+    }
+    if let Some(filter) = pattern(&args.filter) {
+        let (includes, excludes) = parse_pattern_expression(filter);
+        rows.retain(|i| matches_expression(&i.index_name, &includes, &excludes));
+    }
+    rows.sort_by_key(|i| std::cmp::Reverse(i.size_bytes));
+    print_rows(args.format, &rows, &["schema", "table", "index", "size", "scans", "tuples_read"], |i, human| {
+        vec![
+            i.schema.clone(),
+            i.table_name.clone(),
+            i.index_name.clone(),
+            size(i.size_bytes, human),
+            i.scans.map(|n| n.to_string()).unwrap_or_default(),
+            i.idx_tup_read.map(|n| n.to_string()).unwrap_or_default(),
+        ]
+    })
+}
+
+async fn tuning_report(state: &Arc<AppState>, args: TuningArgs) -> Result<()> {
+    let endpoint = resolve_endpoint(state, &args.endpoint).await?;
+    let pg = connect_pg(state, &endpoint).await.context("Failed to connect to Postgres")?;
+    let report = fetch_tuning_report(&pg).await;
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Csv => print!("{}", format_csv(&["category", "object", "detail"], &tuning_findings(&report))),
+        OutputFormat::Table => {
+            println!("Health score: {}/100", report.health_score);
+            for issue in &report.health_issues {
+                println!("  [{}] {} ({}): {}", issue.severity, issue.title, issue.count, issue.description);
+            }
+            let findings = tuning_findings(&report);
+            if !findings.is_empty() {
+                println!();
+                print!("{}", format_table(&["category", "object", "detail"], &findings));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Nálezy tuningu jako řádky (kategorie, objekt, detail) pro tabulku a CSV
+fn tuning_findings(report: &TuningReport) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for q in &report.full_scan_queries {
+        let query: String = q.query.split_whitespace().collect::<Vec<_>>().join(" ");
+        rows.push(vec![
+            "full_scan".to_string(),
+            query,
+            format!("{} calls, {:.0} ms total, {} seq scans", q.calls, q.total_time_ms, q.seq_scans),
+        ]);
+    }
+    for t in &report.over_indexed_tables {
+        rows.push(vec![
+            "over_indexed".to_string(),
+            format!("{}.{}", t.schema, t.table),
+            format!("{} indexes, {} of indexes for {} table", t.index_count, t.total_index_size, t.table_size),
+        ]);
+    }
+    for t in &report.fragmented_tables {
+        rows.push(vec![
+            "fragmented_table".to_string(),
+            format!("{}.{}", t.schema, t.table),
+            format!("{:.1}% bloat, {} wasted of {}", t.bloat_pct, t.wasted_space, t.size),
+        ]);
+    }
+    for i in &report.fragmented_indexes {
+        rows.push(vec![
+            "fragmented_index".to_string(),
+            format!("{}.{}", i.schema, i.index),
+            format!("{:.1}% bloat of {} (table {})", i.bloat_pct, i.size, i.table),
+        ]);
+    }
+    rows
+}
+
+async fn export(state: &Arc<AppState>, args: ExportArgs) -> Result<()> {
+    let endpoint = resolve_endpoint(state, &args.endpoint).await?;
+    if let Some(output) = &args.output
+        && output.exists()
+    {
+        anyhow::bail!("{} already exists", output.display());
+    }
+    let target = match &args.target {
+        Some(key) => Some(resolve_target(state, key).await?),
+        None => None,
+    };
+    let req = ExportRequest {
+        scope: if args.tables.is_empty() { args.scope } else { "tables".to_string() },
+        format: args.format,
+        compress: args.compress,
+        include_ownership: args.include_ownership,
+        include_drop: args.clean,
+        include_create_db: args.create,
+        verbose: args.verbose,
+        exclude_patterns: if args.exclude_table_data.is_empty() { None } else { Some(args.exclude_table_data.join(",")) },
+        pg_version: None,
+        selected_tables: if args.tables.is_empty() { None } else { Some(args.tables) },
+        target_id: target.as_ref().map(|t| t.id),
+    };
+
+    let job_id = run_export(state, &endpoint, req).await?;
+    if let Some(user) = crate::auth::request_user() {
+        audit::record_job(state, &user, &endpoint, "export", &job_id).await;
+    }
+    let job = state.export_jobs.read().await.get(&job_id).cloned().context("Export job disappeared")?;
+    if args.verbose || matches!(job.status, JobStatus::Failed) {
+        for line in &job.logs {
+            eprintln!("{}", line);
+        }
+    }
+    if let Some(error) = job.error {
+        anyhow::bail!(error);
+    }
+    // Nahraný dump: vypíše se i umístění v bucketu, lokální kopie mohla být smazána (delete_local)
+    let remote = match (&target, state.db.as_ref()) {
+        (Some(target), Some(db)) => db
+            .get_job(&job_id)
+            .await?
+            .and_then(|job| job.remote_key)
+            .map(|key| format!("s3://{}/{}", target.bucket, key)),
+        _ => None,
+    };
+    if let Some(file_path) = job.file_path {
+        let destination = match args.output {
+            Some(output) => {
+                move_path(Path::new(&file_path), &output)?;
+                output
+            }
+            None => PathBuf::from(file_path),
+        };
+        println!("{}", destination.display());
+    } else if target.is_none() {
+        anyhow::bail!("Export did not produce a file");
+    }
+    match remote {
+        Some(remote) => println!("{}", remote),
+        None if target.is_some() => anyhow::bail!("Upload of the dump was not recorded"),
+        None => {}
+    }
+    Ok(())
+}
+
+async fn resolve_target(state: &Arc<AppState>, key: &str) -> Result<StorageTarget> {
+    let db = state.db.as_ref().context("Storage targets are not available in stateless mode")?;
+    let targets = db.get_storage_targets().await?;
+    targets
+        .into_iter()
+        .find(|t| t.name == key || t.id.to_string() == key)
+        .with_context(|| format!("Storage target '{}' not found", key))
+}
+
+/// Přesun dumpu (rename nejde mezi filesystémy, pak kopie; adresářový formát jen rename)
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to).with_context(|| format!("Failed to copy the dump to {}", to.display()))?;
+    std::fs::remove_file(from).ok();
+    Ok(())
+}
+
+async fn patroni_status(state: &Arc<AppState>, args: PatroniArgs) -> Result<()> {
+    let endpoint = match &args.endpoint {
+        Some(key) => Some(resolve_endpoint(state, key).await?),
+        None => None,
+    };
+    let urls = patroni_urls(state, endpoint.as_ref())
+        .context("Patroni monitoring is not enabled (use --enable-patroni and --patroni-urls)")?;
+    let nodes = fetch_cluster_status(&urls).await?;
+    if args.format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&nodes)?);
+        return Ok(());
+    }
+    for node in nodes.iter().filter(|n| !n.online) {
+        eprintln!("{}: {}", node.url, node.error.as_deref().unwrap_or("offline"));
+    }
+    // Členy clusteru vrací každý dostupný uzel, stačí první
+    let members = nodes
+        .iter()
+        .find(|n| n.online && !n.members_extended.is_empty())
+        .map(|n| n.members_extended.as_slice())
+        .context("No Patroni node is reachable")?;
+    print_rows(args.format, members, &["member", "host", "role", "state", "timeline", "lag"], |m, _| {
+        vec![
+            m.name.clone(),
+            format!("{}:{}", m.host, m.port),
+            m.role.clone(),
+            m.state.clone(),
+            m.timeline.map(|t| t.to_string()).unwrap_or_default(),
+            m.lag.map(|l| l.to_string()).unwrap_or_default(),
+        ]
+    })
+}
+
+fn size(bytes: i64, human: bool) -> String {
+    if human { bytes_to_human(bytes) } else { bytes.to_string() }
+}
+
+/// Výstup seznamu; `row` dostane true pro tabulku (velikosti čitelně), false pro CSV (v bajtech)
+fn print_rows<T: Serialize>(
+    format: OutputFormat,
+    items: &[T],
+    headers: &[&str],
+    row: impl Fn(&T, bool) -> Vec<String>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        OutputFormat::Csv => {
+            let rows: Vec<Vec<String>> = items.iter().map(|item| row(item, false)).collect();
+            print!("{}", format_csv(headers, &rows));
+        }
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = items.iter().map(|item| row(item, true)).collect();
+            print!("{}", format_table(headers, &rows));
+        }
+    }
+    Ok(())
+}
+
+fn format_csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = headers.join(",");
+    out.push('\n');
+    for row in rows {
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+    let line = |fields: Vec<&str>| {
+        let padded: Vec<String> = fields
+            .iter()
+            .zip(&widths)
+            .map(|(field, width)| format!("{:<width$}", field, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    let mut out = line(headers.to_vec());
+    out.push_str(&line(separator.iter().map(String::as_str).collect()));
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_and_csv_output() {
+        let rows = vec![
+            vec!["public".to_string(), "users".to_string()],
+            vec!["app".to_string(), "a,b".to_string()],
+        ];
+        assert_eq!(
+            format_table(&["schema", "table"], &rows),
+            "schema  table\n------  -----\npublic  users\napp     a,b\n"
+        );
+        assert_eq!(format_csv(&["schema", "table"], &rows), "schema,table\npublic,users\napp,\"a,b\"\n");
+    }
+}
@@ -1,8 +1,13 @@
+pub mod stateless;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
 use anyhow::{Context, Result};
+use base64::Engine;
 use rand::TryRngCore;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn get_app_dir() -> Result<PathBuf> {
     let base_dir = dirs::config_dir()
@@ -27,41 +32,158 @@ pub fn get_key_path() -> Result<PathBuf> {
     Ok(key_path)
 }
 
-pub fn load_or_create_key() -> Result<Vec<u8>> {
-    let key_path = get_key_path()?;
+/// Kontrolní hodnota, podle které poznáme špatnou master passphrase
+const PASSPHRASE_CHECK: &str = "postgres-explorer-master-key";
+pub const MIN_PASSPHRASE_LEN: usize = 8;
 
-    if key_path.exists() {
-        let key_hex = fs::read_to_string(&key_path)
-            .context("Failed to read encryption key")?;
-        let key_bytes = hex::decode(key_hex.trim())
-            .context("Failed to decode encryption key")?;
-        if key_bytes.len() != 32 {
-            return Err(anyhow::anyhow!("Encryption key must be 32 bytes"));
-        }
-        return Ok(key_bytes);
-    }
+/// Obsah db.key při použití master passphrase - místo klíče jen sůl pro KDF
+#[derive(Debug, Serialize, Deserialize)]
+struct PassphraseKeyFile {
+    /// argon2id
+    kdf: String,
+    salt: String,
+    /// PASSPHRASE_CHECK zašifrovaný odvozeným klíčem
+    check: String,
+}
 
+/// Odvodí AES klíč z master passphrase (Argon2id)
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
     let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive key from master passphrase: {}", e))?;
+    Ok(key)
+}
+
+/// AES-256-GCM, výsledek je base64(nonce + ciphertext)
+pub fn encrypt_secret(key: &[u8; 32], plaintext: &str) -> Result<String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut nonce_bytes = [0u8; 12];
     let mut rng = rand::rngs::OsRng;
-    rng.try_fill_bytes(&mut key)
-        .context("Failed to generate encryption key")?;
-    let key_hex = hex::encode(key);
+    rng.try_fill_bytes(&mut nonce_bytes)
+        .context("Failed to generate encryption nonce")?;
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt password"))?;
+
+    let mut payload = Vec::with_capacity(nonce_bytes.len() + ciphertext.len());
+    payload.extend_from_slice(&nonce_bytes);
+    payload.extend_from_slice(&ciphertext);
+    Ok(base64::prelude::BASE64_STANDARD.encode(payload))
+}
+
+pub fn decrypt_secret(key: &[u8; 32], encrypted: &str) -> Result<String> {
+    let payload = base64::prelude::BASE64_STANDARD
+        .decode(encrypted)
+        .context("Failed to decode encrypted password")?;
+    if payload.len() < 12 {
+        return Err(anyhow::anyhow!("Encrypted password payload is too short"));
+    }
+    let (nonce_bytes, ciphertext) = payload.split_at(12);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Nonce::from_slice(nonce_bytes);
+    let plaintext = cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt password"))?;
+    let password = String::from_utf8(plaintext)
+        .context("Decrypted password is not valid UTF-8")?;
+    Ok(password)
+}
+
+/// Vygeneruje nový klíč a obsah db.key (hex klíč, nebo se passphrase jen sůl a kontrolní hodnotu)
+pub fn generate_key(passphrase: Option<&str>) -> Result<([u8; 32], String)> {
+    let mut rng = rand::rngs::OsRng;
+    match passphrase {
+        Some(passphrase) => {
+            if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+                anyhow::bail!("Master passphrase must have at least {} characters", MIN_PASSPHRASE_LEN);
+            }
+            let mut salt = [0u8; 16];
+            rng.try_fill_bytes(&mut salt)
+                .context("Failed to generate salt")?;
+            let key = derive_key(passphrase, &salt)?;
+            let file = PassphraseKeyFile {
+                kdf: "argon2id".to_string(),
+                salt: hex::encode(salt),
+                check: encrypt_secret(&key, PASSPHRASE_CHECK)?,
+            };
+            Ok((key, serde_json::to_string_pretty(&file)?))
+        }
+        None => {
+            let mut key = [0u8; 32];
+            rng.try_fill_bytes(&mut key)
+                .context("Failed to generate encryption key")?;
+            let key_hex = hex::encode(key);
+            Ok((key, key_hex))
+        }
+    }
+}
 
-    let mut file = fs::File::create(&key_path)
-        .context("Failed to create encryption key file")?;
-    file.write_all(key_hex.as_bytes())
+/// Zapíše soubor s klíčem (jen pro vlastníka). Vzniká rovnou s 0600 jako dočasný soubor
+/// a přejmenuje se, takže klíč nikdy neleží na disku s právy podle umask.
+pub fn write_key_file(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .context("Invalid encryption key path")?
+        .to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4().simple()));
+    crate::utils::runtime::write_new(&tmp, content.as_bytes(), 0o600)
+        .context("Failed to write encryption key")?;
+    fs::rename(&tmp, path)
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
         .context("Failed to write encryption key")?;
+    Ok(())
+}
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = fs::Permissions::from_mode(0o600);
-        fs::set_permissions(&key_path, perms)
-            .context("Failed to set encryption key permissions")?;
+/// Načte klíč z db.key; soubor chráněný passphrase vyžaduje --master-passphrase
+pub fn load_key(passphrase: Option<&str>) -> Result<[u8; 32]> {
+    let key_path = get_key_path()?;
+    let content = fs::read_to_string(&key_path)
+        .with_context(|| format!("Failed to read encryption key {}", key_path.display()))?;
+
+    if content.trim_start().starts_with('{') {
+        let file: PassphraseKeyFile = serde_json::from_str(&content)
+            .context("Failed to parse passphrase-protected encryption key")?;
+        if file.kdf != "argon2id" {
+            anyhow::bail!("Unsupported key derivation {}", file.kdf);
+        }
+        let passphrase = passphrase.context(
+            "Encryption key is protected by a master passphrase, set --master-passphrase or MASTER_PASSPHRASE",
+        )?;
+        let salt = hex::decode(&file.salt).context("Failed to decode key salt")?;
+        let key = derive_key(passphrase, &salt)?;
+        if decrypt_secret(&key, &file.check).ok().as_deref() != Some(PASSPHRASE_CHECK) {
+            anyhow::bail!("Wrong master passphrase");
+        }
+        return Ok(key);
     }
 
+    if passphrase.is_some() {
+        anyhow::bail!(
+            "Encryption key {} is not protected by a passphrase, run `postgres-explorer rotate-key --new-passphrase ...` first",
+            key_path.display()
+        );
+    }
+    let key_bytes = hex::decode(content.trim())
+        .context("Failed to decode encryption key")?;
+    key_bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Encryption key must be 32 bytes"))
+}
+
+pub fn load_or_create_key(passphrase: Option<&str>) -> Result<[u8; 32]> {
+    let key_path = get_key_path()?;
+    if key_path.exists() {
+        return load_key(passphrase);
+    }
+
+    let (key, content) = generate_key(passphrase)?;
+    write_key_file(&key_path, &content)?;
     tracing::info!("Created encryption key: {}", key_path.display());
-    Ok(key.to_vec())
+    Ok(key)
 }
 
 pub fn init_directories() -> Result<()> {
@@ -72,7 +194,5 @@ pub fn init_directories() -> Result<()> {
             .context("Failed to create data directory")?;
         tracing::info!("Created data directory: {}", data_dir.display());
     }
-
-    let _ = load_or_create_key()?;
     Ok(())
 }
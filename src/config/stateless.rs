@@ -0,0 +1,223 @@
+//! Deklarativní konfigurace endpointů pro --stateless režim (TOML nebo YAML)
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::db::models::{Endpoint, ENVIRONMENTS};
+
+/// Endpoint ze stateless konfigurace včetně tajných údajů (nikam se neukládají)
+#[derive(Debug, Clone)]
+pub struct StatelessEndpoint {
+    pub endpoint: Endpoint,
+    pub password: Option<String>,
+    pub ssh_passphrase: Option<String>,
+    pub ssl_key: Option<String>,
+    /// Patroni REST API clusteru (jinak se použijí globální --patroni-urls)
+    pub patroni_urls: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatelessConfig {
+    /// Výchozí Patroni URL pro endpointy bez vlastních
+    #[serde(default)]
+    pub patroni_urls: Vec<String>,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointConfig {
+    /// Stabilní ID (cookie s vybraným endpointem); výchozí je pořadí od 1
+    pub id: Option<i64>,
+    pub name: String,
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub ssl_mode: Option<String>,
+    #[serde(default)]
+    pub insecure: bool,
+    pub search_path: Option<String>,
+    #[serde(default)]
+    pub enable_blueprint: bool,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub environment: Option<String>,
+    pub ssl_root_cert: Option<String>,
+    pub ssl_cert: Option<String>,
+    pub ssl_key: Option<Secret>,
+    pub ssh_host: Option<String>,
+    pub ssh_port: Option<u16>,
+    pub ssh_user: Option<String>,
+    pub ssh_key_path: Option<String>,
+    pub ssh_passphrase: Option<Secret>,
+    #[serde(default)]
+    pub patroni_urls: Vec<String>,
+    /// Read repliky (connection stringy), na které jdou čtecí dotazy
+    #[serde(default)]
+    pub replica_urls: Vec<String>,
+    pub replica_max_lag: Option<u32>,
+}
+
+/// Tajná hodnota: přímo (`"..."`), z proměnné prostředí (`{ env = "PG_PASSWORD" }`)
+/// nebo ze souboru (`{ file = "/run/secrets/pg" }`, např. Kubernetes secret)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Value(String),
+    Env { env: String },
+    File { file: String },
+}
+
+impl Secret {
+    fn resolve(&self) -> Result<String> {
+        match self {
+            Secret::Value(value) => Ok(value.clone()),
+            Secret::Env { env } => {
+                std::env::var(env).with_context(|| format!("Environment variable {} is not set", env))
+            }
+            Secret::File { file } => std::fs::read_to_string(file)
+                // Soubory ze secretů obvykle končí novým řádkem
+                .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+                .with_context(|| format!("Failed to read secret file {}", file)),
+        }
+    }
+}
+
+/// Ověří connection stringy replik a spojí je do formátu Endpoint::replica_urls (řádek na repliku)
+pub fn join_replicas(urls: &[String], context: &str) -> Result<Option<String>> {
+    let urls: Vec<&str> = urls.iter().map(|u| u.trim()).filter(|u| !u.is_empty()).collect();
+    for url in &urls {
+        crate::handlers::conninfo::ConnInfo::parse(url)
+            .with_context(|| format!("{}: invalid replica url {}", context, url))?;
+    }
+    Ok(if urls.is_empty() { None } else { Some(urls.join("\n")) })
+}
+
+/// Načte konfiguraci podle přípony (.toml, .yaml/.yml)
+pub fn load(path: &Path) -> Result<StatelessConfig> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let config: StatelessConfig = match extension.as_str() {
+        "yaml" | "yml" => serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid YAML in {}", path.display()))?,
+        "toml" => toml::from_str(&content).with_context(|| format!("Invalid TOML in {}", path.display()))?,
+        _ => anyhow::bail!("Unsupported config file {} (expected .toml, .yaml or .yml)", path.display()),
+    };
+    if config.endpoints.is_empty() {
+        anyhow::bail!("Config file {} does not define any endpoints", path.display());
+    }
+    Ok(config)
+}
+
+impl StatelessConfig {
+    /// Převede konfiguraci na endpointy (ověří URL, prostředí a duplicity, načte tajné údaje)
+    pub fn into_endpoints(self) -> Result<Vec<StatelessEndpoint>> {
+        let mut endpoints: Vec<StatelessEndpoint> = Vec::with_capacity(self.endpoints.len());
+        for (index, item) in self.endpoints.into_iter().enumerate() {
+            let id = item.id.unwrap_or(index as i64 + 1);
+            let context = format!("Endpoint '{}'", item.name);
+            if item.name.trim().is_empty() {
+                anyhow::bail!("Endpoint #{} has an empty name", index + 1);
+            }
+            if endpoints.iter().any(|e| e.endpoint.id == id) {
+                anyhow::bail!("{}: duplicate id {}", context, id);
+            }
+            if endpoints.iter().any(|e| e.endpoint.name == item.name) {
+                anyhow::bail!("{}: duplicate name", context);
+            }
+            crate::handlers::conninfo::ConnInfo::parse(&item.url)
+                .with_context(|| format!("{}: invalid url", context))?;
+            if let Some(env) = &item.environment
+                && !ENVIRONMENTS.contains(&env.as_str())
+            {
+                anyhow::bail!("{}: environment must be one of {}", context, ENVIRONMENTS.join(", "));
+            }
+            let resolve = |secret: &Option<Secret>| -> Result<Option<String>> {
+                secret.as_ref().map(Secret::resolve).transpose().with_context(|| context.clone())
+            };
+            let tags: Vec<String> = item.tags.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+
+            endpoints.push(StatelessEndpoint {
+                password: resolve(&item.password)?,
+                ssh_passphrase: resolve(&item.ssh_passphrase)?,
+                ssl_key: resolve(&item.ssl_key)?,
+                patroni_urls: if item.patroni_urls.is_empty() { self.patroni_urls.clone() } else { item.patroni_urls },
+                endpoint: Endpoint {
+                    id,
+                    name: item.name,
+                    url: item.url,
+                    insecure: item.insecure,
+                    username: item.username,
+                    password_encrypted: None,
+                    ssl_mode: item.ssl_mode,
+                    search_path: item.search_path,
+                    enable_blueprint: item.enable_blueprint,
+                    ssh_host: item.ssh_host,
+                    ssh_port: item.ssh_port.map(i64::from),
+                    ssh_user: item.ssh_user,
+                    ssh_key_path: item.ssh_key_path,
+                    ssh_passphrase_encrypted: None,
+                    ssl_root_cert: item.ssl_root_cert,
+                    ssl_cert: item.ssl_cert,
+                    ssl_key_encrypted: None,
+                    group_name: item.group,
+                    tags: if tags.is_empty() { None } else { Some(tags.join(",")) },
+                    environment: item.environment,
+                    replica_urls: join_replicas(&item.replica_urls, &context)?,
+                    replica_max_lag: item.replica_max_lag.map(i64::from),
+                    created_at: String::new(),
+                    updated_at: String::new(),
+                },
+            });
+        }
+        Ok(endpoints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_yaml_configs() {
+        let toml_config: StatelessConfig = toml::from_str(
+            r#"
+            patroni_urls = ["http://patroni:8008"]
+
+            [[endpoints]]
+            name = "billing"
+            url = "postgres://db1:5432/billing"
+            password = { env = "PATH" }
+            environment = "prod"
+            tags = ["eu", " reporting "]
+
+            [[endpoints]]
+            id = 10
+            name = "local"
+            url = "host=/tmp dbname=postgres"
+            password = "inline"
+            enable_blueprint = true
+            patroni_urls = []
+            "#,
+        )
+        .unwrap();
+        let endpoints = toml_config.into_endpoints().unwrap();
+        assert_eq!(endpoints[0].endpoint.id, 1);
+        assert_eq!(endpoints[0].password, std::env::var("PATH").ok());
+        assert_eq!(endpoints[0].endpoint.tags.as_deref(), Some("eu,reporting"));
+        assert_eq!(endpoints[0].patroni_urls, vec!["http://patroni:8008".to_string()]);
+        assert_eq!(endpoints[1].endpoint.id, 10);
+        assert!(endpoints[1].endpoint.enable_blueprint);
+
+        let yaml_config: StatelessConfig = serde_yaml::from_str(
+            "endpoints:\n  - name: a\n    url: postgres://a/db\n  - name: a\n    url: postgres://b/db\n",
+        )
+        .unwrap();
+        assert!(yaml_config.into_endpoints().is_err());
+    }
+}
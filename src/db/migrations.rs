@@ -0,0 +1,229 @@
+//! Verzované migrace lokální SQLite databáze (tabulka schema_migrations)
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Všechny migrace ve vzestupném pořadí; jednou aplikovaný soubor se už nesmí měnit
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "init", sql: include_str!("../../migrations/001_init.sql") },
+    Migration { version: 2, name: "add_password_encrypted", sql: include_str!("../../migrations/002_add_password_encrypted.sql") },
+    Migration { version: 3, name: "add_ssl_and_search_path", sql: include_str!("../../migrations/003_add_ssl_and_search_path.sql") },
+    Migration { version: 4, name: "add_enable_blueprint", sql: include_str!("../../migrations/004_add_enable_blueprint.sql") },
+    Migration { version: 5, name: "add_query_history", sql: include_str!("../../migrations/005_add_query_history.sql") },
+    Migration { version: 6, name: "add_users", sql: include_str!("../../migrations/006_add_users.sql") },
+    Migration { version: 7, name: "add_endpoint_roles", sql: include_str!("../../migrations/007_add_endpoint_roles.sql") },
+    Migration { version: 8, name: "add_audit_log", sql: include_str!("../../migrations/008_add_audit_log.sql") },
+    Migration { version: 9, name: "add_ssh_tunnel", sql: include_str!("../../migrations/009_add_ssh_tunnel.sql") },
+    Migration { version: 10, name: "add_ssl_certs", sql: include_str!("../../migrations/010_add_ssl_certs.sql") },
+    Migration { version: 11, name: "add_endpoint_groups", sql: include_str!("../../migrations/011_add_endpoint_groups.sql") },
+    Migration { version: 12, name: "add_replicas", sql: include_str!("../../migrations/012_add_replicas.sql") },
+    Migration { version: 13, name: "add_api_tokens", sql: include_str!("../../migrations/013_add_api_tokens.sql") },
+    Migration { version: 14, name: "add_jobs", sql: include_str!("../../migrations/014_add_jobs.sql") },
+    Migration { version: 15, name: "add_export_schedules", sql: include_str!("../../migrations/015_add_export_schedules.sql") },
+    Migration { version: 16, name: "add_storage_targets", sql: include_str!("../../migrations/016_add_storage_targets.sql") },
+];
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.sql.as_bytes()))
+    }
+}
+
+/// Aplikuje chybějící migrace, každou ve vlastní transakci
+pub async fn run(pool: &SqlitePool) -> Result<()> {
+    let has_table = table_exists(pool, "schema_migrations").await?;
+    sqlx::raw_sql(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create schema_migrations table")?;
+    if !has_table {
+        adopt_legacy_schema(pool).await?;
+    }
+
+    let applied = sqlx::query("SELECT version, name, checksum FROM schema_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await
+        .context("Failed to read schema_migrations")?;
+    let latest = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+    for row in &applied {
+        let version: i64 = row.get("version");
+        let checksum: String = row.get("checksum");
+        match MIGRATIONS.iter().find(|m| m.version == version) {
+            Some(migration) if migration.checksum() != checksum => anyhow::bail!(
+                "Migration {:03}_{} was changed after it had been applied (checksum mismatch)",
+                version,
+                migration.name
+            ),
+            Some(_) => {}
+            None => anyhow::bail!(
+                "The local database has schema version {} ({}), but this binary only knows migrations up to {}. \
+                 Upgrade postgres-explorer or restore a backup of the database.",
+                version,
+                row.get::<String, _>("name"),
+                latest
+            ),
+        }
+    }
+
+    for migration in MIGRATIONS {
+        if applied.iter().any(|row| row.get::<i64, _>("version") == migration.version) {
+            continue;
+        }
+        // 001 už sloupec password_encrypted zakládá, 002 je jen pro databáze ze starého 001
+        if migration.version == 2 && column_exists(pool, "endpoints", "password_encrypted").await? {
+            record(&mut *pool.acquire().await?, migration).await?;
+            continue;
+        }
+        tracing::info!("Applying migration {:03}_{}", migration.version, migration.name);
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to run migration {:03}_{}", migration.version, migration.name))?;
+        record(&mut tx, migration).await?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Failed to commit migration {:03}", migration.version))?;
+    }
+    Ok(())
+}
+
+async fn record(conn: &mut sqlx::SqliteConnection, migration: &Migration) -> Result<()> {
+    sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES (?, ?, ?)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(migration.checksum())
+        .execute(conn)
+        .await
+        .context("Failed to record migration")?;
+    Ok(())
+}
+
+/// Databáze z verzí před schema_migrations: aplikované migrace poznáme podle schématu
+/// (stejné kontroly jako dřív v run_migrations) a jen je zapíšeme
+async fn adopt_legacy_schema(pool: &SqlitePool) -> Result<()> {
+    if !table_exists(pool, "endpoints").await? {
+        return Ok(());
+    }
+    let mut adopted = Vec::new();
+    for migration in MIGRATIONS {
+        let applied = match migration.version {
+            1 => true,
+            2 => column_exists(pool, "endpoints", "password_encrypted").await?,
+            3 => column_exists(pool, "endpoints", "search_path").await?,
+            4 => column_exists(pool, "endpoints", "enable_blueprint").await?,
+            5 => table_exists(pool, "query_history").await?,
+            6 => table_exists(pool, "users").await?,
+            7 => column_exists(pool, "users", "groups").await?,
+            8 => table_exists(pool, "audit_log").await?,
+            9 => column_exists(pool, "endpoints", "ssh_host").await?,
+            10 => column_exists(pool, "endpoints", "ssl_root_cert").await?,
+            11 => column_exists(pool, "endpoints", "group_name").await?,
+            _ => false,
+        };
+        // Starší migrace mohly chybět jen v neúplném schématu, novější určitě neběžely
+        if !applied {
+            break;
+        }
+        adopted.push(migration);
+    }
+
+    let mut tx = pool.begin().await?;
+    for migration in &adopted {
+        record(&mut tx, migration).await?;
+    }
+    tx.commit().await.context("Failed to record existing migrations")?;
+    tracing::info!(
+        "Existing database adopted into schema_migrations (up to version {})",
+        adopted.last().map(|m| m.version).unwrap_or(0)
+    );
+    Ok(())
+}
+
+async fn table_exists(pool: &SqlitePool, table: &str) -> Result<bool> {
+    let row = sqlx::query("SELECT count(*) AS n FROM sqlite_master WHERE type = 'table' AND name = ?")
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .context("Failed to inspect database schema")?;
+    Ok(row.get::<i64, _>("n") > 0)
+}
+
+async fn column_exists(pool: &SqlitePool, table: &str, column: &str) -> Result<bool> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to inspect {} schema", table))?;
+    Ok(columns.iter().any(|row| row.get::<String, _>("name") == column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn versions(pool: &SqlitePool) -> Vec<i64> {
+        sqlx::query("SELECT version FROM schema_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get("version"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fresh_and_legacy_databases() {
+        let all: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+
+        let fresh = memory_pool().await;
+        run(&fresh).await.unwrap();
+        run(&fresh).await.unwrap();
+        assert_eq!(versions(&fresh).await, all);
+        assert!(column_exists(&fresh, "endpoints", "group_name").await.unwrap());
+        assert!(table_exists(&fresh, "jobs").await.unwrap());
+        assert!(column_exists(&fresh, "jobs", "schedule_id").await.unwrap());
+        assert!(table_exists(&fresh, "storage_targets").await.unwrap());
+
+        // Instalace na úrovni 005 bez schema_migrations
+        let legacy = memory_pool().await;
+        // (stejně jako dřívější run_migrations: 002 jen pokud sloupec v 001 chybí)
+        for migration in MIGRATIONS[..5].iter().filter(|m| m.version != 2) {
+            sqlx::raw_sql(migration.sql).execute(&legacy).await.unwrap();
+        }
+        run(&legacy).await.unwrap();
+        assert_eq!(versions(&legacy).await, all);
+        assert!(table_exists(&legacy, "audit_log").await.unwrap());
+        assert!(column_exists(&legacy, "endpoints", "password_encrypted").await.unwrap());
+
+        sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES (999, 'future', '')")
+            .execute(&legacy)
+            .await
+            .unwrap();
+        let err = run(&legacy).await.unwrap_err().to_string();
+        assert!(err.contains("schema version 999"), "{}", err);
+    }
+}
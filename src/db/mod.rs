@@ -1,16 +1,16 @@
+mod migrations;
 pub mod models;
 
 use anyhow::{Context, Result};
-use aes_gcm::aead::{Aead, KeyInit};
-use aes_gcm::{Aes256Gcm, Key, Nonce};
-use rand::TryRngCore;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
-use sqlx::Row;
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row};
 use std::str::FromStr;
-use base64::Engine;
 
 use crate::config;
-use crate::db::models::{CreateEndpoint, Endpoint, UpdateEndpoint};
+use crate::db::models::{
+    ApiToken, AuditEntry, AuditFilter, CreateEndpoint, Endpoint, EndpointRole, ExportSchedule, JobFilter, JobRecord,
+    NewApiToken, NewAuditEntry, NewExportSchedule, NewJob, NewStorageTarget, StorageTarget, UpdateEndpoint, User,
+};
 
 #[derive(Clone)]
 pub struct Database {
@@ -19,7 +19,7 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(encryption_key: [u8; 32]) -> Result<Self> {
         let db_path = config::get_db_path()?;
         let db_url = format!("sqlite://{}", db_path.display());
 
@@ -36,109 +36,26 @@ impl Database {
 
         Self::run_migrations(&pool).await?;
 
-        let encryption_key = config::load_or_create_key()?
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("Invalid encryption key length"))?;
-
         Ok(Self { pool, encryption_key })
     }
 
     async fn run_migrations(pool: &SqlitePool) -> Result<()> {
         tracing::info!("Running database migrations...");
-
-        let migration_001 = include_str!("../../migrations/001_init.sql");
-        sqlx::raw_sql(migration_001)
-            .execute(pool)
-            .await
-            .context("Failed to run migration 001")?;
-
-        let columns = sqlx::query("PRAGMA table_info(endpoints)")
-            .fetch_all(pool)
-            .await
-            .context("Failed to inspect endpoints schema")?;
-        let mut has_encrypted_column = false;
-        for row in &columns {
-            let name: String = row.get("name");
-            if name == "password_encrypted" {
-                has_encrypted_column = true;
-            }
-        }
-
-        if !has_encrypted_column {
-            let migration_002 = include_str!("../../migrations/002_add_password_encrypted.sql");
-            sqlx::raw_sql(migration_002)
-                .execute(pool)
-                .await
-                .context("Failed to run migration 002")?;
-        }
-
-        // Check if ssl_mode and search_path columns exist
-        let columns = sqlx::query("PRAGMA table_info(endpoints)")
-            .fetch_all(pool)
-            .await
-            .context("Failed to inspect endpoints schema")?;
-        let mut has_ssl_mode = false;
-        let mut has_search_path = false;
-        for row in &columns {
-            let name: String = row.get("name");
-            if name == "ssl_mode" {
-                has_ssl_mode = true;
-            }
-            if name == "search_path" {
-                has_search_path = true;
-            }
-        }
-
-        if !has_ssl_mode || !has_search_path {
-            let migration_003 = include_str!("../../migrations/003_add_ssl_and_search_path.sql");
-            sqlx::raw_sql(migration_003)
-                .execute(pool)
-                .await
-                .context("Failed to run migration 003")?;
-        }
-
-        // Check if enable_blueprint column exists
-        let columns = sqlx::query("PRAGMA table_info(endpoints)")
-            .fetch_all(pool)
-            .await
-            .context("Failed to inspect endpoints schema")?;
-        let mut has_enable_blueprint = false;
-        for row in &columns {
-            let name: String = row.get("name");
-            if name == "enable_blueprint" {
-                has_enable_blueprint = true;
-            }
-        }
-
-        if !has_enable_blueprint {
-            let migration_004 = include_str!("../../migrations/004_add_enable_blueprint.sql");
-            sqlx::raw_sql(migration_004)
-                .execute(pool)
-                .await
-                .context("Failed to run migration 004")?;
-        }
-
-        // Check if query_history table exists
-        let tables = sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='query_history'")
-            .fetch_all(pool)
-            .await
-            .context("Failed to check for query_history table")?;
-
-        if tables.is_empty() {
-            let migration_005 = include_str!("../../migrations/005_add_query_history.sql");
-            sqlx::raw_sql(migration_005)
-                .execute(pool)
-                .await
-                .context("Failed to run migration 005")?;
-        }
-
+        migrations::run(pool).await?;
         tracing::info!("Migrations completed successfully");
         Ok(())
     }
 
+    /// Readiness: SQLite odpovídá a jde zamknout pro zápis (transakce se hned vrátí)
+    pub async fn check_writable(&self) -> Result<()> {
+        let tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        tx.rollback().await?;
+        Ok(())
+    }
+
     pub async fn get_endpoints(&self) -> Result<Vec<Endpoint>> {
         let endpoints = sqlx::query_as::<_, Endpoint>(
-            "SELECT * FROM endpoints ORDER BY name"
+            "SELECT * FROM endpoints ORDER BY COALESCE(group_name, ''), name"
         )
         .fetch_all(&self.pool)
         .await
@@ -163,8 +80,10 @@ impl Database {
         let mut tx = self.pool.begin().await?;
 
         let result = sqlx::query(
-            "INSERT INTO endpoints (name, url, insecure, username, password_encrypted, ssl_mode, search_path, enable_blueprint)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO endpoints (name, url, insecure, username, password_encrypted, ssl_mode, search_path, enable_blueprint,
+                                    ssh_host, ssh_port, ssh_user, ssh_key_path, ssl_root_cert, ssl_cert,
+                                    group_name, tags, environment, replica_urls, replica_max_lag)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )
         .bind(&endpoint.name)
         .bind(&endpoint.url)
@@ -174,6 +93,17 @@ impl Database {
         .bind(&endpoint.ssl_mode)
         .bind(&endpoint.search_path)
         .bind(endpoint.enable_blueprint)
+        .bind(&endpoint.ssh_host)
+        .bind(endpoint.ssh_port)
+        .bind(&endpoint.ssh_user)
+        .bind(&endpoint.ssh_key_path)
+        .bind(&endpoint.ssl_root_cert)
+        .bind(&endpoint.ssl_cert)
+        .bind(&endpoint.group_name)
+        .bind(&endpoint.tags)
+        .bind(&endpoint.environment)
+        .bind(&endpoint.replica_urls)
+        .bind(endpoint.replica_max_lag)
         .execute(&mut *tx)
         .await
         .context("Failed to insert endpoint")?;
@@ -189,6 +119,25 @@ impl Database {
                 .await?;
         }
 
+        let ssh_enabled = endpoint.ssh_host.as_deref().is_some_and(|h| !h.trim().is_empty());
+        if let Some(passphrase) = endpoint.ssh_passphrase.filter(|_| ssh_enabled) {
+            let encrypted = self.encrypt_password(&passphrase)?;
+            sqlx::query("UPDATE endpoints SET ssh_passphrase_encrypted = ? WHERE id = ?")
+                .bind(&encrypted)
+                .bind(endpoint_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if let Some(key) = endpoint.ssl_key {
+            let encrypted = self.encrypt_password(&key)?;
+            sqlx::query("UPDATE endpoints SET ssl_key_encrypted = ? WHERE id = ?")
+                .bind(&encrypted)
+                .bind(endpoint_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
         tx.commit().await?;
 
         tracing::info!("Created endpoint: {} (id: {})", endpoint.name, endpoint_id);
@@ -211,10 +160,15 @@ impl Database {
         let name = endpoint.name.context("Missing endpoint name")?;
         let url = endpoint.url.context("Missing endpoint url")?;
         let insecure = endpoint.insecure.context("Missing endpoint insecure flag")?;
+        // Bez SSH tunelu nemá smysl držet passphrase ke klíči
+        let ssh_enabled = endpoint.ssh_host.as_deref().is_some_and(|h| !h.trim().is_empty());
 
         sqlx::query(
             "UPDATE endpoints
-             SET name = ?, url = ?, insecure = ?, username = ?, ssl_mode = ?, search_path = ?, enable_blueprint = ?, updated_at = CURRENT_TIMESTAMP
+             SET name = ?, url = ?, insecure = ?, username = ?, ssl_mode = ?, search_path = ?, enable_blueprint = ?,
+                 ssh_host = ?, ssh_port = ?, ssh_user = ?, ssh_key_path = ?, ssl_root_cert = ?, ssl_cert = ?,
+                 group_name = ?, tags = ?, environment = ?, replica_urls = ?, replica_max_lag = ?,
+                 updated_at = CURRENT_TIMESTAMP
              WHERE id = ?"
         )
         .bind(name)
@@ -224,6 +178,17 @@ impl Database {
         .bind(endpoint.ssl_mode)
         .bind(endpoint.search_path)
         .bind(endpoint.enable_blueprint.unwrap_or(false))
+        .bind(endpoint.ssh_host)
+        .bind(endpoint.ssh_port)
+        .bind(endpoint.ssh_user)
+        .bind(endpoint.ssh_key_path)
+        .bind(endpoint.ssl_root_cert)
+        .bind(endpoint.ssl_cert)
+        .bind(endpoint.group_name)
+        .bind(endpoint.tags)
+        .bind(endpoint.environment)
+        .bind(endpoint.replica_urls)
+        .bind(endpoint.replica_max_lag)
         .bind(id)
         .execute(&mut *tx)
         .await
@@ -239,6 +204,42 @@ impl Database {
                 .context("Failed to update endpoint password")?;
         }
 
+        match endpoint.ssh_passphrase.filter(|_| ssh_enabled) {
+            Some(passphrase) => {
+                let encrypted = self.encrypt_password(&passphrase)?;
+                sqlx::query("UPDATE endpoints SET ssh_passphrase_encrypted = ? WHERE id = ?")
+                    .bind(&encrypted)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await
+                    .context("Failed to update endpoint SSH passphrase")?;
+            }
+            None if endpoint.clear_ssh_passphrase || !ssh_enabled => {
+                sqlx::query("UPDATE endpoints SET ssh_passphrase_encrypted = NULL WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await
+                    .context("Failed to clear endpoint SSH passphrase")?;
+            }
+            None => {}
+        }
+
+        if let Some(key) = endpoint.ssl_key {
+            let encrypted = self.encrypt_password(&key)?;
+            sqlx::query("UPDATE endpoints SET ssl_key_encrypted = ? WHERE id = ?")
+                .bind(&encrypted)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to update endpoint TLS client key")?;
+        } else if endpoint.clear_ssl_key {
+            sqlx::query("UPDATE endpoints SET ssl_key_encrypted = NULL WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to clear endpoint TLS client key")?;
+        }
+
         tx.commit().await?;
         tracing::info!("Updated endpoint: {}", id);
         Ok(())
@@ -260,39 +261,101 @@ impl Database {
         None
     }
 
+    pub async fn get_endpoint_ssl_key(&self, endpoint: &Endpoint) -> Option<String> {
+        let encrypted = endpoint.ssl_key_encrypted.as_ref()?;
+        match self.decrypt_password(encrypted) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to decrypt TLS client key for endpoint {}: {}",
+                    endpoint.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn get_endpoint_ssh_passphrase(&self, endpoint: &Endpoint) -> Option<String> {
+        let encrypted = endpoint.ssh_passphrase_encrypted.as_ref()?;
+        match self.decrypt_password(encrypted) {
+            Ok(passphrase) => Some(passphrase),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to decrypt SSH passphrase for endpoint {}: {}",
+                    endpoint.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
     fn encrypt_password(&self, password: &str) -> Result<String> {
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.encryption_key));
-        let mut nonce_bytes = [0u8; 12];
-        let mut rng = rand::rngs::OsRng;
-        rng.try_fill_bytes(&mut nonce_bytes)
-            .context("Failed to generate encryption nonce")?;
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        let ciphertext = cipher
-            .encrypt(nonce, password.as_bytes())
-            .map_err(|_| anyhow::anyhow!("Failed to encrypt password"))?;
-
-        let mut payload = Vec::with_capacity(nonce_bytes.len() + ciphertext.len());
-        payload.extend_from_slice(&nonce_bytes);
-        payload.extend_from_slice(&ciphertext);
-        Ok(base64::prelude::BASE64_STANDARD.encode(payload))
+        config::encrypt_secret(&self.encryption_key, password)
     }
 
     fn decrypt_password(&self, encrypted: &str) -> Result<String> {
-        let payload = base64::prelude::BASE64_STANDARD
-            .decode(encrypted)
-            .context("Failed to decode encrypted password")?;
-        if payload.len() < 12 {
-            return Err(anyhow::anyhow!("Encrypted password payload is too short"));
+        config::decrypt_secret(&self.encryption_key, encrypted)
+    }
+
+    /// Přešifruje všechny tajné údaje endpointů a cílů úložiště novým klíčem v jedné transakci.
+    /// `before_commit` se volá těsně před commitem (zápis nového klíče); při chybě se nic nezmění.
+    pub async fn rotate_encryption_key(
+        &self,
+        new_key: &[u8; 32],
+        before_commit: impl FnOnce() -> Result<()>,
+    ) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query(
+            "SELECT id, name, password_encrypted, ssh_passphrase_encrypted, ssl_key_encrypted FROM endpoints"
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch endpoints")?;
+
+        let mut count = 0;
+        for row in &rows {
+            let id: i64 = row.get("id");
+            let name: String = row.get("name");
+            for column in ["password_encrypted", "ssh_passphrase_encrypted", "ssl_key_encrypted"] {
+                let Some(encrypted) = row.get::<Option<String>, _>(column) else {
+                    continue;
+                };
+                // Nedešifrovatelnou hodnotu nezahazujeme, rotace se celá zruší
+                let plaintext = self
+                    .decrypt_password(&encrypted)
+                    .with_context(|| format!("Failed to decrypt {} of endpoint '{}' with the current key", column, name))?;
+                let reencrypted = config::encrypt_secret(new_key, &plaintext)?;
+                sqlx::query(&format!("UPDATE endpoints SET {} = ? WHERE id = ?", column))
+                    .bind(&reencrypted)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                count += 1;
+            }
         }
-        let (nonce_bytes, ciphertext) = payload.split_at(12);
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.encryption_key));
-        let nonce = Nonce::from_slice(nonce_bytes);
-        let plaintext = cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|_| anyhow::anyhow!("Failed to decrypt password"))?;
-        let password = String::from_utf8(plaintext)
-            .context("Decrypted password is not valid UTF-8")?;
-        Ok(password)
+
+        let targets = sqlx::query("SELECT id, name, secret_key_encrypted FROM storage_targets")
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to fetch storage targets")?;
+        for row in &targets {
+            let name: String = row.get("name");
+            let plaintext = self
+                .decrypt_password(row.get("secret_key_encrypted"))
+                .with_context(|| format!("Failed to decrypt the secret key of storage target '{}' with the current key", name))?;
+            sqlx::query("UPDATE storage_targets SET secret_key_encrypted = ? WHERE id = ?")
+                .bind(config::encrypt_secret(new_key, &plaintext)?)
+                .bind(row.get::<i64, _>("id"))
+                .execute(&mut *tx)
+                .await?;
+            count += 1;
+        }
+
+        before_commit()?;
+        tx.commit().await.context("Failed to commit re-encrypted secrets")?;
+        Ok(count)
     }
 
     // Query history methods
@@ -361,4 +424,634 @@ impl Database {
 
         Ok(())
     }
+
+    // User & session methods
+    pub async fn count_users(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count users")?;
+        Ok(count)
+    }
+
+    pub async fn get_users(&self) -> Result<Vec<User>> {
+        let users = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY username")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch users")?;
+        Ok(users)
+    }
+
+    pub async fn get_user(&self, id: i64) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch user")?;
+        Ok(user)
+    }
+
+    pub async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch user")?;
+        Ok(user)
+    }
+
+    pub async fn create_user(&self, username: &str, password_hash: &str, is_admin: bool) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO users (username, password_hash, is_admin) VALUES (?, ?, ?)"
+        )
+        .bind(username)
+        .bind(password_hash)
+        .bind(is_admin)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert user")?;
+
+        tracing::info!("Created user: {}", username);
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Vytvoří prvního administrátora jedním příkazem; vrací None, pokud už nějaký účet existuje
+    pub async fn create_first_admin(&self, username: &str, password_hash: &str) -> Result<Option<i64>> {
+        let result = sqlx::query(
+            "INSERT INTO users (username, password_hash, is_admin)
+             SELECT ?, ?, 1 WHERE NOT EXISTS (SELECT 1 FROM users)"
+        )
+        .bind(username)
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert first admin")?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        tracing::info!("Created initial admin: {}", username);
+        Ok(Some(result.last_insert_rowid()))
+    }
+
+    pub async fn update_user_password(&self, id: i64, password_hash: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to update user password")?;
+        // Po změně hesla odhlásíme všechny existující session
+        sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to revoke user sessions")?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_user(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete user")?;
+
+        tracing::info!("Deleted user: {}", id);
+        Ok(())
+    }
+
+    pub async fn create_session(&self, user_id: i64, token_hash: &str, ttl_secs: i64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO sessions (token_hash, user_id, expires_at)
+             VALUES (?, ?, datetime('now', '+' || ? || ' seconds'))"
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(ttl_secs)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create session")?;
+
+        sqlx::query("UPDATE users SET last_login_at = datetime('now') WHERE id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update last login")?;
+
+        // Cleanup expired sessions
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= datetime('now')")
+            .execute(&self.pool)
+            .await
+            .context("Failed to cleanup expired sessions")?;
+
+        Ok(())
+    }
+
+    pub async fn get_session_user(&self, token_hash: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT u.* FROM sessions s
+             JOIN users u ON u.id = s.user_id
+             WHERE s.token_hash = ? AND s.expires_at > datetime('now')"
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch session")?;
+        Ok(user)
+    }
+
+    pub async fn delete_session(&self, token_hash: &str) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE token_hash = ?")
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete session")?;
+        Ok(())
+    }
+
+    pub async fn update_user_groups(&self, id: i64, groups: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE users SET groups = ? WHERE id = ?")
+            .bind(groups)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update user groups")?;
+        Ok(())
+    }
+
+    // API token methods
+    pub async fn create_api_token(&self, token: &NewApiToken) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO api_tokens (name, kind, user_id, username, groups, is_admin, scopes, token_hash, token_prefix, expires_at, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', '+' || ? || ' days') END, ?)"
+        )
+        .bind(&token.name)
+        .bind(&token.kind)
+        .bind(token.user_id)
+        .bind(&token.username)
+        .bind(&token.groups)
+        .bind(token.is_admin)
+        .bind(&token.scopes)
+        .bind(&token.token_hash)
+        .bind(&token.token_prefix)
+        .bind(token.expires_in_days)
+        .bind(token.expires_in_days)
+        .bind(&token.created_by)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create API token")?;
+
+        tracing::info!("Created {} API token {} for {}", token.kind, token.name, token.username);
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_api_tokens(&self) -> Result<Vec<ApiToken>> {
+        let tokens = sqlx::query_as::<_, ApiToken>(
+            "SELECT id, name, kind, user_id, username, groups, is_admin, scopes, token_prefix, expires_at, last_used_at, created_by, created_at
+             FROM api_tokens ORDER BY kind, username, name"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch API tokens")?;
+        Ok(tokens)
+    }
+
+    /// Platný (nevypršelý) token podle hashe; zároveň zapíše čas použití
+    pub async fn use_api_token(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let token = sqlx::query_as::<_, ApiToken>(
+            "SELECT id, name, kind, user_id, username, groups, is_admin, scopes, token_prefix, expires_at, last_used_at, created_by, created_at
+             FROM api_tokens
+             WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > datetime('now'))"
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch API token")?;
+
+        if let Some(token) = &token {
+            // Zápis nejvýš jednou za minutu, ať každý request nezapisuje do SQLite
+            sqlx::query(
+                "UPDATE api_tokens SET last_used_at = datetime('now')
+                 WHERE id = ? AND (last_used_at IS NULL OR last_used_at <= datetime('now', '-1 minute'))"
+            )
+            .bind(token.id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update API token usage")?;
+        }
+        Ok(token)
+    }
+
+    pub async fn delete_api_token(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM api_tokens WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete API token")?;
+        Ok(())
+    }
+
+    // Endpoint role methods
+    pub async fn get_endpoint_roles(&self) -> Result<Vec<EndpointRole>> {
+        let roles = sqlx::query_as::<_, EndpointRole>(
+            "SELECT r.id, r.endpoint_id, e.name AS endpoint_name, r.subject_type, r.subject, r.role, r.created_at
+             FROM endpoint_roles r
+             JOIN endpoints e ON e.id = r.endpoint_id
+             ORDER BY e.name, r.subject_type, r.subject"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch endpoint roles")?;
+        Ok(roles)
+    }
+
+    pub async fn set_endpoint_role(
+        &self,
+        endpoint_id: i64,
+        subject_type: &str,
+        subject: &str,
+        role: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO endpoint_roles (endpoint_id, subject_type, subject, role)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT (endpoint_id, subject_type, subject) DO UPDATE SET role = excluded.role"
+        )
+        .bind(endpoint_id)
+        .bind(subject_type)
+        .bind(subject)
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .context("Failed to save endpoint role")?;
+
+        tracing::info!("Granted {} on endpoint {} to {} {}", role, endpoint_id, subject_type, subject);
+        Ok(())
+    }
+
+    pub async fn delete_endpoint_role(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM endpoint_roles WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete endpoint role")?;
+        Ok(())
+    }
+
+    // Audit log methods
+    pub async fn insert_audit_entry(&self, entry: &NewAuditEntry) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (username, endpoint_id, endpoint_name, database_name, action, command, success, error)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&entry.username)
+        .bind(entry.endpoint_id)
+        .bind(&entry.endpoint_name)
+        .bind(&entry.database_name)
+        .bind(&entry.action)
+        .bind(&entry.command)
+        .bind(entry.success)
+        .bind(&entry.error)
+        .execute(&self.pool)
+        .await
+        .context("Failed to write audit log entry")?;
+        Ok(())
+    }
+
+    pub async fn get_audit_log(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT id, created_at, username, endpoint_id, endpoint_name, database_name, action, command, success, error
+             FROM audit_log WHERE 1 = 1",
+        );
+        if let Some(username) = &filter.username {
+            qb.push(" AND username = ").push_bind(username);
+        }
+        if let Some(endpoint_id) = filter.endpoint_id {
+            qb.push(" AND endpoint_id = ").push_bind(endpoint_id);
+        }
+        if let Some(action) = &filter.action {
+            qb.push(" AND action = ").push_bind(action);
+        }
+        if let Some(success) = filter.success {
+            qb.push(" AND success = ").push_bind(success);
+        }
+        if let Some(from) = &filter.from {
+            qb.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = &filter.to {
+            // "to" je den včetně
+            qb.push(" AND created_at < date(").push_bind(to).push(", '+1 day')");
+        }
+        if let Some(search) = &filter.search {
+            qb.push(" AND (command LIKE ")
+                .push_bind(format!("%{}%", search))
+                .push(" OR error LIKE ")
+                .push_bind(format!("%{}%", search))
+                .push(")");
+        }
+        qb.push(" ORDER BY created_at DESC, id DESC");
+        if let Some(limit) = filter.limit {
+            qb.push(" LIMIT ").push_bind(limit);
+        }
+
+        let entries = qb
+            .build_query_as::<AuditEntry>()
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch audit log")?;
+        Ok(entries)
+    }
+
+    pub async fn get_audit_usernames(&self) -> Result<Vec<String>> {
+        let names = sqlx::query_scalar("SELECT DISTINCT username FROM audit_log ORDER BY username")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch audit usernames")?;
+        Ok(names)
+    }
+
+    // Job methods
+    pub async fn insert_job(&self, job: &NewJob) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO jobs (id, kind, endpoint_id, endpoint_name, database_name, username, params, status, command)
+             VALUES (?, ?, ?, ?, ?, ?, ?, 'running', ?)"
+        )
+        .bind(&job.id)
+        .bind(&job.kind)
+        .bind(job.endpoint_id)
+        .bind(&job.endpoint_name)
+        .bind(&job.database_name)
+        .bind(&job.username)
+        .bind(&job.params)
+        .bind(&job.command)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert job")?;
+        Ok(())
+    }
+
+    pub async fn finish_job(
+        &self,
+        id: &str,
+        status: &str,
+        command: Option<&str>,
+        error: Option<&str>,
+        artifact_path: Option<&str>,
+        log_path: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE jobs SET status = ?, command = COALESCE(?, command), error = ?, artifact_path = ?, log_path = ?,
+                 completed_at = datetime('now')
+             WHERE id = ?"
+        )
+        .bind(status)
+        .bind(command)
+        .bind(error)
+        .bind(artifact_path)
+        .bind(log_path)
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .context("Failed to update job")?;
+        Ok(())
+    }
+
+    pub async fn get_jobs(&self, filter: &JobFilter) -> Result<Vec<JobRecord>> {
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM jobs WHERE 1 = 1");
+        if let Some(kind) = &filter.kind {
+            qb.push(" AND kind = ").push_bind(kind);
+        }
+        if let Some(status) = &filter.status {
+            qb.push(" AND status = ").push_bind(status);
+        }
+        qb.push(" ORDER BY started_at DESC, rowid DESC");
+        if let Some(limit) = filter.limit {
+            qb.push(" LIMIT ").push_bind(limit);
+        }
+        let jobs = qb
+            .build_query_as::<JobRecord>()
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch jobs")?;
+        Ok(jobs)
+    }
+
+    pub async fn get_job(&self, id: &str) -> Result<Option<JobRecord>> {
+        let job = sqlx::query_as::<_, JobRecord>("SELECT * FROM jobs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch job")?;
+        Ok(job)
+    }
+
+    /// Joby (a poslední běhy plánů), které při pádu nebo restartu zůstaly ve stavu running
+    pub async fn mark_interrupted_jobs(&self) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE jobs SET status = 'interrupted', error = 'Interrupted by restart', completed_at = datetime('now')
+             WHERE status = 'running'"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to mark interrupted jobs")?;
+        sqlx::query(
+            "UPDATE export_schedules SET last_status = 'interrupted', last_error = 'Interrupted by restart'
+             WHERE last_status = 'running'"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to mark interrupted schedules")?;
+        Ok(result.rows_affected())
+    }
+
+    // Export schedule methods
+    pub async fn create_export_schedule(&self, schedule: &NewExportSchedule) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO export_schedules (name, endpoint_id, cron, options, keep_last, created_by)
+             VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&schedule.name)
+        .bind(schedule.endpoint_id)
+        .bind(&schedule.cron)
+        .bind(&schedule.options)
+        .bind(schedule.keep_last)
+        .bind(&schedule.created_by)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create export schedule")?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_export_schedules(&self) -> Result<Vec<ExportSchedule>> {
+        let schedules = sqlx::query_as::<_, ExportSchedule>("SELECT * FROM export_schedules ORDER BY name, id")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch export schedules")?;
+        Ok(schedules)
+    }
+
+    pub async fn get_export_schedule(&self, id: i64) -> Result<Option<ExportSchedule>> {
+        let schedule = sqlx::query_as::<_, ExportSchedule>("SELECT * FROM export_schedules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch export schedule")?;
+        Ok(schedule)
+    }
+
+    pub async fn set_export_schedule_enabled(&self, id: i64, enabled: bool) -> Result<()> {
+        sqlx::query("UPDATE export_schedules SET enabled = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update export schedule")?;
+        Ok(())
+    }
+
+    pub async fn delete_export_schedule(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM export_schedules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete export schedule")?;
+        Ok(())
+    }
+
+    /// Běh plánu: job se přiřadí k plánu (kvůli keep_last) a plán si ho pamatuje jako poslední běh;
+    /// bez jobu (export se nespustil) se zapíše jen čas pokusu
+    pub async fn start_schedule_run(&self, id: i64, job_id: Option<&str>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(job_id) = job_id {
+            sqlx::query("UPDATE jobs SET schedule_id = ? WHERE id = ?")
+                .bind(id)
+                .bind(job_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to link job to schedule")?;
+        }
+        sqlx::query(
+            "UPDATE export_schedules SET last_run_at = datetime('now'), last_job_id = ?, last_status = 'running',
+                 last_error = NULL
+             WHERE id = ?"
+        )
+        .bind(job_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update export schedule")?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn finish_schedule_run(&self, id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE export_schedules SET last_status = ?, last_error = ? WHERE id = ?")
+            .bind(status)
+            .bind(error)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update export schedule")?;
+        Ok(())
+    }
+
+    /// Dokončené joby plánu, které ještě mají dump (lokálně nebo v S3), nejnovější první
+    pub async fn get_schedule_artifacts(&self, schedule_id: i64) -> Result<Vec<JobRecord>> {
+        let jobs = sqlx::query_as::<_, JobRecord>(
+            "SELECT * FROM jobs WHERE schedule_id = ? AND status = 'completed'
+                 AND (artifact_path IS NOT NULL OR remote_key IS NOT NULL)
+             ORDER BY started_at DESC, rowid DESC"
+        )
+        .bind(schedule_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch schedule artifacts")?;
+        Ok(jobs)
+    }
+
+    pub async fn set_job_remote(&self, id: &str, target_id: i64, remote_key: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET target_id = ?, remote_key = ? WHERE id = ?")
+            .bind(target_id)
+            .bind(remote_key)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update job")?;
+        Ok(())
+    }
+
+    pub async fn clear_job_remote(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET remote_key = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update job")?;
+        Ok(())
+    }
+
+    pub async fn clear_job_artifact(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET artifact_path = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update job")?;
+        Ok(())
+    }
+
+    // Storage target methods
+    pub async fn create_storage_target(&self, target: &NewStorageTarget) -> Result<i64> {
+        let secret_key_encrypted = self.encrypt_password(&target.secret_key)?;
+        let result = sqlx::query(
+            "INSERT INTO storage_targets (name, endpoint_url, region, bucket, prefix, access_key, secret_key_encrypted,
+                 path_style, delete_local)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&target.name)
+        .bind(&target.endpoint_url)
+        .bind(&target.region)
+        .bind(&target.bucket)
+        .bind(&target.prefix)
+        .bind(&target.access_key)
+        .bind(&secret_key_encrypted)
+        .bind(target.path_style)
+        .bind(target.delete_local)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create storage target")?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_storage_targets(&self) -> Result<Vec<StorageTarget>> {
+        let targets = sqlx::query_as::<_, StorageTarget>("SELECT * FROM storage_targets ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch storage targets")?;
+        Ok(targets)
+    }
+
+    pub async fn get_storage_target(&self, id: i64) -> Result<Option<StorageTarget>> {
+        let target = sqlx::query_as::<_, StorageTarget>("SELECT * FROM storage_targets WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch storage target")?;
+        Ok(target)
+    }
+
+    pub async fn delete_storage_target(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM storage_targets WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete storage target")?;
+        Ok(())
+    }
+
+    pub fn get_storage_target_secret(&self, target: &StorageTarget) -> Result<String> {
+        self.decrypt_password(&target.secret_key_encrypted)
+            .with_context(|| format!("Failed to decrypt the secret key of storage target '{}'", target.name))
+    }
 }
@@ -13,10 +13,77 @@ pub struct Endpoint {
     pub search_path: Option<String>,
     #[serde(default)]
     pub enable_blueprint: bool,
+    #[serde(default)]
+    pub ssh_host: Option<String>,
+    #[serde(default)]
+    pub ssh_port: Option<i64>,
+    #[serde(default)]
+    pub ssh_user: Option<String>,
+    #[serde(default)]
+    pub ssh_key_path: Option<String>,
+    #[serde(default)]
+    pub ssh_passphrase_encrypted: Option<String>,
+    #[serde(default)]
+    pub ssl_root_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_key_encrypted: Option<String>,
+    #[serde(default)]
+    pub group_name: Option<String>,
+    /// Tagy oddělené čárkou
+    #[serde(default)]
+    pub tags: Option<String>,
+    /// dev / test / staging / prod
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Read repliky, jeden connection string na řádek
+    #[serde(default)]
+    pub replica_urls: Option<String>,
+    /// Maximální zpoždění repliky v sekundách (výchozí DEFAULT_REPLICA_MAX_LAG)
+    #[serde(default)]
+    pub replica_max_lag: Option<i64>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Povolené hodnoty Endpoint::environment
+pub const ENVIRONMENTS: [&str; 4] = ["dev", "test", "staging", "prod"];
+
+/// Výchozí max. zpoždění repliky v sekundách, nad ním čtení jde na primární server
+pub const DEFAULT_REPLICA_MAX_LAG: i64 = 30;
+
+impl Endpoint {
+    pub fn tag_list(&self) -> Vec<&str> {
+        self.tags
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+
+    pub fn environment_name(&self) -> &str {
+        self.environment.as_deref().unwrap_or("")
+    }
+
+    /// Connection stringy replik (jeden na řádek)
+    pub fn replica_list(&self) -> Vec<&str> {
+        self.replica_urls
+            .as_deref()
+            .unwrap_or("")
+            .lines()
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .collect()
+    }
+
+    pub fn replica_max_lag(&self) -> i64 {
+        self.replica_max_lag.unwrap_or(DEFAULT_REPLICA_MAX_LAG)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateEndpoint {
     pub name: String,
@@ -28,6 +95,32 @@ pub struct CreateEndpoint {
     pub search_path: Option<String>,
     #[serde(default)]
     pub enable_blueprint: bool,
+    #[serde(default)]
+    pub ssh_host: Option<String>,
+    #[serde(default)]
+    pub ssh_port: Option<i64>,
+    #[serde(default)]
+    pub ssh_user: Option<String>,
+    #[serde(default)]
+    pub ssh_key_path: Option<String>,
+    #[serde(default)]
+    pub ssh_passphrase: Option<String>,
+    #[serde(default)]
+    pub ssl_root_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_key: Option<String>,
+    #[serde(default)]
+    pub group_name: Option<String>,
+    #[serde(default)]
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub replica_urls: Option<String>,
+    #[serde(default)]
+    pub replica_max_lag: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +133,25 @@ pub struct UpdateEndpoint {
     pub ssl_mode: Option<String>,
     pub search_path: Option<String>,
     pub enable_blueprint: Option<bool>,
+    pub ssh_host: Option<String>,
+    pub ssh_port: Option<i64>,
+    pub ssh_user: Option<String>,
+    pub ssh_key_path: Option<String>,
+    /// None ponechá uloženou passphrase
+    pub ssh_passphrase: Option<String>,
+    #[serde(default)]
+    pub clear_ssh_passphrase: bool,
+    pub ssl_root_cert: Option<String>,
+    pub ssl_cert: Option<String>,
+    /// None ponechá uložený klíč
+    pub ssl_key: Option<String>,
+    #[serde(default)]
+    pub clear_ssl_key: bool,
+    pub group_name: Option<String>,
+    pub tags: Option<String>,
+    pub environment: Option<String>,
+    pub replica_urls: Option<String>,
+    pub replica_max_lag: Option<i64>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -51,3 +163,213 @@ pub struct QueryHistory {
     pub status: String,
     pub duration_ms: Option<i64>,
 }
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    #[serde(skip)]
+    pub password_hash: String,
+    pub is_admin: bool,
+    pub created_at: String,
+    pub last_login_at: Option<String>,
+    pub groups: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// 'personal' nebo 'service'
+    pub kind: String,
+    pub user_id: Option<i64>,
+    pub username: String,
+    pub groups: Option<String>,
+    pub is_admin: bool,
+    pub scopes: String,
+    /// Začátek tokenu pro rozpoznání v seznamu (celý token se neukládá)
+    pub token_prefix: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+impl ApiToken {
+    pub fn scope_list(&self) -> Vec<&str> {
+        self.scopes.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect()
+    }
+
+    /// expires_at je ve formátu SQLite datetime() (UTC), takže stačí porovnat řetězce
+    pub fn is_expired(&self) -> bool {
+        let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.expires_at.as_deref().is_some_and(|expires| expires <= now.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewApiToken {
+    pub name: String,
+    pub kind: String,
+    pub user_id: Option<i64>,
+    pub username: String,
+    pub groups: Option<String>,
+    pub is_admin: bool,
+    pub scopes: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    /// Platnost ve dnech, None = bez expirace
+    pub expires_in_days: Option<i64>,
+    pub created_by: String,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct EndpointRole {
+    pub id: i64,
+    pub endpoint_id: i64,
+    pub endpoint_name: String,
+    pub subject_type: String,
+    pub subject: String,
+    pub role: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: String,
+    pub username: String,
+    pub endpoint_id: Option<i64>,
+    pub endpoint_name: Option<String>,
+    pub database_name: Option<String>,
+    pub action: String,
+    pub command: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub username: String,
+    pub endpoint_id: Option<i64>,
+    pub endpoint_name: Option<String>,
+    pub database_name: Option<String>,
+    pub action: String,
+    pub command: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub username: Option<String>,
+    pub endpoint_id: Option<i64>,
+    pub action: Option<String>,
+    pub success: Option<bool>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub search: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Záznam background jobu (export, import, console) v tabulce jobs
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub kind: String,
+    pub endpoint_id: Option<i64>,
+    pub endpoint_name: Option<String>,
+    pub database_name: Option<String>,
+    pub username: String,
+    /// JSON požadavku, kterým se job spustil (pro re-run)
+    pub params: String,
+    /// running, completed, failed, interrupted
+    pub status: String,
+    pub command: Option<String>,
+    pub error: Option<String>,
+    pub artifact_path: Option<String>,
+    pub log_path: Option<String>,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+    /// Cíl a klíč objektu, kam se dump nahrál
+    pub target_id: Option<i64>,
+    pub remote_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub id: String,
+    pub kind: String,
+    pub endpoint_id: Option<i64>,
+    pub endpoint_name: Option<String>,
+    pub database_name: Option<String>,
+    pub username: String,
+    pub params: String,
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    pub kind: Option<String>,
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Plánovaný opakovaný export (tabulka export_schedules)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ExportSchedule {
+    pub id: i64,
+    pub name: String,
+    pub endpoint_id: i64,
+    /// Cron výraz v UTC (5 polí, nebo 6-7 se sekundami)
+    pub cron: String,
+    /// JSON ExportRequest
+    pub options: String,
+    pub keep_last: i64,
+    pub enabled: bool,
+    pub created_by: String,
+    pub last_run_at: Option<String>,
+    pub last_job_id: Option<String>,
+    pub last_status: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewExportSchedule {
+    pub name: String,
+    pub endpoint_id: i64,
+    pub cron: String,
+    pub options: String,
+    pub keep_last: i64,
+    pub created_by: String,
+}
+
+/// S3-kompatibilní cíl pro nahrání dumpů (tabulka storage_targets)
+#[derive(Debug, Clone, FromRow)]
+pub struct StorageTarget {
+    pub id: i64,
+    pub name: String,
+    pub endpoint_url: String,
+    pub region: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub access_key: String,
+    pub secret_key_encrypted: String,
+    pub path_style: bool,
+    pub delete_local: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewStorageTarget {
+    pub name: String,
+    pub endpoint_url: String,
+    pub region: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    pub path_style: bool,
+    pub delete_local: bool,
+}
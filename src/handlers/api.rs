@@ -0,0 +1,465 @@
+//! Verzované JSON API (/api/v1) nad stejnými dotazy a cache jako HTML stránky
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Role;
+use crate::db::models::Endpoint;
+use crate::handlers::conninfo::ConnInfo;
+use crate::handlers::databases::{fetch_databases, DatabaseRowDb};
+use crate::handlers::indices::{fetch_indices_from_db, get_cached_indices, IndexRowDb};
+use crate::handlers::patroni::{fetch_cluster_status, PatroniNodeStatus};
+use crate::handlers::replica::connect_pg_read;
+use crate::handlers::schemas::{fetch_schemas_from_db, get_cached_schemas, SchemaRowDb};
+use crate::handlers::table_detail::{
+    fetch_table_columns, fetch_table_data, fetch_table_indexes, fetch_table_partitions,
+    fetch_table_relationships, fetch_table_triggers, is_safe_ident, TableColumn, TableData,
+    TableIndex, TablePartition, TableRelationships, TableTrigger,
+};
+use crate::handlers::tables::{fetch_tables_from_db, get_cached_tables, TableRowDb};
+use crate::handlers::tuning::{fetch_tuning_report, TuningReport};
+use crate::handlers::{all_endpoints, connect_pg, db_session, endpoint_role, patroni_urls, AppState};
+use crate::utils::filter::{matches_pattern, matches_simple_terms, parse_simple_terms};
+
+pub const API_DEFAULT_PER_PAGE: usize = 50;
+pub const API_MAX_PER_PAGE: usize = 1000;
+
+/// Chyba API jako `{"error": "..."}`
+#[derive(Debug)]
+pub struct ApiError(pub StatusCode, pub String);
+
+impl ApiError {
+    fn not_found(message: impl Into<String>) -> Self {
+        Self(StatusCode::NOT_FOUND, message.into())
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self(StatusCode::BAD_REQUEST, message.into())
+    }
+
+    /// Chyba při spojení nebo dotazu na spravovaný Postgres
+    fn upstream(message: impl std::fmt::Display) -> Self {
+        Self(StatusCode::BAD_GATEWAY, message.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    /// Jednoduchý filtr jmen (substring, více výrazů přes čárku)
+    pub filter: Option<String>,
+    /// Schéma (tabulky podporují i wildcard vzory jako na stránce /tables)
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub sort_by: Option<String>,
+    pub sort_order: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub per_page: usize,
+    /// Počet položek před filtrem
+    pub total: usize,
+    pub filtered: usize,
+    pub total_pages: usize,
+    /// Cache se na pozadí obnovuje, data můžou být zastaralá
+    pub fetching: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiEndpoint {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub group: Option<String>,
+    pub tags: Vec<String>,
+    pub environment: Option<String>,
+    pub role: &'static str,
+    pub replicas: usize,
+}
+
+impl ApiEndpoint {
+    fn new(state: &Arc<AppState>, endpoint: &Endpoint) -> Self {
+        Self {
+            id: endpoint.id,
+            name: endpoint.name.clone(),
+            // Bez hesla; co nejde naparsovat, radši nevracíme vůbec
+            url: ConnInfo::parse(&endpoint.url)
+                .map(|info| info.redacted().to_connection_string())
+                .unwrap_or_default(),
+            group: endpoint.group_name.clone(),
+            tags: endpoint.tag_list().into_iter().map(str::to_string).collect(),
+            environment: endpoint.environment.clone(),
+            role: endpoint_role(state, endpoint.id).as_str(),
+            replicas: endpoint.replica_list().len(),
+        }
+    }
+}
+
+impl ListQuery {
+    fn terms(&self) -> Option<Vec<String>> {
+        let filter = self.filter.as_deref().map(str::trim).filter(|f| !f.is_empty() && *f != "*")?;
+        let terms = parse_simple_terms(filter);
+        if terms.is_empty() { None } else { Some(terms) }
+    }
+
+    fn ascending(&self, default: &str) -> bool {
+        self.sort_order.as_deref().unwrap_or(default) == "asc"
+    }
+
+    fn sort_key<'a>(&'a self, default: &'a str) -> &'a str {
+        self.sort_by.as_deref().unwrap_or(default)
+    }
+
+    fn scope(value: &Option<String>) -> Option<&str> {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty() && *v != "*")
+    }
+
+    fn paginate<T>(&self, items: Vec<T>, total: usize, fetching: bool) -> Result<Page<T>, ApiError> {
+        let page = self.page.unwrap_or(1).max(1);
+        let per_page = self.per_page.unwrap_or(API_DEFAULT_PER_PAGE);
+        if per_page == 0 || per_page > API_MAX_PER_PAGE {
+            return Err(ApiError::bad_request(format!("per_page must be between 1 and {}", API_MAX_PER_PAGE)));
+        }
+        let filtered = items.len();
+        Ok(Page {
+            items: items.into_iter().skip((page - 1) * per_page).take(per_page).collect(),
+            page,
+            per_page,
+            total,
+            filtered,
+            total_pages: filtered.div_ceil(per_page),
+            fetching,
+        })
+    }
+}
+
+fn sort_rows<T, K: Ord>(rows: &mut [T], ascending: bool, key: impl Fn(&T) -> K) {
+    rows.sort_by_key(|row| key(row));
+    if !ascending {
+        rows.reverse();
+    }
+}
+
+/// Endpoint podle ID nebo jména; bez role Viewer se tváří jako neexistující
+async fn resolve_endpoint(state: &Arc<AppState>, key: &str) -> Result<Endpoint, ApiError> {
+    let endpoints = all_endpoints(state)
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let endpoint = endpoints
+        .into_iter()
+        .find(|e| e.id.to_string() == key || e.name == key)
+        .filter(|e| endpoint_role(state, e.id) >= Role::Viewer)
+        .ok_or_else(|| ApiError::not_found(format!("Endpoint '{}' not found", key)))?;
+    // Prohlížeč s přepnutou databází dostane stejná data jako na stránkách
+    Ok(match db_session::current_override(state, endpoint.id) {
+        Some(session) => session.endpoint,
+        None => endpoint,
+    })
+}
+
+async fn read_pool(state: &Arc<AppState>, endpoint: &Endpoint) -> Result<sqlx::PgPool, ApiError> {
+    connect_pg_read(state, endpoint)
+        .await
+        .map_err(|e| ApiError::upstream(format!("Failed to connect to Postgres: {}", e)))
+}
+
+/// Studená cache: data se načtou rovnou, ať klient nedostane prázdnou stránku
+async fn cached_or_fetch<T, C, F>(cached: C, fetch: F) -> Result<(Vec<T>, bool), ApiError>
+where
+    C: Future<Output = (Vec<T>, bool)>,
+    F: Future<Output = Result<Vec<T>, String>>,
+{
+    let (rows, fetching) = cached.await;
+    if rows.is_empty() && fetching {
+        return fetch.await.map(|rows| (rows, false)).map_err(ApiError::upstream);
+    }
+    Ok((rows, fetching))
+}
+
+/// GET /api/v1/endpoints
+pub async fn list_endpoints(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Page<ApiEndpoint>> {
+    let endpoints = all_endpoints(&state)
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut items: Vec<ApiEndpoint> = endpoints
+        .iter()
+        .filter(|e| endpoint_role(&state, e.id) >= Role::Viewer)
+        .map(|e| ApiEndpoint::new(&state, e))
+        .collect();
+    let total = items.len();
+    if let Some(terms) = query.terms() {
+        items.retain(|e| matches_simple_terms(&e.name, &terms));
+    }
+    let ascending = query.ascending("asc");
+    match query.sort_key("name") {
+        "id" => sort_rows(&mut items, ascending, |e| e.id),
+        "group" => sort_rows(&mut items, ascending, |e| e.group.clone()),
+        _ => sort_rows(&mut items, ascending, |e| e.name.to_lowercase()),
+    }
+    Ok(Json(query.paginate(items, total, false)?))
+}
+
+/// GET /api/v1/endpoints/{endpoint}
+pub async fn get_endpoint(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+) -> ApiResult<ApiEndpoint> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    Ok(Json(ApiEndpoint::new(&state, &endpoint)))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/databases
+pub async fn list_databases(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Page<DatabaseRowDb>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    let mut rows = fetch_databases(&pg).await.map_err(ApiError::upstream)?;
+    let total = rows.len();
+    if let Some(terms) = query.terms() {
+        rows.retain(|d| matches_simple_terms(&d.name, &terms));
+    }
+    let ascending = query.ascending("asc");
+    match query.sort_key("name") {
+        "size" => sort_rows(&mut rows, ascending, |d| d.size_bytes),
+        "connections" => sort_rows(&mut rows, ascending, |d| d.connections),
+        _ => sort_rows(&mut rows, ascending, |d| d.name.clone()),
+    }
+    Ok(Json(query.paginate(rows, total, false)?))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/schemas
+pub async fn list_schemas(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Page<SchemaRowDb>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let (mut rows, fetching) = cached_or_fetch(
+        get_cached_schemas(&state, &endpoint),
+        fetch_schemas_from_db(&state, &endpoint),
+    )
+    .await?;
+    let total = rows.len();
+    if let Some(terms) = query.terms() {
+        rows.retain(|s| matches_simple_terms(&s.name, &terms));
+    }
+    let ascending = query.ascending("asc");
+    match query.sort_key("name") {
+        "tables" => sort_rows(&mut rows, ascending, |s| s.table_count),
+        "indexes" => sort_rows(&mut rows, ascending, |s| s.index_count),
+        "size" => sort_rows(&mut rows, ascending, |s| s.total_size),
+        _ => sort_rows(&mut rows, ascending, |s| s.name.clone()),
+    }
+    Ok(Json(query.paginate(rows, total, fetching)?))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables
+pub async fn list_tables(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Page<TableRowDb>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let (mut rows, fetching) = cached_or_fetch(
+        get_cached_tables(&state, &endpoint),
+        fetch_tables_from_db(&state, &endpoint),
+    )
+    .await?;
+    if let Some(schema) = ListQuery::scope(&query.schema) {
+        rows.retain(|t| matches_pattern(&t.schema, schema));
+    }
+    let total = rows.len();
+    if let Some(terms) = query.terms() {
+        rows.retain(|t| matches_simple_terms(&t.name, &terms));
+    }
+    let ascending = query.ascending("desc");
+    match query.sort_key("size") {
+        "schema" => sort_rows(&mut rows, ascending, |t| t.schema.clone()),
+        "name" => sort_rows(&mut rows, ascending, |t| t.name.clone()),
+        "rows" => sort_rows(&mut rows, ascending, |t| t.row_estimate),
+        "indexes" => sort_rows(&mut rows, ascending, |t| t.index_count),
+        _ => sort_rows(&mut rows, ascending, |t| t.size_bytes),
+    }
+    Ok(Json(query.paginate(rows, total, fetching)?))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/indices
+pub async fn list_indices(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Page<IndexRowDb>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let (mut rows, fetching) = cached_or_fetch(
+        get_cached_indices(&state, &endpoint),
+        fetch_indices_from_db(&state, &endpoint),
+    )
+    .await?;
+    if let Some(schema) = ListQuery::scope(&query.schema) {
+        rows.retain(|i| i.schema == schema);
+    }
+    if let Some(table) = ListQuery::scope(&query.table) {
+        rows.retain(|i| i.table_name == table);
+    }
+    let total = rows.len();
+    if let Some(terms) = query.terms() {
+        rows.retain(|i| matches_simple_terms(&i.index_name, &terms));
+    }
+    let ascending = query.ascending("desc");
+    match query.sort_key("size") {
+        "schema" => sort_rows(&mut rows, ascending, |i| i.schema.clone()),
+        "table" => sort_rows(&mut rows, ascending, |i| i.table_name.clone()),
+        "name" => sort_rows(&mut rows, ascending, |i| i.index_name.clone()),
+        "scans" => sort_rows(&mut rows, ascending, |i| i.scans.unwrap_or(0)),
+        _ => sort_rows(&mut rows, ascending, |i| i.size_bytes),
+    }
+    Ok(Json(query.paginate(rows, total, fetching)?))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/columns
+pub async fn table_columns(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+) -> ApiResult<Vec<TableColumn>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_columns(&pg, &schema, &table)
+        .await
+        .map_err(ApiError::upstream)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Table {}.{} not found", schema, table)))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/indexes
+pub async fn table_indexes(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+) -> ApiResult<Vec<TableIndex>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_indexes(&pg, &schema, &table).await.map(Json).map_err(ApiError::upstream)
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/triggers
+pub async fn table_triggers(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+) -> ApiResult<Vec<TableTrigger>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_triggers(&pg, &schema, &table).await.map(Json).map_err(ApiError::upstream)
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/partitions
+pub async fn table_partitions(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+) -> ApiResult<Vec<TablePartition>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_partitions(&pg, &schema, &table).await.map(Json).map_err(ApiError::upstream)
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/relationships
+pub async fn table_relationships(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+) -> ApiResult<TableRelationships> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_relationships(&pg, &schema, &table).await.map(Json).map_err(ApiError::upstream)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DataQuery {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tables/{schema}/{table}/data
+pub async fn table_data(
+    State(state): State<Arc<AppState>>,
+    Path((key, schema, table)): Path<(String, String, String)>,
+    Query(query): Query<DataQuery>,
+) -> ApiResult<TableData> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    if !is_safe_ident(&schema) || !is_safe_ident(&table) {
+        return Err(ApiError::bad_request("Invalid table identifier"));
+    }
+    let limit = query.limit.unwrap_or(API_DEFAULT_PER_PAGE);
+    if limit == 0 || limit > API_MAX_PER_PAGE {
+        return Err(ApiError::bad_request(format!("limit must be between 1 and {}", API_MAX_PER_PAGE)));
+    }
+    let pg = read_pool(&state, &endpoint).await?;
+    fetch_table_data(&pg, &schema, &table, limit, query.offset.unwrap_or(0))
+        .await
+        .map(Json)
+        .map_err(ApiError::upstream)
+}
+
+/// GET /api/v1/endpoints/{endpoint}/tuning (jako stránka /tuning jde na primary)
+pub async fn tuning(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+) -> ApiResult<TuningReport> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let pg = connect_pg(&state, &endpoint)
+        .await
+        .map_err(|e| ApiError::upstream(format!("Failed to connect to Postgres: {}", e)))?;
+    Ok(Json(fetch_tuning_report(&pg).await))
+}
+
+/// GET /api/v1/endpoints/{endpoint}/patroni
+pub async fn patroni(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+) -> ApiResult<Vec<PatroniNodeStatus>> {
+    let endpoint = resolve_endpoint(&state, &key).await?;
+    let urls = patroni_urls(&state, Some(&endpoint))
+        .ok_or_else(|| ApiError::not_found("Patroni monitoring is not enabled"))?;
+    fetch_cluster_status(&urls)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_and_sort() {
+        let query = ListQuery { page: Some(2), per_page: Some(2), ..Default::default() };
+        let mut rows = vec![3, 1, 5, 4, 2];
+        sort_rows(&mut rows, false, |n| *n);
+        let page = query.paginate(rows, 5, false).unwrap();
+        assert_eq!(page.items, vec![3, 2]);
+        assert_eq!(page.total_pages, 3);
+
+        let too_big = ListQuery { per_page: Some(API_MAX_PER_PAGE + 1), ..Default::default() };
+        assert!(too_big.paginate(vec![1], 1, false).is_err());
+    }
+}
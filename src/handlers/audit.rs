@@ -0,0 +1,268 @@
+use askama::Template;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::CurrentUser;
+use crate::db::models::{AuditFilter, Endpoint, NewAuditEntry};
+use crate::handlers::conninfo::ConnInfo;
+use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::templates::AuditTemplate;
+
+/// Typy akcí, které zapisujeme do audit logu
+pub const ACTIONS: &[&str] = &["maintenance", "autovacuum", "console", "blueprint", "export", "import", "catalog", "token", "storage", "schedule"];
+
+const PAGE_LIMIT: i64 = 500;
+
+/// Zapíše akci do audit logu; chyba zápisu akci neblokuje, jen se zaloguje
+pub async fn record(
+    state: &AppState,
+    user: &CurrentUser,
+    endpoint: &Endpoint,
+    action: &str,
+    command: &str,
+    error: Option<&str>,
+) {
+    tracing::info!(
+        "Audit: {} {} on {} ({}): {}",
+        user.audit_name(),
+        action,
+        endpoint.name,
+        if error.is_none() { "ok" } else { "failed" },
+        command
+    );
+    let Some(db) = state.db.as_ref() else {
+        return;
+    };
+    let entry = NewAuditEntry {
+        username: user.audit_name(),
+        endpoint_id: Some(endpoint.id),
+        endpoint_name: Some(endpoint.name.clone()),
+        database_name: database_name(&endpoint.url),
+        action: action.to_string(),
+        command: command.to_string(),
+        success: error.is_none(),
+        error: error.map(|e| e.to_string()),
+    };
+    if let Err(e) = db.insert_audit_entry(&entry).await {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
+}
+
+/// Akce nad katalogem endpointů (export/import) - nevážou se k jednomu endpointu
+pub async fn record_catalog(state: &AppState, user: &CurrentUser, command: &str, error: Option<&str>) {
+    record_global(state, user, "catalog", command, error).await;
+}
+
+/// Akce bez vazby na endpoint (katalog, API tokeny)
+pub async fn record_global(state: &AppState, user: &CurrentUser, action: &str, command: &str, error: Option<&str>) {
+    tracing::info!(
+        "Audit: {} {} ({}): {}",
+        user.audit_name(),
+        action,
+        if error.is_none() { "ok" } else { "failed" },
+        command
+    );
+    let Some(db) = state.db.as_ref() else {
+        return;
+    };
+    let entry = NewAuditEntry {
+        username: user.audit_name(),
+        endpoint_id: None,
+        endpoint_name: None,
+        database_name: None,
+        action: action.to_string(),
+        command: command.to_string(),
+        success: error.is_none(),
+        error: error.map(|e| e.to_string()),
+    };
+    if let Err(e) = db.insert_audit_entry(&entry).await {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
+}
+
+/// Audit pro background job (console, export, import) - výsledek a příkaz bereme z dokončeného jobu
+pub async fn record_job(
+    state: &AppState,
+    user: &CurrentUser,
+    endpoint: &Endpoint,
+    action: &str,
+    job_id: &str,
+) {
+    let job = state.export_jobs.read().await.get(job_id).cloned();
+    let Some(job) = job else {
+        return;
+    };
+    let command = job.command.unwrap_or_default();
+    record(state, user, endpoint, action, &command, job.error.as_deref()).await;
+}
+
+pub fn database_name(url: &str) -> Option<String> {
+    ConnInfo::parse(url).ok().map(|info| info.database().to_string())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub user: Option<String>,
+    pub endpoint_id: Option<String>,
+    pub action: Option<String>,
+    pub status: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub q: Option<String>,
+}
+
+impl AuditQuery {
+    fn to_filter(&self, limit: Option<i64>) -> AuditFilter {
+        let non_empty = |v: &Option<String>| v.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        AuditFilter {
+            username: non_empty(&self.user),
+            endpoint_id: non_empty(&self.endpoint_id).and_then(|id| id.parse().ok()),
+            action: non_empty(&self.action),
+            success: match self.status.as_deref() {
+                Some("success") => Some(true),
+                Some("failed") => Some(false),
+                _ => None,
+            },
+            from: non_empty(&self.from),
+            to: non_empty(&self.to),
+            search: non_empty(&self.q),
+            limit,
+        }
+    }
+
+    /// Query string pro odkaz na CSV export se stejnými filtry
+    fn query_string(&self) -> String {
+        [
+            ("user", &self.user),
+            ("endpoint_id", &self.endpoint_id),
+            ("action", &self.action),
+            ("status", &self.status),
+            ("from", &self.from),
+            ("to", &self.to),
+            ("q", &self.q),
+        ]
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_deref()
+                .filter(|v| !v.is_empty())
+                .map(|v| format!("{}={}", key, urlencoding::encode(v)))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+    }
+}
+
+fn require_admin(user: &CurrentUser) -> Result<(), (StatusCode, String)> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Administrator role required".to_string()))
+    }
+}
+
+pub async fn audit_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Query(query): Query<AuditQuery>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Audit log is not available in stateless mode".to_string()))?;
+
+    let entries = db
+        .get_audit_log(&query.to_filter(Some(PAGE_LIMIT)))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let endpoints = db
+        .get_endpoints()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let usernames = db
+        .get_audit_usernames()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let active = get_active_endpoint(&state, &jar).await;
+    let tpl = AuditTemplate {
+        ctx: build_ctx_with_endpoint(&state, active.as_ref()),
+        title: "Audit Log | Postgres Explorer".to_string(),
+        limit_reached: entries.len() as i64 >= PAGE_LIMIT,
+        entries,
+        endpoints,
+        usernames,
+        actions: ACTIONS.iter().map(|a| a.to_string()).collect(),
+        f_user: query.user.clone().unwrap_or_default(),
+        f_endpoint_id: query.endpoint_id.clone().unwrap_or_default(),
+        f_action: query.action.clone().unwrap_or_default(),
+        f_status: query.status.clone().unwrap_or_default(),
+        f_from: query.from.clone().unwrap_or_default(),
+        f_to: query.to.clone().unwrap_or_default(),
+        f_q: query.q.clone().unwrap_or_default(),
+        export_query: query.query_string(),
+    };
+    tpl.render()
+        .map(Html)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn export_csv(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Audit log is not available in stateless mode".to_string()))?;
+
+    let entries = db
+        .get_audit_log(&query.to_filter(None))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut csv = String::from("id,created_at,username,endpoint_id,endpoint,database,action,success,error,command\n");
+    for e in &entries {
+        let fields = [
+            e.id.to_string(),
+            e.created_at.clone(),
+            e.username.clone(),
+            e.endpoint_id.map(|id| id.to_string()).unwrap_or_default(),
+            e.endpoint_name.clone().unwrap_or_default(),
+            e.database_name.clone().unwrap_or_default(),
+            e.action.clone(),
+            e.success.to_string(),
+            e.error.clone().unwrap_or_default(),
+            e.command.clone(),
+        ];
+        let line = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+        csv.push_str(&line);
+        csv.push('\n');
+    }
+
+    let file_name = format!("audit_{}.csv", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,391 @@
+use askama::Template;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::{
+    generate_token, hash_password, hash_token, reload_endpoint_roles, safe_next_path, session_cookie,
+    verify_password, AuthMode, CurrentUser, Role, SESSION_COOKIE, SESSION_TTL_SECS,
+};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::templates::{LoginTemplate, UsersTemplate};
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+    pub password_confirm: Option<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateUserForm {
+    pub username: String,
+    pub password: String,
+    pub is_admin: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordForm {
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct GroupsForm {
+    pub groups: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EndpointRoleForm {
+    pub endpoint_id: i64,
+    pub subject_type: String,
+    pub subject: String,
+    pub role: String,
+}
+
+fn render_login(
+    state: &Arc<AppState>,
+    setup_required: bool,
+    error: Option<String>,
+    next: String,
+    username: String,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let tpl = LoginTemplate {
+        base_path: state.base_path.clone(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        setup_required,
+        error,
+        next,
+        username,
+    };
+    tpl.render()
+        .map(Html)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn local_db(state: &Arc<AppState>) -> Result<&crate::db::Database, (StatusCode, String)> {
+    if state.auth_mode != AuthMode::Local {
+        return Err((StatusCode::NOT_FOUND, "Local authentication is disabled".to_string()));
+    }
+    state
+        .db
+        .as_ref()
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Stateless mode".to_string()))
+}
+
+/// Přidělování rolí funguje i pro uživatele z proxy, potřebuje jen SQLite
+fn roles_db(state: &Arc<AppState>) -> Result<&crate::db::Database, (StatusCode, String)> {
+    if state.auth_mode == AuthMode::None {
+        return Err((StatusCode::NOT_FOUND, "Authentication is disabled".to_string()));
+    }
+    state
+        .db
+        .as_ref()
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Stateless mode".to_string()))
+}
+
+async fn start_session(
+    state: &Arc<AppState>,
+    jar: CookieJar,
+    user_id: i64,
+    next: Option<&str>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = local_db(state)?;
+    let token = generate_token().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db.create_session(user_id, &hash_token(&token), SESSION_TTL_SECS)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let jar = jar.add(session_cookie(token));
+    let target = base_path_url(state, &safe_next_path(next));
+    Ok((jar, Redirect::to(&target)).into_response())
+}
+
+pub async fn login_page(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LoginQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    if state.auth_mode != AuthMode::Local {
+        return Ok(Redirect::to(&base_path_url(&state, "/")).into_response());
+    }
+    let db = local_db(&state)?;
+    let setup_required = db
+        .count_users()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        == 0;
+    let next = safe_next_path(query.next.as_deref());
+    Ok(render_login(&state, setup_required, None, next, String::new())?.into_response())
+}
+
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = local_db(&state)?;
+    let user = db
+        .get_user_by_username(form.username.trim())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match user {
+        Some(user) if verify_password(&form.password, &user.password_hash) => {
+            tracing::info!("User {} logged in", user.username);
+            start_session(&state, jar, user.id, form.next.as_deref()).await
+        }
+        _ => {
+            tracing::warn!("Failed login attempt for user {}", form.username.trim());
+            let html = render_login(
+                &state,
+                false,
+                Some("Invalid username or password".to_string()),
+                safe_next_path(form.next.as_deref()),
+                form.username.trim().to_string(),
+            )?;
+            Ok((StatusCode::UNAUTHORIZED, html).into_response())
+        }
+    }
+}
+
+/// Vytvoření prvního administrátora (jen pokud zatím neexistuje žádný účet)
+pub async fn setup(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = local_db(&state)?;
+    let count = db
+        .count_users()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if count > 0 {
+        return Err((StatusCode::FORBIDDEN, "Setup has already been completed".to_string()));
+    }
+
+    let username = form.username.trim().to_string();
+    let error = if username.is_empty() || form.password.is_empty() {
+        Some("Username and password are required")
+    } else if form.password_confirm.as_deref() != Some(form.password.as_str()) {
+        Some("Passwords do not match")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        let html = render_login(
+            &state,
+            true,
+            Some(error.to_string()),
+            safe_next_path(form.next.as_deref()),
+            username,
+        )?;
+        return Ok((StatusCode::BAD_REQUEST, html).into_response());
+    }
+
+    let hash = hash_password(&form.password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Kontrola výše je jen pro UX; o vítězi souběžných požadavků rozhoduje podmíněný INSERT
+    let user_id = db
+        .create_first_admin(&username, &hash)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::CONFLICT, "Setup has already been completed".to_string()))?;
+    start_session(&state, jar, user_id, form.next.as_deref()).await
+}
+
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<Response, (StatusCode, String)> {
+    if let (Some(db), Some(cookie)) = (state.db.as_ref(), jar.get(SESSION_COOKIE)) {
+        db.delete_session(&hash_token(cookie.value()))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+    let target = base_path_url(&state, "/login");
+    Ok((jar, Redirect::to(&target)).into_response())
+}
+
+fn require_admin(user: &CurrentUser) -> Result<(), (StatusCode, String)> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Administrator role required".to_string()))
+    }
+}
+
+async fn render_users(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+    user: &CurrentUser,
+    error: Option<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = roles_db(state)?;
+    let users = if state.auth_mode == AuthMode::Local {
+        db.get_users()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    } else {
+        Vec::new()
+    };
+    let endpoints = db
+        .get_endpoints()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let endpoint_roles = db
+        .get_endpoint_roles()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let active = get_active_endpoint(state, jar).await;
+    let status = if error.is_some() { StatusCode::BAD_REQUEST } else { StatusCode::OK };
+    let tpl = UsersTemplate {
+        ctx: build_ctx_with_endpoint(state, active.as_ref()),
+        title: "Users | Postgres Explorer".to_string(),
+        users,
+        current_user_id: user.id.unwrap_or(-1),
+        error,
+        endpoints,
+        endpoint_roles,
+        default_role: state.default_role.as_str().to_string(),
+    };
+    let html = tpl
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((status, Html(html)).into_response())
+}
+
+pub async fn users_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    render_users(&state, &jar, &user, None).await
+}
+
+pub async fn create_user(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<CreateUserForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = local_db(&state)?;
+    let username = form.username.trim();
+    if username.is_empty() || form.password.is_empty() {
+        return render_users(&state, &jar, &user, Some("Username and password are required".to_string())).await;
+    }
+    let existing = db
+        .get_user_by_username(username)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if existing.is_some() {
+        return render_users(&state, &jar, &user, Some(format!("User {} already exists", username))).await;
+    }
+
+    let hash = hash_password(&form.password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db.create_user(username, &hash, form.is_admin.is_some())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
+
+pub async fn set_user_password(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<PasswordForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = local_db(&state)?;
+    if form.password.is_empty() {
+        return render_users(&state, &jar, &user, Some("Password must not be empty".to_string())).await;
+    }
+    let hash = hash_password(&form.password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    db.update_user_password(id, &hash)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
+
+pub async fn delete_user(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = local_db(&state)?;
+    if user.id == Some(id) {
+        return render_users(&state, &jar, &user, Some("You cannot delete your own account".to_string())).await;
+    }
+    db.delete_user(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
+
+pub async fn set_user_groups(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<GroupsForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = local_db(&state)?;
+    let groups = crate::auth::parse_groups(form.groups.as_deref()).join(",");
+    db.update_user_groups(id, if groups.is_empty() { None } else { Some(groups.as_str()) })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
+
+pub async fn set_endpoint_role(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<EndpointRoleForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = roles_db(&state)?;
+    let subject = form.subject.trim();
+    let role = match Role::parse(&form.role) {
+        Some(role) if !subject.is_empty() && matches!(form.subject_type.as_str(), "user" | "group") => role,
+        _ => {
+            return render_users(&state, &jar, &user, Some("Invalid role assignment".to_string())).await;
+        }
+    };
+    db.set_endpoint_role(form.endpoint_id, &form.subject_type, subject, role.as_str())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    reload_endpoint_roles(&state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
+
+pub async fn delete_endpoint_role(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let db = roles_db(&state)?;
+    db.delete_endpoint_role(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    reload_endpoint_roles(&state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Redirect::to(&base_path_url(&state, "/users")).into_response())
+}
@@ -5,9 +5,11 @@ use axum::response::Html;
 use axum::http::StatusCode;
 use axum::Json;
 use serde::{Deserialize, Serialize};
-use sqlx::Connection;
+use sqlx::postgres::PgPoolOptions;
 
-use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, connect_pg, build_pg_url, apply_connection_params, AppState};
+use crate::auth::{require_role, CurrentUser, Role};
+use crate::handlers::{audit, tls, build_ctx_with_endpoint, get_active_endpoint, connect_pg, resolve_conninfo, AppState};
+use crate::handlers::pool::open_pool;
 use crate::templates::BlueprintWizardTemplate;
 
 #[derive(Debug, Deserialize)]
@@ -128,23 +130,50 @@ pub async fn preview_blueprint(
 pub async fn execute_blueprint(
     State(state): State<Arc<AppState>>,
     jar: axum_extra::extract::CookieJar,
+    user: CurrentUser,
     Json(req): Json<BlueprintRequest>,
 ) -> Result<Json<BlueprintResponse>, (StatusCode, String)> {
     let active = get_active_endpoint(&state, &jar).await;
-    if let Some(ref endpoint) = active {
-        if !endpoint.enable_blueprint {
-            return Err((
-                StatusCode::FORBIDDEN,
-                "Blueprint wizard is not enabled for this connection".to_string(),
-            ));
-        }
-    } else {
+    let Some(endpoint) = active.clone() else {
         return Err((
             StatusCode::FORBIDDEN,
             "No active connection".to_string(),
         ));
+    };
+    if !endpoint.enable_blueprint {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Blueprint wizard is not enabled for this connection".to_string(),
+        ));
     }
+    require_role(&state, &user, endpoint.id, Role::Admin, "run blueprint")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    // Do audit logu jde SQL bez hesel (stejné jako preview)
+    let audit_sql = generate_blueprint_sql(
+        &req.app_name,
+        endpoint.username.as_deref().unwrap_or("postgres"),
+        req.schema_name.as_ref().unwrap_or(&req.app_name),
+        req.encoding.as_deref().unwrap_or("UTF8"),
+        req.lock_public_schema,
+        req.revoke_db_public,
+        req.set_search_path,
+        false,
+    );
+    let result = run_blueprint(state.clone(), active, req).await;
+    let error = match &result {
+        Ok(Json(response)) => response.error.clone(),
+        Err((_, e)) => Some(e.clone()),
+    };
+    audit::record(&state, &user, &endpoint, "blueprint", &audit_sql, error.as_deref()).await;
+    result
+}
 
+async fn run_blueprint(
+    state: Arc<AppState>,
+    active: Option<crate::db::models::Endpoint>,
+    req: BlueprintRequest,
+) -> Result<Json<BlueprintResponse>, (StatusCode, String)> {
     // Validate app name
     if !is_valid_identifier(&req.app_name) {
         return Ok(Json(BlueprintResponse {
@@ -210,32 +239,32 @@ pub async fn execute_blueprint(
 
     // Phase 2: Connect to new database and set up schema/permissions
     // Get password (same logic as in connect_pg)
-    let override_password = state
-        .active_override_password
-        .read()
-        .ok()
-        .and_then(|p| p.clone());
-
-    let password = if override_password.is_some() {
-        override_password
-    } else if let Some(db) = &state.db {
-        db.get_endpoint_password(&active).await
-    } else {
-        state.stateless_password.clone()
-    };
+    let password = crate::handlers::connection_password(&state, &active).await;
 
-    let base_url = build_pg_url(&active.url, active.username.as_deref(), password.as_deref());
-    let app_db_url = build_database_url(&base_url, &req.app_name);
+    let mut info = resolve_conninfo(&state, &active)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to prepare connection: {:#}", e)))?;
+    if info.password.is_none() {
+        info.password = password;
+    }
+    info.dbname = Some(req.app_name.clone());
 
     // Apply connection parameters (SSL mode, insecure, etc.)
-    let app_db_url = apply_connection_params(
-        app_db_url,
+    info.apply_endpoint_params(
         active.ssl_mode.as_deref(),
-        None, // Don't set search_path in URL for Phase 2
+        None, // Don't set search_path for Phase 2
         active.insecure,
     );
+    tls::endpoint_tls_files(&state, &active)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to prepare TLS certificates: {:#}", e)))?
+        .apply_to(&mut info);
 
-    let mut app_conn = sqlx::postgres::PgConnection::connect(&app_db_url)
+    let app_pool = open_pool(&info, PgPoolOptions::new().max_connections(1))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to new database: {}", e)))?;
+    let mut app_conn = app_pool
+        .acquire()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to connect to new database: {}", e)))?;
 
@@ -247,7 +276,7 @@ pub async fn execute_blueprint(
     );
 
     for (idx, stmt) in phase2_sql.iter().enumerate() {
-        if let Err(e) = sqlx::query(stmt).execute(&mut app_conn).await {
+        if let Err(e) = sqlx::query(stmt).execute(&mut *app_conn).await {
             return Ok(Json(BlueprintResponse {
                 success: false,
                 passwords: None,
@@ -459,14 +488,3 @@ fn generate_phase2_sql(
 
     sql
 }
-
-fn build_database_url(base_url: &str, database: &str) -> String {
-    // Parse URL and replace database name
-    if let Ok(mut parsed) = url::Url::parse(base_url) {
-        parsed.set_path(&format!("/{}", database));
-        parsed.to_string()
-    } else {
-        // Fallback: simple string replacement
-        base_url.rsplit_once('/').map(|(prefix, _)| format!("{}/{}", prefix, database)).unwrap_or_else(|| base_url.to_string())
-    }
-}
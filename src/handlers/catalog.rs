@@ -0,0 +1,859 @@
+//! Export a import katalogu endpointů (JSON, pg_service.conf, .pgpass)
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use askama::Template;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use axum_extra::extract::CookieJar;
+use base64::Engine;
+use rand::TryRngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::auth::CurrentUser;
+use crate::db::models::{CreateEndpoint, Endpoint, UpdateEndpoint, ENVIRONMENTS};
+use crate::handlers::conninfo::ConnInfo;
+use crate::handlers::{audit, build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::templates::{CatalogImportTemplate, CatalogPreviewRow};
+
+pub const CATALOG_VERSION: u32 = 1;
+
+/// Kontrolní hodnota, podle které při importu poznáme špatnou passphrase
+const PASSPHRASE_CHECK: &str = "postgres-explorer-catalog";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogFile {
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    /// Vyplněné, pokud export obsahuje tajné údaje zašifrované passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<CatalogEncryption>,
+    pub endpoints: Vec<CatalogEndpoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogEncryption {
+    /// argon2id + AES-256-GCM
+    pub kdf: String,
+    pub salt: String,
+    pub check: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogEndpoint {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub insecure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_path: Option<String>,
+    #[serde(default)]
+    pub enable_blueprint: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_port: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_root_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica_urls: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica_max_lag: Option<i64>,
+    /// Tajné údaje - jen v šifrovaném exportu (base64 nonce + ciphertext)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_passphrase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_key: Option<String>,
+}
+
+/// Klíč odvozený z passphrase exportu
+struct CatalogKey([u8; 32]);
+
+impl CatalogKey {
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive key from passphrase: {}", e))?;
+        Ok(Self(key))
+    }
+
+    fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0));
+        let mut nonce_bytes = [0u8; 12];
+        rand::rngs::OsRng
+            .try_fill_bytes(&mut nonce_bytes)
+            .context("Failed to generate encryption nonce")?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt secret"))?;
+        let mut payload = nonce_bytes.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(base64::prelude::BASE64_STANDARD.encode(payload))
+    }
+
+    fn decrypt(&self, encrypted: &str) -> Result<String> {
+        let payload = base64::prelude::BASE64_STANDARD
+            .decode(encrypted)
+            .context("Failed to decode encrypted secret")?;
+        if payload.len() < 12 {
+            anyhow::bail!("Encrypted secret is too short");
+        }
+        let (nonce_bytes, ciphertext) = payload.split_at(12);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Wrong passphrase or corrupted catalog"))?;
+        String::from_utf8(plaintext).context("Decrypted secret is not valid UTF-8")
+    }
+}
+
+/// Sestaví JSON katalog; s passphrase obsahuje i hesla, SSH passphrase a TLS klíče
+pub async fn export_catalog(state: &AppState, passphrase: Option<&str>) -> Result<CatalogFile> {
+    let db = state.db.as_ref().context("Stateless mode")?;
+    let (encryption, key) = match passphrase {
+        Some(passphrase) => {
+            let mut salt = [0u8; 16];
+            rand::rngs::OsRng
+                .try_fill_bytes(&mut salt)
+                .context("Failed to generate salt")?;
+            let key = CatalogKey::derive(passphrase, &salt)?;
+            let encryption = CatalogEncryption {
+                kdf: "argon2id".to_string(),
+                salt: base64::prelude::BASE64_STANDARD.encode(salt),
+                check: key.encrypt(PASSPHRASE_CHECK)?,
+            };
+            (Some(encryption), Some(key))
+        }
+        None => (None, None),
+    };
+
+    let mut endpoints = Vec::new();
+    for endpoint in db.get_endpoints().await? {
+        let mut item = CatalogEndpoint {
+            name: endpoint.name.clone(),
+            url: endpoint.url.clone(),
+            insecure: endpoint.insecure,
+            username: endpoint.username.clone(),
+            ssl_mode: endpoint.ssl_mode.clone(),
+            search_path: endpoint.search_path.clone(),
+            enable_blueprint: endpoint.enable_blueprint,
+            ssh_host: endpoint.ssh_host.clone(),
+            ssh_port: endpoint.ssh_port,
+            ssh_user: endpoint.ssh_user.clone(),
+            ssh_key_path: endpoint.ssh_key_path.clone(),
+            ssl_root_cert: endpoint.ssl_root_cert.clone(),
+            ssl_cert: endpoint.ssl_cert.clone(),
+            group_name: endpoint.group_name.clone(),
+            tags: endpoint.tags.clone(),
+            environment: endpoint.environment.clone(),
+            replica_urls: endpoint.replica_urls.clone(),
+            replica_max_lag: endpoint.replica_max_lag,
+            ..Default::default()
+        };
+        if let Some(key) = &key {
+            if let Some(password) = db.get_endpoint_password(&endpoint).await {
+                item.password = Some(key.encrypt(&password)?);
+            }
+            if let Some(passphrase) = db.get_endpoint_ssh_passphrase(&endpoint).await {
+                item.ssh_passphrase = Some(key.encrypt(&passphrase)?);
+            }
+            if let Some(ssl_key) = db.get_endpoint_ssl_key(&endpoint).await {
+                item.ssl_key = Some(key.encrypt(&ssl_key)?);
+            }
+        }
+        endpoints.push(item);
+    }
+
+    Ok(CatalogFile {
+        version: CATALOG_VERSION,
+        exported_at: Some(chrono::Utc::now().to_rfc3339()),
+        encryption,
+        endpoints,
+    })
+}
+
+/// Endpoint připravený k importu (tajné údaje už v čisté podobě)
+#[derive(Debug, Clone)]
+pub struct ImportEntry {
+    pub endpoint: CreateEndpoint,
+    /// pg_service/.pgpass neznají SSH, skupiny ani tagy - při přepisu se ponechají stávající
+    pub partial: bool,
+}
+
+/// Načte JSON katalog; šifrované tajné údaje vyžadují passphrase z exportu
+pub fn parse_catalog(content: &str, passphrase: Option<&str>) -> Result<Vec<ImportEntry>> {
+    let catalog: CatalogFile = serde_json::from_str(content).context("Invalid catalog JSON")?;
+    if catalog.version > CATALOG_VERSION {
+        anyhow::bail!("Unsupported catalog version {}", catalog.version);
+    }
+    let key = match &catalog.encryption {
+        Some(encryption) => {
+            let passphrase = passphrase.context("This catalog contains encrypted secrets, enter its passphrase")?;
+            if encryption.kdf != "argon2id" {
+                anyhow::bail!("Unsupported catalog encryption {}", encryption.kdf);
+            }
+            let salt = base64::prelude::BASE64_STANDARD
+                .decode(&encryption.salt)
+                .context("Invalid catalog salt")?;
+            let key = CatalogKey::derive(passphrase, &salt)?;
+            if key.decrypt(&encryption.check).ok().as_deref() != Some(PASSPHRASE_CHECK) {
+                anyhow::bail!("Wrong passphrase");
+            }
+            Some(key)
+        }
+        None => None,
+    };
+    let decrypt = |value: Option<String>| -> Result<Option<String>> {
+        match (value, &key) {
+            (Some(value), Some(key)) => key.decrypt(&value).map(Some),
+            _ => Ok(None),
+        }
+    };
+
+    catalog
+        .endpoints
+        .into_iter()
+        .map(|item| {
+            Ok(ImportEntry {
+                endpoint: CreateEndpoint {
+                    name: item.name,
+                    url: item.url,
+                    insecure: item.insecure,
+                    username: item.username,
+                    password: decrypt(item.password)?,
+                    ssl_mode: item.ssl_mode,
+                    search_path: item.search_path,
+                    enable_blueprint: item.enable_blueprint,
+                    ssh_host: item.ssh_host,
+                    ssh_port: item.ssh_port,
+                    ssh_user: item.ssh_user,
+                    ssh_key_path: item.ssh_key_path,
+                    ssh_passphrase: decrypt(item.ssh_passphrase)?,
+                    ssl_root_cert: item.ssl_root_cert,
+                    ssl_cert: item.ssl_cert,
+                    ssl_key: decrypt(item.ssl_key)?,
+                    group_name: item.group_name,
+                    tags: item.tags,
+                    environment: item.environment,
+                    replica_urls: item.replica_urls,
+                    replica_max_lag: item.replica_max_lag,
+                },
+                partial: false,
+            })
+        })
+        .collect()
+}
+
+/// Jeden řádek ~/.pgpass (`hostname:port:database:username:password`, `*` = cokoliv)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgPassEntry {
+    pub host: String,
+    pub port: String,
+    pub database: String,
+    pub user: String,
+    pub password: String,
+}
+
+impl PgPassEntry {
+    fn matches(&self, host: &str, port: &str, database: &str, user: &str) -> bool {
+        let field = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        field(&self.host, host) && field(&self.port, port) && field(&self.database, database) && field(&self.user, user)
+    }
+}
+
+pub fn parse_pgpass(content: &str) -> Vec<PgPassEntry> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            // `\:` a `\\` jsou escapované znaky uvnitř polí
+            let mut fields = vec![String::new()];
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some(next) = chars.next() {
+                            fields.last_mut().unwrap().push(next);
+                        }
+                    }
+                    ':' if fields.len() < 5 => fields.push(String::new()),
+                    c => fields.last_mut().unwrap().push(c),
+                }
+            }
+            if fields.len() != 5 {
+                return None;
+            }
+            let mut it = fields.into_iter();
+            Some(PgPassEntry {
+                host: it.next()?,
+                port: it.next()?,
+                database: it.next()?,
+                user: it.next()?,
+                password: it.next()?,
+            })
+        })
+        .collect()
+}
+
+/// Sekce pg_service.conf: název služby a její parametry
+pub type PgService = (String, Vec<(String, String)>);
+
+/// pg_service.conf: `[service]` sekce s `klíč=hodnota`
+pub fn parse_pg_service(content: &str) -> Result<Vec<PgService>> {
+    let mut services: Vec<PgService> = Vec::new();
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            services.push((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .with_context(|| format!("Line {}: expected key=value", index + 1))?;
+        let (_, params) = services
+            .last_mut()
+            .with_context(|| format!("Line {}: parameter outside of a [service] section", index + 1))?;
+        params.push((key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(services)
+}
+
+/// Endpointy z pg_service.conf (hesla doplní .pgpass); bez pg_service.conf vzniknou endpointy přímo z .pgpass
+pub fn libpq_entries(service_conf: Option<&str>, pgpass: Option<&str>) -> Result<Vec<ImportEntry>> {
+    let passwords = pgpass.map(parse_pgpass).unwrap_or_default();
+    let Some(service_conf) = service_conf else {
+        return Ok(passwords
+            .iter()
+            .filter(|p| p.host != "*")
+            .map(pgpass_entry)
+            .collect());
+    };
+
+    let mut entries = Vec::new();
+    for (name, params) in parse_pg_service(service_conf)? {
+        let mut conninfo = Vec::new();
+        let mut endpoint = CreateEndpoint {
+            name: name.clone(),
+            url: String::new(),
+            insecure: false,
+            username: None,
+            password: None,
+            ssl_mode: None,
+            search_path: None,
+            enable_blueprint: false,
+            ssh_host: None,
+            ssh_port: None,
+            ssh_user: None,
+            ssh_key_path: None,
+            ssh_passphrase: None,
+            ssl_root_cert: None,
+            ssl_cert: None,
+            ssl_key: None,
+            group_name: None,
+            tags: None,
+            environment: None,
+            replica_urls: None,
+            replica_max_lag: None,
+        };
+        for (key, value) in params {
+            match key.as_str() {
+                "user" => endpoint.username = Some(value),
+                "password" => endpoint.password = Some(value),
+                "sslmode" => endpoint.ssl_mode = Some(value),
+                "sslrootcert" => endpoint.ssl_root_cert = Some(value),
+                "sslcert" => endpoint.ssl_cert = Some(value),
+                "sslkey" => endpoint.ssl_key = Some(value),
+                "service" | "passfile" => {}
+                _ => conninfo.push(format!("{}='{}'", key, value.replace('\\', "\\\\").replace('\'', "\\'"))),
+            }
+        }
+        let info = ConnInfo::parse(&conninfo.join(" "))
+            .with_context(|| format!("Service [{}]: invalid connection parameters", name))?;
+        endpoint.url = info.to_uri();
+
+        if endpoint.password.is_none() {
+            let host = info.hosts.first().map(|h| h.host.clone()).unwrap_or_else(|| "localhost".to_string());
+            let port = info.hosts.first().map(|h| h.port.to_string()).unwrap_or_else(|| "5432".to_string());
+            let user = endpoint.username.clone().unwrap_or_default();
+            let database = info.dbname.clone().unwrap_or_else(|| user.clone());
+            endpoint.password = passwords
+                .iter()
+                .find(|p| p.matches(&host, &port, &database, &user))
+                .map(|p| p.password.clone());
+        }
+        entries.push(ImportEntry { endpoint, partial: true });
+    }
+    Ok(entries)
+}
+
+fn pgpass_entry(entry: &PgPassEntry) -> ImportEntry {
+    let port = if entry.port == "*" { "5432" } else { entry.port.as_str() };
+    let database = if entry.database == "*" { "postgres" } else { entry.database.as_str() };
+    let user = (entry.user != "*").then(|| entry.user.clone());
+    let host = if entry.host.contains(':') { format!("[{}]", entry.host) } else { entry.host.clone() };
+    let name = match &user {
+        Some(user) => format!("{}@{}:{}/{}", user, entry.host, port, database),
+        None => format!("{}:{}/{}", entry.host, port, database),
+    };
+    ImportEntry {
+        endpoint: CreateEndpoint {
+            name,
+            url: format!("postgresql://{}:{}/{}", host, port, urlencoding::encode(database)),
+            insecure: false,
+            username: user,
+            password: Some(entry.password.clone()),
+            ssl_mode: None,
+            search_path: None,
+            enable_blueprint: false,
+            ssh_host: None,
+            ssh_port: None,
+            ssh_user: None,
+            ssh_key_path: None,
+            ssh_passphrase: None,
+            ssl_root_cert: None,
+            ssl_cert: None,
+            ssl_key: None,
+            group_name: None,
+            tags: None,
+            environment: None,
+            replica_urls: None,
+            replica_max_lag: None,
+        },
+        partial: true,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportAction {
+    Create,
+    Update(i64),
+    Skip,
+    Invalid,
+}
+
+/// Co se s položkou při importu stane (podle jména endpointu a strategie)
+pub fn plan_import(entries: &[ImportEntry], existing: &[Endpoint], overwrite: bool) -> Vec<(ImportAction, String)> {
+    let mut seen: Vec<&str> = Vec::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let endpoint = &entry.endpoint;
+            if endpoint.name.trim().is_empty() {
+                return (ImportAction::Invalid, "Missing name".to_string());
+            }
+            if let Err(e) = ConnInfo::parse(&endpoint.url) {
+                return (ImportAction::Invalid, format!("Invalid connection string: {}", e));
+            }
+            if let Some(env) = &endpoint.environment
+                && !ENVIRONMENTS.contains(&env.as_str())
+            {
+                return (ImportAction::Invalid, format!("Invalid environment: {}", env));
+            }
+            if endpoint.ssl_key.is_some() && endpoint.ssl_cert.is_none() {
+                return (ImportAction::Invalid, "TLS client key without a client certificate".to_string());
+            }
+            if seen.contains(&endpoint.name.as_str()) {
+                return (ImportAction::Skip, "Duplicate name in import".to_string());
+            }
+            seen.push(&endpoint.name);
+            match existing.iter().find(|e| e.name == endpoint.name) {
+                None => (ImportAction::Create, String::new()),
+                Some(current) if overwrite => (ImportAction::Update(current.id), String::new()),
+                Some(_) => (ImportAction::Skip, "Already exists".to_string()),
+            }
+        })
+        .collect()
+}
+
+fn update_from(entry: &ImportEntry, current: &Endpoint) -> UpdateEndpoint {
+    let imported = entry.endpoint.clone();
+    // Částečný zdroj (pg_service/.pgpass) přepíše jen připojení, zbytek zůstane
+    let keep = |new: Option<String>, old: &Option<String>| {
+        if entry.partial { new.or_else(|| old.clone()) } else { new }
+    };
+    UpdateEndpoint {
+        name: Some(imported.name),
+        url: Some(imported.url),
+        insecure: Some(if entry.partial { current.insecure } else { imported.insecure }),
+        username: keep(imported.username, &current.username),
+        password: imported.password,
+        ssl_mode: keep(imported.ssl_mode, &current.ssl_mode),
+        search_path: keep(imported.search_path, &current.search_path),
+        enable_blueprint: Some(if entry.partial { current.enable_blueprint } else { imported.enable_blueprint }),
+        ssh_host: keep(imported.ssh_host, &current.ssh_host),
+        ssh_port: if entry.partial { current.ssh_port } else { imported.ssh_port },
+        ssh_user: keep(imported.ssh_user, &current.ssh_user),
+        ssh_key_path: keep(imported.ssh_key_path, &current.ssh_key_path),
+        ssh_passphrase: imported.ssh_passphrase,
+        clear_ssh_passphrase: false,
+        ssl_root_cert: keep(imported.ssl_root_cert, &current.ssl_root_cert),
+        ssl_cert: keep(imported.ssl_cert, &current.ssl_cert),
+        ssl_key: imported.ssl_key,
+        clear_ssl_key: false,
+        group_name: keep(imported.group_name, &current.group_name),
+        tags: keep(imported.tags, &current.tags),
+        environment: keep(imported.environment, &current.environment),
+        replica_urls: keep(imported.replica_urls, &current.replica_urls),
+        replica_max_lag: if entry.partial { current.replica_max_lag } else { imported.replica_max_lag },
+    }
+}
+
+fn require_admin(user: &CurrentUser) -> Result<(), (StatusCode, String)> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Only administrators can manage connections".to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportForm {
+    passphrase: Option<String>,
+}
+
+pub async fn export(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Form(form): Form<ExportForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    if state.db.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
+    }
+    let passphrase = form.passphrase.filter(|p| !p.is_empty());
+    if let Some(passphrase) = &passphrase
+        && passphrase.chars().count() < MIN_PASSPHRASE_LEN
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Passphrase must have at least {} characters", MIN_PASSPHRASE_LEN),
+        ));
+    }
+
+    let catalog = export_catalog(&state, passphrase.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    let body = serde_json::to_string_pretty(&catalog)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    audit::record_catalog(
+        &state,
+        &user,
+        &format!(
+            "Exported {} connections {}",
+            catalog.endpoints.len(),
+            if passphrase.is_some() { "with encrypted secrets" } else { "without secrets" }
+        ),
+        None,
+    )
+    .await;
+
+    let filename = format!("postgres-explorer-connections-{}.json", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize, Default)]
+pub struct ImportForm {
+    /// json | libpq
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    pgpass: String,
+    #[serde(default)]
+    passphrase: String,
+    /// merge | overwrite
+    #[serde(default)]
+    strategy: String,
+    /// Načíst ~/.pg_service.conf a ~/.pgpass na serveru, pokud nejsou vložené
+    use_local_files: Option<String>,
+}
+
+impl ImportForm {
+    fn overwrite(&self) -> bool {
+        self.strategy == "overwrite"
+    }
+
+    fn entries(&self) -> Result<Vec<ImportEntry>> {
+        let non_empty = |v: &str| Some(v.to_string()).filter(|v| !v.trim().is_empty());
+        match self.source.as_str() {
+            "libpq" => {
+                let mut service_conf = non_empty(&self.content);
+                let mut pgpass = non_empty(&self.pgpass);
+                if self.use_local_files.is_some() {
+                    if service_conf.is_none() {
+                        service_conf = read_local(&local_service_file());
+                    }
+                    if pgpass.is_none() {
+                        pgpass = read_local(&local_pgpass_file());
+                    }
+                }
+                if service_conf.is_none() && pgpass.is_none() {
+                    anyhow::bail!("Paste pg_service.conf and/or .pgpass content");
+                }
+                libpq_entries(service_conf.as_deref(), pgpass.as_deref())
+            }
+            _ => {
+                if self.content.trim().is_empty() {
+                    anyhow::bail!("Paste or upload an exported catalog");
+                }
+                parse_catalog(&self.content, non_empty(&self.passphrase).as_deref())
+            }
+        }
+    }
+}
+
+fn local_service_file() -> std::path::PathBuf {
+    std::env::var_os("PGSERVICEFILE")
+        .map(std::path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".pg_service.conf")))
+        .unwrap_or_default()
+}
+
+fn local_pgpass_file() -> std::path::PathBuf {
+    std::env::var_os("PGPASSFILE")
+        .map(std::path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".pgpass")))
+        .unwrap_or_default()
+}
+
+fn read_local(path: &std::path::Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().filter(|c| !c.trim().is_empty())
+}
+
+async fn render_import(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+    form: &ImportForm,
+    preview: Vec<CatalogPreviewRow>,
+    error: String,
+    message: String,
+) -> Result<Response, (StatusCode, String)> {
+    let active = get_active_endpoint(state, jar).await;
+    let tpl = CatalogImportTemplate {
+        ctx: build_ctx_with_endpoint(state, active.as_ref()),
+        title: "Import Connections".to_string(),
+        source: if form.source.is_empty() { "json".to_string() } else { form.source.clone() },
+        content: form.content.clone(),
+        pgpass: form.pgpass.clone(),
+        passphrase: form.passphrase.clone(),
+        strategy: if form.strategy.is_empty() { "merge".to_string() } else { form.strategy.clone() },
+        use_local_files: form.use_local_files.is_some(),
+        can_apply: preview.iter().any(|r| r.action == "create" || r.action == "update"),
+        preview,
+        error,
+        message,
+        service_file: local_service_file().display().to_string(),
+        pgpass_file: local_pgpass_file().display().to_string(),
+    };
+    tpl.render()
+        .map(|html| Html(html).into_response())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn import_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    if state.db.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
+    }
+    render_import(&state, &jar, &ImportForm::default(), Vec::new(), String::new(), String::new()).await
+}
+
+/// Náhled importu - nic nezapisuje
+pub async fn import_preview(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<ImportForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let Some(db) = state.db.as_ref() else {
+        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
+    };
+    let entries = match form.entries() {
+        Ok(entries) => entries,
+        Err(e) => return render_import(&state, &jar, &form, Vec::new(), format!("{:#}", e), String::new()).await,
+    };
+    let existing = db
+        .get_endpoints()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let plan = plan_import(&entries, &existing, form.overwrite());
+    let rows = entries
+        .iter()
+        .zip(plan)
+        .map(|(entry, (action, note))| preview_row(entry, action, note))
+        .collect::<Vec<_>>();
+    let error = if rows.is_empty() { "No connections found in the input".to_string() } else { String::new() };
+    render_import(&state, &jar, &form, rows, error, String::new()).await
+}
+
+fn preview_row(entry: &ImportEntry, action: ImportAction, note: String) -> CatalogPreviewRow {
+    let endpoint = &entry.endpoint;
+    CatalogPreviewRow {
+        name: endpoint.name.clone(),
+        url: endpoint.url.clone(),
+        username: endpoint.username.clone().unwrap_or_default(),
+        environment: endpoint.environment.clone().unwrap_or_default(),
+        group_name: endpoint.group_name.clone().unwrap_or_default(),
+        has_password: endpoint.password.is_some(),
+        has_ssh: endpoint.ssh_host.is_some(),
+        has_client_cert: endpoint.ssl_key.is_some(),
+        action: match action {
+            ImportAction::Create => "create",
+            ImportAction::Update(_) => "update",
+            ImportAction::Skip => "skip",
+            ImportAction::Invalid => "invalid",
+        }
+        .to_string(),
+        note,
+    }
+}
+
+/// Zapíše import do SQLite (stejný plán jako v náhledu)
+pub async fn import_apply(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<ImportForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let Some(db) = state.db.as_ref() else {
+        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
+    };
+    let entries = match form.entries() {
+        Ok(entries) => entries,
+        Err(e) => return render_import(&state, &jar, &form, Vec::new(), format!("{:#}", e), String::new()).await,
+    };
+    let existing = db
+        .get_endpoints()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let by_id: HashMap<i64, &Endpoint> = existing.iter().map(|e| (e.id, e)).collect();
+    let plan = plan_import(&entries, &existing, form.overwrite());
+
+    let (mut created, mut updated, mut skipped) = (0, 0, 0);
+    let mut failures = Vec::new();
+    for (entry, (action, _)) in entries.iter().zip(plan) {
+        let result = match action {
+            ImportAction::Create => db.create_endpoint(entry.endpoint.clone()).await.map(|_| created += 1),
+            ImportAction::Update(id) => {
+                let update = update_from(entry, by_id[&id]);
+                let result = db.update_endpoint(id, update).await.map(|_| updated += 1);
+                state.pg_pools.invalidate_endpoint(id).await;
+                state.db_sessions.remove_endpoint(id);
+                state.ssh_tunnels.close(id).await;
+                crate::handlers::tls::remove_endpoint_files(id);
+                result
+            }
+            ImportAction::Skip | ImportAction::Invalid => {
+                skipped += 1;
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            failures.push(format!("{}: {:#}", entry.endpoint.name, e));
+        }
+    }
+
+    let summary = format!(
+        "Imported connections ({}, {}): {} created, {} updated, {} skipped",
+        if form.source == "libpq" { "pg_service.conf/.pgpass" } else { "JSON catalog" },
+        if form.overwrite() { "overwrite" } else { "merge" },
+        created,
+        updated,
+        skipped
+    );
+    let error = failures.join("; ");
+    audit::record_catalog(&state, &user, &summary, (!error.is_empty()).then_some(error.as_str())).await;
+
+    let cleared = ImportForm {
+        source: form.source.clone(),
+        strategy: form.strategy.clone(),
+        ..Default::default()
+    };
+    render_import(&state, &jar, &cleared, Vec::new(), error, summary).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pgpass() {
+        let entries = parse_pgpass("# comment\ndb1:5432:app:alice:s3cr\\:et\n*:*:*:bob:pw\nbroken:line\n");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].password, "s3cr:et");
+        assert!(entries[1].matches("any", "6543", "x", "bob"));
+        assert!(!entries[0].matches("db1", "5432", "app", "bob"));
+    }
+
+    #[test]
+    fn test_libpq_entries_use_pgpass_passwords() {
+        let service = "[billing]\nhost=db1\nport=5433\ndbname=billing\nuser=alice\nsslmode=require\n\n[local]\nhost=/tmp\n";
+        let pgpass = "db1:5433:billing:alice:secret\n";
+        let entries = libpq_entries(Some(service), Some(pgpass)).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].endpoint.name, "billing");
+        assert_eq!(entries[0].endpoint.password.as_deref(), Some("secret"));
+        assert_eq!(entries[0].endpoint.ssl_mode.as_deref(), Some("require"));
+        let info = ConnInfo::parse(&entries[0].endpoint.url).unwrap();
+        assert_eq!(info.hosts[0].host, "db1");
+        assert_eq!(info.hosts[0].port, 5433);
+        assert_eq!(info.database(), "billing");
+        assert!(ConnInfo::parse(&entries[1].endpoint.url).unwrap().hosts[0].is_socket());
+
+        let only_pgpass = libpq_entries(None, Some("db2:*:*:carol:pw\n*:*:*:*:x\n")).unwrap();
+        assert_eq!(only_pgpass.len(), 1);
+        assert_eq!(only_pgpass[0].endpoint.url, "postgresql://db2:5432/postgres");
+    }
+
+    #[test]
+    fn test_catalog_key_roundtrip() {
+        let key = CatalogKey::derive("correct horse", b"0123456789abcdef").unwrap();
+        let encrypted = key.encrypt("pw").unwrap();
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "pw");
+        let wrong = CatalogKey::derive("wrong horse", b"0123456789abcdef").unwrap();
+        assert!(wrong.decrypt(&encrypted).is_err());
+    }
+}
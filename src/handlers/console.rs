@@ -1,4 +1,4 @@
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::response::{Html, Sse};
 use axum::http::StatusCode;
 use axum::Json;
@@ -16,10 +16,12 @@ use tokio_postgres::{NoTls, Client};
 use postgres_openssl::MakeTlsConnector;
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
 
-use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::auth::{require_role, CurrentUser, Role};
+use crate::handlers::db_session::in_current_session;
+use crate::handlers::{audit, build_ctx_with_endpoint, get_active_endpoint, jobs, resolve_conninfo, AppState};
 use crate::templates::ConsoleTemplate;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ExecuteRequest {
     pub query: String,
     pub read_only: bool,
@@ -108,11 +110,31 @@ pub async fn console(
 pub async fn execute_query(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Json(req): Json<ExecuteRequest>,
 ) -> Result<Json<ExecuteResponse>, (StatusCode, String)> {
     let active = get_active_endpoint(&state, &jar)
         .await
         .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    let job_id = start_query(&state, active, user, req).await?;
+    Ok(Json(ExecuteResponse { job_id }))
+}
+
+/// Ověří roli a destruktivní příkazy a spustí dotaz na pozadí (i re-run z /jobs)
+pub async fn start_query(
+    state: &Arc<AppState>,
+    active: crate::db::models::Endpoint,
+    user: CurrentUser,
+    req: ExecuteRequest,
+) -> Result<String, (StatusCode, String)> {
+    // Operátor smí jen read-only dotazy, zápis vyžaduje admin roli
+    let (required, action) = if req.read_only {
+        (Role::Operator, "run read-only console queries")
+    } else {
+        (Role::Admin, "run write queries in the console")
+    };
+    require_role(state, &user, active.id, required, action)
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
 
     // Check for destructive commands
     let destructive_check = is_destructive_query(&req.query);
@@ -126,34 +148,21 @@ pub async fn execute_query(
         ));
     }
 
-    // Generate unique job ID
-    let job_id = format!("console_{}", uuid::Uuid::new_v4());
-
-    // Create job entry
-    let job = crate::handlers::ExportJob {
-        job_id: job_id.clone(),
-        status: crate::handlers::JobStatus::Running,
-        logs: std::collections::VecDeque::new(),
-        started_at: std::time::SystemTime::now(),
-        completed_at: None,
-        file_path: None,
-        error: None,
-    };
-
-    state.export_jobs.write().await.insert(job_id.clone(), job);
+    let job_id = jobs::register(state, "console", &active, &user, &req, Some(req.query.clone())).await;
 
     // Spawn background task - choose mode
     let state_clone = state.clone();
     let job_id_clone = job_id.clone();
-    tokio::spawn(async move {
+    tokio::spawn(in_current_session(async move {
         if req.safe_mode {
-            run_safe_query(state_clone, job_id_clone, active, req).await;
+            run_safe_query(state_clone.clone(), job_id_clone.clone(), active.clone(), req).await;
         } else {
-            run_psql_query(state_clone, job_id_clone, active, req).await;
+            run_psql_query(state_clone.clone(), job_id_clone.clone(), active.clone(), req).await;
         }
-    });
+        audit::record_job(&state_clone, &user, &active, "console", &job_id_clone).await;
+    }));
 
-    Ok(Json(ExecuteResponse { job_id }))
+    Ok(job_id)
 }
 
 async fn run_psql_query(
@@ -163,27 +172,31 @@ async fn run_psql_query(
     req: ExecuteRequest,
 ) {
     // Get password
-    let password = if let Some(db) = &state.db {
-        db.get_endpoint_password(&endpoint).await
-    } else {
-        state.stateless_password.clone()
-    };
+    let password = crate::handlers::connection_password(&state, &endpoint).await;
 
-    let conn_parts = crate::handlers::export::parse_connection_url(&endpoint.url);
+    let info = match resolve_conninfo(&state, &endpoint).await {
+        Ok(info) => info,
+        Err(e) => {
+            let error = format!("Failed to prepare connection: {:#}", e);
+            append_log(&state, &job_id, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+    let tls = match crate::handlers::tls::endpoint_tls_files(&state, &endpoint).await {
+        Ok(tls) => tls,
+        Err(e) => {
+            let error = format!("Failed to prepare TLS certificates: {:#}", e);
+            append_log(&state, &job_id, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
 
     let mut cmd = Command::new("psql");
 
-    if let Some(ref pw) = password {
-        cmd.env("PGPASSWORD", pw);
-    }
-
-    cmd.arg("-h").arg(&conn_parts.host);
-    cmd.arg("-p").arg(&conn_parts.port);
-    cmd.arg("-d").arg(&conn_parts.database);
-
-    if let Some(username) = &endpoint.username {
-        cmd.arg("-U").arg(username);
-    }
+    tls.apply_env(&mut cmd, &endpoint);
+    crate::handlers::export::set_connection_args(&mut cmd, &info, password.as_deref());
 
     // psql options for better output
     cmd.arg("--no-psqlrc");  // Don't load user config
@@ -193,6 +206,8 @@ async fn run_psql_query(
 
     // Set environment to prevent buffering issues
     cmd.env("PAGER", "cat");  // No pager
+    jobs::own_process_group(&mut cmd);
+    let cancel = jobs::cancel_token(&state, &job_id).await;
 
     append_log(&state, &job_id, "🚀 Starting query execution...".to_string()).await;
     if req.read_only {
@@ -286,14 +301,19 @@ async fn run_psql_query(
             });
 
             // Wait for process with timeout (60 seconds max)
-            let wait_future = child.wait();
+            let wait_future = jobs::wait_or_cancel(&mut child, &cancel);
             let wait_result = tokio::time::timeout(
                 Duration::from_secs(60),
                 wait_future
             ).await;
 
             match wait_result {
-                Ok(Ok(status)) => {
+                Ok(None) => {
+                    append_log(&state, &job_id, "".to_string()).await;
+                    append_log(&state, &job_id, "🛑 Query cancelled".to_string()).await;
+                    complete_job(&state, &job_id, None, Some(jobs::CANCELLED.to_string())).await;
+                }
+                Ok(Some(Ok(status))) => {
                     if status.success() {
                         append_log(&state, &job_id, "".to_string()).await;
                         append_log(&state, &job_id, "✅ Query executed successfully!".to_string()).await;
@@ -305,13 +325,14 @@ async fn run_psql_query(
                         complete_job(&state, &job_id, None, Some(error)).await;
                     }
                 }
-                Ok(Err(e)) => {
+                Ok(Some(Err(e))) => {
                     let error = format!("Failed to wait for psql process: {}", e);
                     append_log(&state, &job_id, format!("❌ {}", error)).await;
                     complete_job(&state, &job_id, None, Some(error)).await;
                 }
                 Err(_) => {
                     // Timeout - force kill the process
+                    jobs::terminate(&mut child).await;
                     append_log(&state, &job_id, "".to_string()).await;
                     append_log(&state, &job_id, "⏱️  Query timeout (60 seconds) - process killed".to_string()).await;
                     let error = "Query timeout (60 seconds)".to_string();
@@ -340,22 +361,48 @@ async fn append_log(state: &Arc<AppState>, job_id: &str, line: String) {
 async fn complete_job(state: &Arc<AppState>, job_id: &str, file_path: Option<String>, error: Option<String>) {
     let mut jobs = state.export_jobs.write().await;
     if let Some(job) = jobs.get_mut(job_id) {
-        job.status = if error.is_some() {
-            crate::handlers::JobStatus::Failed
-        } else {
-            crate::handlers::JobStatus::Completed
+        job.status = match &error {
+            None => crate::handlers::JobStatus::Completed,
+            Some(_) if job.cancel.is_cancelled() => crate::handlers::JobStatus::Cancelled,
+            Some(_) => crate::handlers::JobStatus::Failed,
         };
         job.completed_at = Some(std::time::SystemTime::now());
         job.file_path = file_path;
         job.error = error;
     }
+    drop(jobs);
+    jobs::finish(state, job_id).await;
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogStreamQuery {
+    /// Zavření streamu (odchod ze stránky) zruší běžící dotaz
+    #[serde(default)]
+    pub cancel_on_close: bool,
 }
 
 pub async fn stream_console_logs(
     State(state): State<Arc<AppState>>,
+    user: CurrentUser,
     axum::extract::Path(job_id): axum::extract::Path<String>,
-) -> Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
-    let stream = stream::unfold(0usize, move |last_index| {
+    Query(query): Query<LogStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>>, (StatusCode, String)> {
+    let role = jobs::job_role(&state, &user, &job_id, Role::Viewer, "view job logs").await?;
+    // Guard je ve stavu streamu; axum stream zahodí, když klient spojení zavře.
+    // Jen u dotazů z konzole a jen pro toho, kdo by je směl zrušit i tlačítkem.
+    let guard = if query.cancel_on_close && job_id.starts_with("console_") {
+        let required = jobs::cancel_role(&job_id);
+        if role < required {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Permission denied: cancelling jobs requires the {} role on this connection", required.as_str()),
+            ));
+        }
+        Some(jobs::cancel_token(&state, &job_id).await.drop_guard())
+    } else {
+        None
+    };
+    let stream = stream::unfold((0usize, guard), move |(last_index, guard)| {
         let state = state.clone();
         let job_id = job_id.clone();
 
@@ -367,20 +414,20 @@ pub async fn stream_console_logs(
                 if let Some(job) = jobs.get(&job_id) {
                     let logs: Vec<String> = job.logs.iter().skip(last_index).cloned().collect();
                     let new_index = last_index + logs.len();
-                    let is_done = matches!(job.status, crate::handlers::JobStatus::Completed | crate::handlers::JobStatus::Failed);
+                    let is_done = job.status.is_done();
                     drop(jobs);
 
                     if !logs.is_empty() {
                         let data = logs.join("\n");
                         let event = axum::response::sse::Event::default().data(data);
-                        return Some((Ok(event), new_index));
+                        return Some((Ok(event), (new_index, guard)));
                     }
 
                     // Send keepalive ping
                     if !is_done && last_index % 50 == 0 {
                         let event = axum::response::sse::Event::default()
                             .comment("keepalive");
-                        return Some((Ok(event), new_index));
+                        return Some((Ok(event), (new_index, guard)));
                     }
 
                     // Check if job is done
@@ -394,7 +441,7 @@ pub async fn stream_console_logs(
         }
     });
 
-    Sse::new(stream)
+    Ok(Sse::new(stream))
 }
 
 pub async fn check_destructive(
@@ -481,16 +528,42 @@ pub async fn clear_history(
     Ok(StatusCode::OK)
 }
 
-// Helper function to connect with TLS
-async fn connect_with_tls(conn_str: &str, insecure: bool) -> Result<Client, String> {
+/// Klient safe mode i s tím, jak vznikl - zrušení dotazu musí jít na stejný uzel se stejným TLS
+struct SafeClient {
+    client: Client,
+    /// Connection string jen s hostem, ke kterému se klient připojil
+    conn_str: String,
+    tls: Option<MakeTlsConnector>,
+}
+
+// Helper function to build the TLS connector
+fn tls_connector(
+    ssl_mode: &str,
+    insecure: bool,
+    tls: &crate::handlers::tls::TlsFiles,
+) -> Result<MakeTlsConnector, String> {
     let mut builder = SslConnector::builder(SslMethod::tls())
         .map_err(|e| format!("Failed to create SSL connector: {}", e))?;
 
     if insecure {
         builder.set_verify(SslVerifyMode::NONE);
     }
+    tls.configure_connector(&mut builder)
+        .map_err(|e| format!("{:#}", e))?;
+
+    let mut tls_connector = MakeTlsConnector::new(builder.build());
+    if ssl_mode == "verify-ca" {
+        // verify-ca ověřuje jen CA, ne hostname
+        tls_connector.set_callback(|config, _| {
+            config.set_verify_hostname(false);
+            Ok(())
+        });
+    }
+    Ok(tls_connector)
+}
 
-    let tls_connector = MakeTlsConnector::new(builder.build());
+// Helper function to connect with TLS
+async fn connect_with_tls(conn_str: &str, tls_connector: MakeTlsConnector) -> Result<Client, String> {
     let (client, connection) = tokio_postgres::connect(conn_str, tls_connector)
         .await
         .map_err(|e| format!("Failed to connect: {}", e))?;
@@ -519,6 +592,26 @@ async fn connect_no_tls(conn_str: &str) -> Result<Client, String> {
     Ok(client)
 }
 
+async fn open_client(conn_str: &str, tls: Option<&MakeTlsConnector>) -> Result<Client, String> {
+    match tls {
+        Some(tls) => connect_with_tls(conn_str, tls.clone()).await,
+        None => connect_no_tls(conn_str).await,
+    }
+}
+
+/// Zkouší hosty v pořadí jako libpq, ale zapamatuje si, ke kterému se klient připojil
+async fn connect_safe(info: &crate::handlers::conninfo::ConnInfo, tls: Option<MakeTlsConnector>) -> Result<SafeClient, String> {
+    let mut errors = Vec::new();
+    for index in 0..info.hosts.len().max(1) {
+        let conn_str = info.with_single_host(index).to_tokio_config();
+        match open_client(&conn_str, tls.as_ref()).await {
+            Ok(client) => return Ok(SafeClient { client, conn_str, tls }),
+            Err(e) => errors.push(e),
+        }
+    }
+    Err(errors.join("; "))
+}
+
 // Safe Query mode - using tokio-postgres with row limit
 async fn run_safe_query(
     state: Arc<AppState>,
@@ -529,55 +622,60 @@ async fn run_safe_query(
     const MAX_ROWS: usize = 1000;
 
     // Get password
-    let password = if let Some(db) = &state.db {
-        db.get_endpoint_password(&endpoint).await
+    let password = crate::handlers::connection_password(&state, &endpoint).await;
+
+    // Read-only dotazy můžou jít na repliku (stejný výběr jako u prohlížení)
+    let resolved = if req.read_only {
+        crate::handlers::replica::resolve_read_conninfo(&state, &endpoint).await
     } else {
-        state.stateless_password.clone()
+        resolve_conninfo(&state, &endpoint).await.map(|info| (info, None))
     };
-
-    let conn_parts = crate::handlers::export::parse_connection_url(&endpoint.url);
-
-    // Build connection string
-    let mut conn_str = format!(
-        "host={} port={} dbname={}",
-        conn_parts.host, conn_parts.port, conn_parts.database
-    );
-
-    if let Some(ref username) = endpoint.username {
-        conn_str.push_str(&format!(" user={}", username));
-    }
-
-    if let Some(ref pw) = password {
-        conn_str.push_str(&format!(" password={}", pw));
+    let (mut info, served_by) = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            let error = format!("Failed to prepare connection: {:#}", e);
+            append_log(&state, &job_id, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+    if info.password.is_none() {
+        info.password = password;
     }
-
-    // SSL mode
-    let ssl_mode = endpoint.ssl_mode.as_deref().unwrap_or("prefer");
-    conn_str.push_str(&format!(" sslmode={}", ssl_mode));
+    // search_path se nastavuje níže přes SET
+    info.apply_endpoint_params(endpoint.ssl_mode.as_deref(), None, endpoint.insecure);
 
     // Statement timeout
-    conn_str.push_str(" options='-c statement_timeout=30s'");
+    info.append_options("-c statement_timeout=30s");
+
+    // tokio-postgres zná jen disable/prefer/require, ověření certifikátu dělá OpenSSL
+    let ssl_mode = info.param("sslmode").unwrap_or("prefer").to_string();
 
     append_log(&state, &job_id, "🚀 Starting Safe Query execution...".to_string()).await;
     append_log(&state, &job_id, "🔒 Auto-stops at 1000 rows".to_string()).await;
     if req.read_only {
         append_log(&state, &job_id, "🔒 Running in READ-ONLY mode".to_string()).await;
     }
+    if let Some(served_by) = served_by {
+        append_log(&state, &job_id, format!("🛰 Served by {}", served_by)).await;
+    }
     append_log(&state, &job_id, "".to_string()).await;
 
     // Connect to database
-    let client = if ssl_mode == "disable" {
-        match connect_no_tls(&conn_str).await {
-            Ok(c) => c,
-            Err(error) => {
+    let tls = if ssl_mode == "disable" {
+        None
+    } else {
+        let files = match crate::handlers::tls::endpoint_tls_files(&state, &endpoint).await {
+            Ok(files) => files,
+            Err(e) => {
+                let error = format!("Failed to prepare TLS certificates: {:#}", e);
                 append_log(&state, &job_id, format!("❌ {}", error)).await;
                 complete_job(&state, &job_id, None, Some(error)).await;
                 return;
             }
-        }
-    } else {
-        match connect_with_tls(&conn_str, endpoint.insecure).await {
-            Ok(c) => c,
+        };
+        match tls_connector(&ssl_mode, endpoint.insecure, &files) {
+            Ok(connector) => Some(connector),
             Err(error) => {
                 append_log(&state, &job_id, format!("❌ {}", error)).await;
                 complete_job(&state, &job_id, None, Some(error)).await;
@@ -585,6 +683,15 @@ async fn run_safe_query(
             }
         }
     };
+    let safe = match connect_safe(&info, tls).await {
+        Ok(safe) => safe,
+        Err(error) => {
+            append_log(&state, &job_id, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+    let client = &safe.client;
 
     // Apply search_path if configured (safe mode)
     if let Some(path) = endpoint.search_path.as_deref() {
@@ -617,7 +724,26 @@ async fn run_safe_query(
     append_log(&state, &job_id, format!("Executing: {}", req.query)).await;
     append_log(&state, &job_id, "".to_string()).await;
 
-    match execute_query_with_limit(&client, &req.query, MAX_ROWS, &state, &job_id).await {
+    let cancel = jobs::cancel_token(&state, &job_id).await;
+    let backend_pid: Option<i32> = client
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .ok()
+        .map(|row| row.get(0));
+
+    let result = tokio::select! {
+        result = execute_query_with_limit(client, &req.query, MAX_ROWS, &state, &job_id) => Some(result),
+        _ = cancel.cancelled() => None,
+    };
+    let Some(result) = result else {
+        cancel_backend(&safe, backend_pid).await;
+        append_log(&state, &job_id, "".to_string()).await;
+        append_log(&state, &job_id, "🛑 Query cancelled".to_string()).await;
+        complete_job(&state, &job_id, None, Some(jobs::CANCELLED.to_string())).await;
+        return;
+    };
+
+    match result {
         Ok(row_count) => {
             append_log(&state, &job_id, "".to_string()).await;
             if row_count >= MAX_ROWS {
@@ -635,6 +761,34 @@ async fn run_safe_query(
     }
 }
 
+/// Zruší běžící dotaz: cancel request jako Ctrl+C v psql, když neprojde, pg_cancel_backend
+/// přes nové spojení na stejný uzel (replika nebo host multi-host URL, ne primární pool)
+async fn cancel_backend(safe: &SafeClient, backend_pid: Option<i32>) {
+    let token = safe.client.cancel_token();
+    let sent = match &safe.tls {
+        Some(tls) => token.cancel_query(tls.clone()).await,
+        None => token.cancel_query(NoTls).await,
+    };
+    let Err(e) = sent else {
+        return;
+    };
+    tracing::warn!("Cancel request failed ({}), falling back to pg_cancel_backend", e);
+    let Some(pid) = backend_pid else {
+        return;
+    };
+    let cancelled = match open_client(&safe.conn_str, safe.tls.as_ref()).await {
+        Ok(client) => client
+            .query_one("SELECT pg_cancel_backend($1)", &[&pid])
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = cancelled {
+        tracing::warn!("pg_cancel_backend({}) failed: {}", pid, e);
+    }
+}
+
 async fn execute_query_with_limit(
     client: &Client,
     query: &str,
@@ -6,13 +6,40 @@ use sqlx::Row;
 use std::sync::Arc;
 use axum_extra::extract::CookieJar;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState};
+use crate::auth::{require_role, CurrentUser, Role};
+use crate::handlers::{audit, base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState};
+use crate::handlers::replica::connect_pg_read;
 use crate::templates::{ConnectionInfo, ConnectionState, DashboardTemplate, DatabaseInfo, TopTable};
 use crate::utils::format::bytes_to_human;
 
+/// Verze, velikost databáze a počty objektů (dashboard i /metrics)
+pub const OVERVIEW_SQL: &str = r#"
+    SELECT version() as server_version,
+           pg_database_size(current_database()) as total_size,
+           (SELECT count(*) FROM pg_namespace WHERE nspname NOT IN ('pg_catalog','information_schema')) as schema_count,
+           (SELECT count(*) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.relkind='r' AND n.nspname NOT IN ('pg_catalog','information_schema')) as table_count,
+           (SELECT count(*) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.relkind='i' AND n.nspname NOT IN ('pg_catalog','information_schema')) as index_count
+    "#;
+
+/// Cache hit ratio aktuální databáze v procentech
+pub const CACHE_HIT_RATIO_SQL: &str = r#"
+    SELECT
+        sum(blks_hit)::float / NULLIF(sum(blks_hit + blks_read), 0)::float * 100 as cache_hit_ratio
+    FROM pg_stat_database
+    WHERE datname = current_database()
+    "#;
+
+/// Aktivní spojení a max_connections
+pub const CONNECTIONS_SQL: &str = r#"
+    SELECT
+        (SELECT count(*) FROM pg_stat_activity WHERE state = 'active') as active_connections,
+        (SELECT setting::int FROM pg_settings WHERE name = 'max_connections') as max_connections
+    "#;
+
 pub async fn dashboard(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
 ) -> Result<Response, (axum::http::StatusCode, String)> {
     let active = get_active_endpoint(&state, &jar).await;
     if active.is_none() {
@@ -54,19 +81,9 @@ pub async fn dashboard(
         options: "-".to_string(),
     };
 
-    if let Ok(pg) = connect_pg(&state, &active).await {
+    if let Ok(pg) = connect_pg_read(&state, &active).await {
         // Základní info
-        if let Ok(row) = sqlx::query(
-            r#"
-            SELECT version() as server_version,
-                   pg_database_size(current_database()) as total_size,
-                   (SELECT count(*) FROM pg_namespace WHERE nspname NOT IN ('pg_catalog','information_schema')) as schema_count,
-                   (SELECT count(*) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.relkind='r' AND n.nspname NOT IN ('pg_catalog','information_schema')) as table_count,
-                   (SELECT count(*) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.relkind='i' AND n.nspname NOT IN ('pg_catalog','information_schema')) as index_count
-            "#,
-        )
-        .fetch_one(&pg)
-        .await
+        if let Ok(row) = sqlx::query(OVERVIEW_SQL).fetch_one(&pg).await
         {
             server_version = row.get::<String, _>("server_version");
             let size: i64 = row.get("total_size");
@@ -115,16 +132,7 @@ pub async fn dashboard(
         }
 
         // Cache hit ratio
-        if let Ok(row) = sqlx::query(
-            r#"
-            SELECT
-                sum(blks_hit)::float / NULLIF(sum(blks_hit + blks_read), 0)::float * 100 as cache_hit_ratio
-            FROM pg_stat_database
-            WHERE datname = current_database()
-            "#,
-        )
-        .fetch_one(&pg)
-        .await
+        if let Ok(row) = sqlx::query(CACHE_HIT_RATIO_SQL).fetch_one(&pg).await
         {
             if let Ok(ratio) = row.try_get::<f64, _>("cache_hit_ratio") {
                 cache_hit_ratio = ratio;
@@ -133,15 +141,7 @@ pub async fn dashboard(
         }
 
         // Active connections
-        if let Ok(row) = sqlx::query(
-            r#"
-            SELECT
-                (SELECT count(*) FROM pg_stat_activity WHERE state = 'active') as active_connections,
-                (SELECT setting::int FROM pg_settings WHERE name = 'max_connections') as max_connections
-            "#,
-        )
-        .fetch_one(&pg)
-        .await
+        if let Ok(row) = sqlx::query(CONNECTIONS_SQL).fetch_one(&pg).await
         {
             active_connections = row.get::<i64, _>("active_connections") as i32;
             max_connections = row.get::<i32, _>("max_connections");
@@ -300,13 +300,16 @@ pub async fn dashboard(
         guard.insert(active.id, databases_menu_enabled);
     }
 
+    let pg_pools = state.pg_pools.stats().await;
+    let failed_schedules = crate::handlers::schedules::failed_schedules(&state, &user).await;
+
     let tpl = DashboardTemplate {
         ctx: build_ctx_with_endpoint(&state, Some(&active)),
         title: "Dashboard | Postgres Explorer".to_string(),
         server_name: active.name,
         server_version,
         active_endpoint_id: active.id,
-        in_memory_active: state.active_override.read().ok().and_then(|g| g.clone()).is_some(),
+        in_memory_active: crate::handlers::db_session::current_override(&state, active.id).is_some(),
         schema_count,
         table_count,
         index_count,
@@ -321,6 +324,8 @@ pub async fn dashboard(
         databases,
         connection_states,
         conninfo,
+        pg_pools,
+        failed_schedules,
     };
 
     tpl.render()
@@ -338,6 +343,7 @@ pub struct AnalyzeResponse {
 pub async fn analyze_table(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<AnalyzeResponse> {
     let active = match get_active_endpoint(&state, &jar).await {
@@ -350,6 +356,13 @@ pub async fn analyze_table(
         }
     };
 
+    if let Err(e) = require_role(&state, &user, active.id, Role::Operator, "ANALYZE") {
+        return Json(AnalyzeResponse {
+            success: false,
+            error: Some(e),
+        });
+    }
+
     let pg = match connect_pg(&state, &active).await {
         Ok(p) => p,
         Err(e) => {
@@ -375,7 +388,11 @@ pub async fn analyze_table(
             .unwrap_or_else(|_| table.clone())
     );
 
-    match sqlx::query(&analyze_sql).execute(&pg).await {
+    let result = sqlx::query(&analyze_sql).execute(&pg).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    audit::record(&state, &user, &active, "maintenance", &analyze_sql, error.as_deref()).await;
+
+    match result {
         Ok(_) => {
             tracing::info!("Successfully ran ANALYZE on {}.{}", schema, table);
             Json(AnalyzeResponse {
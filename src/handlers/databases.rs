@@ -3,12 +3,14 @@ use std::sync::Arc;
 use axum::extract::State;
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::Json;
+use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::CookieJar;
 use askama::Template;
 use serde::Deserialize;
-use sqlx::Row;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState};
+use crate::handlers::db_session::{db_session_cookie, DB_SESSION_COOKIE};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, endpoint_role, get_active_endpoint, set_active_endpoint_cookie, AppState};
+use crate::handlers::replica::connect_pg_read;
 use crate::templates::{DatabaseInfo, DatabasesTemplate};
 use crate::utils::format::bytes_to_human;
 
@@ -20,6 +22,41 @@ pub struct ActivateDbForm {
     pub password: Option<String>,
 }
 
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct DatabaseRowDb {
+    pub name: String,
+    pub owner: String,
+    pub encoding: String,
+    pub collate: String,
+    pub ctype: String,
+    pub allow_conn: bool,
+    pub is_template: bool,
+    pub conn_limit: i32,
+    pub size_bytes: i64,
+    pub connections: i64,
+}
+
+pub async fn fetch_databases(pg: &sqlx::PgPool) -> Result<Vec<DatabaseRowDb>, sqlx::Error> {
+    sqlx::query_as::<_, DatabaseRowDb>(r#"
+        SELECT
+            d.datname as name,
+            pg_get_userbyid(d.datdba) as owner,
+            pg_encoding_to_char(d.encoding) as encoding,
+            d.datcollate as collate,
+            d.datctype as ctype,
+            d.datallowconn as allow_conn,
+            d.datistemplate as is_template,
+            d.datconnlimit as conn_limit,
+            pg_database_size(d.datname) as size_bytes,
+            COALESCE(s.numbackends, 0)::bigint as connections
+        FROM pg_database d
+        LEFT JOIN pg_stat_database s ON s.datname = d.datname
+        ORDER BY d.datname
+    "#)
+    .fetch_all(pg)
+    .await
+}
+
 pub async fn list_databases(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
@@ -34,40 +71,23 @@ pub async fn list_databases(
     let mut databases: Vec<DatabaseInfo> = Vec::new();
     let mut menu_enabled = false;
 
-    if let Ok(pg) = connect_pg(&state, &active).await {
-        let query = r#"
-            SELECT
-                d.datname as name,
-                pg_get_userbyid(d.datdba) as owner,
-                pg_encoding_to_char(d.encoding) as encoding,
-                d.datcollate as collate,
-                d.datctype as ctype,
-                d.datallowconn as allow_conn,
-                d.datistemplate as is_template,
-                d.datconnlimit as conn_limit,
-                pg_database_size(d.datname) as size_bytes,
-                COALESCE(s.numbackends, 0)::bigint as connections
-            FROM pg_database d
-            LEFT JOIN pg_stat_database s ON s.datname = d.datname
-            ORDER BY d.datname
-        "#;
-        if let Ok(rows) = sqlx::query(query).fetch_all(&pg).await {
-            menu_enabled = true;
-            for row in rows {
-                let size_bytes: i64 = row.get("size_bytes");
-                databases.push(DatabaseInfo {
-                    name: row.get("name"),
-                    owner: row.get("owner"),
-                    encoding: row.get("encoding"),
-                    collate: row.get("collate"),
-                    ctype: row.get("ctype"),
-                    allow_conn: row.get("allow_conn"),
-                    is_template: row.get("is_template"),
-                    conn_limit: row.get("conn_limit"),
-                    size: bytes_to_human(size_bytes),
-                    connections: row.get::<i64, _>("connections"),
-                });
-            }
+    if let Ok(pg) = connect_pg_read(&state, &active).await
+        && let Ok(rows) = fetch_databases(&pg).await
+    {
+        menu_enabled = true;
+        for row in rows {
+            databases.push(DatabaseInfo {
+                size: bytes_to_human(row.size_bytes),
+                name: row.name,
+                owner: row.owner,
+                encoding: row.encoding,
+                collate: row.collate,
+                ctype: row.ctype,
+                allow_conn: row.allow_conn,
+                is_template: row.is_template,
+                conn_limit: row.conn_limit,
+                connections: row.connections,
+            });
         }
     }
 
@@ -90,26 +110,22 @@ pub async fn list_databases(
 
 pub async fn activate_database(
     State(state): State<Arc<AppState>>,
-    _jar: CookieJar,
+    jar: CookieJar,
     Json(form): Json<serde_json::Value>,
-) -> Result<Redirect, (axum::http::StatusCode, String)> {
+) -> Result<Response, (axum::http::StatusCode, String)> {
     let form: ActivateDbForm = serde_json::from_value(form)
         .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
 
-    let active = if let Some(endpoint) = &state.stateless_endpoint {
-        endpoint.clone()
-    } else {
-        let id = form.active_id.ok_or_else(|| {
-            (axum::http::StatusCode::BAD_REQUEST, "Missing active_id".to_string())
-        })?;
-        let db = state.db.as_ref().ok_or_else(|| {
-            (axum::http::StatusCode::BAD_REQUEST, "No database".to_string())
-        })?;
-        db.get_endpoint(id)
-            .await
-            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            .ok_or_else(|| (axum::http::StatusCode::NOT_FOUND, "Endpoint not found".to_string()))?
-    };
+    let id = form.active_id.ok_or_else(|| {
+        (axum::http::StatusCode::BAD_REQUEST, "Missing active_id".to_string())
+    })?;
+    let active = crate::handlers::find_endpoint(&state, id)
+        .await
+        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (axum::http::StatusCode::NOT_FOUND, "Endpoint not found".to_string()))?;
+    if endpoint_role(&state, active.id) < crate::auth::Role::Viewer {
+        return Err((axum::http::StatusCode::NOT_FOUND, "Endpoint not found".to_string()));
+    }
 
     let mut next = active.clone();
     next.url = replace_db_in_url(&active.url, &form.db_name);
@@ -119,62 +135,53 @@ pub async fn activate_database(
         next.username = Some(username.trim().to_string());
     }
 
-    if let Ok(mut guard) = state.active_override.write() {
-        *guard = Some(next);
-    }
-
     let next_password = if let Some(password) = form.password.as_ref().filter(|v| !v.trim().is_empty()) {
         Some(password.trim().to_string())
-    } else if let Some(db) = &state.db {
-        db.get_endpoint_password(&active).await
     } else {
-        state.stateless_password.clone()
+        crate::handlers::endpoint_password(&state, &active).await
     };
 
-    if let Ok(mut guard) = state.active_override_password.write() {
-        *guard = next_password;
-    }
+    // Override patří jen tomuto prohlížeči; existující token znovu použijeme
+    let token = match jar.get(DB_SESSION_COOKIE) {
+        Some(cookie) => cookie.value().to_string(),
+        None => crate::auth::generate_token()
+            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+    // Nové přihlašovací údaje - starý override pool už neplatí
+    state.pg_pools.invalidate_session(&token).await;
+    state.db_sessions.set(&token, next, next_password);
 
+    let jar = jar
+        .add(db_session_cookie(token))
+        .add(set_active_endpoint_cookie(active.id));
     let target = base_path_url(&state, "/");
-    Ok(Redirect::to(&target))
+    Ok((jar, Redirect::to(&target)).into_response())
+}
+
+/// Zruší přepnutou databázi této session (pool se hned zavře)
+pub async fn clear_db_session(state: &AppState, jar: &CookieJar) {
+    if let Some(cookie) = jar.get(DB_SESSION_COOKIE) {
+        state.db_sessions.remove(cookie.value());
+        state.pg_pools.invalidate_session(cookie.value()).await;
+    }
 }
 
 pub async fn reset_database_override(
     State(state): State<Arc<AppState>>,
-    _jar: CookieJar,
-) -> Result<Redirect, (axum::http::StatusCode, String)> {
-    if let Ok(mut guard) = state.active_override.write() {
-        *guard = None;
-    }
-    if let Ok(mut guard) = state.active_override_password.write() {
-        *guard = None;
-    }
+    jar: CookieJar,
+) -> Result<Response, (axum::http::StatusCode, String)> {
+    clear_db_session(&state, &jar).await;
+    let jar = jar.remove(Cookie::build(DB_SESSION_COOKIE).path("/"));
     let target = base_path_url(&state, "/");
-    Ok(Redirect::to(&target))
+    Ok((jar, Redirect::to(&target)).into_response())
 }
 
 fn replace_db_in_url(url: &str, db_name: &str) -> String {
-    let mut parts = url.splitn(2, '?');
-    let base = parts.next().unwrap_or(url);
-    let query = parts.next();
-
-    let host = if let Some(pos) = base.find("://") {
-        let after = &base[pos + 3..];
-        if let Some(slash) = after.find('/') {
-            base[..pos + 3 + slash].to_string()
-        } else {
-            base.to_string()
+    match crate::handlers::conninfo::ConnInfo::parse(url) {
+        Ok(mut info) => {
+            info.dbname = Some(db_name.to_string());
+            info.to_connection_string()
         }
-    } else if let Some(slash) = base.find('/') {
-        base[..slash].to_string()
-    } else {
-        base.to_string()
-    };
-
-    let mut rebuilt = format!("{}/{}", host, db_name);
-    if let Some(q) = query {
-        rebuilt.push('?');
-        rebuilt.push_str(q);
+        Err(_) => url.to_string(),
     }
-    rebuilt
 }
@@ -0,0 +1,121 @@
+//! Přepnutí databáze (/databases) platí jen pro prohlížeč, který ho provedl.
+//! Override je uložený v paměti pod náhodným tokenem z cookie `pg_db_session`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
+
+use crate::db::models::Endpoint;
+use crate::handlers::AppState;
+
+pub const DB_SESSION_COOKIE: &str = "pg_db_session";
+/// Nepoužívaný override po této době vyprší a platí zase výchozí databáze endpointu
+pub const DB_SESSION_TTL: Duration = Duration::from_secs(8 * 60 * 60);
+
+tokio::task_local! {
+    /// Token z cookie aktuálního requestu, nastavuje ho scope_db_session
+    static DB_SESSION: Option<String>;
+}
+
+#[derive(Debug, Clone)]
+pub struct DbOverride {
+    /// Endpoint s URL (a případně uživatelem) přepnutým na jinou databázi
+    pub endpoint: Endpoint,
+    /// Heslo zadané při přepnutí, jinak heslo endpointu
+    pub password: Option<String>,
+    last_used: Instant,
+}
+
+/// In-memory overridy databáze podle tokenu session
+#[derive(Clone, Default)]
+pub struct DbSessionStore {
+    sessions: Arc<RwLock<HashMap<String, DbOverride>>>,
+}
+
+impl DbSessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Platný override pro token (prodlouží mu platnost)
+    pub fn get(&self, token: &str) -> Option<DbOverride> {
+        let mut guard = self.sessions.write().ok()?;
+        let entry = guard.get_mut(token)?;
+        if entry.last_used.elapsed() > DB_SESSION_TTL {
+            guard.remove(token);
+            return None;
+        }
+        entry.last_used = Instant::now();
+        Some(entry.clone())
+    }
+
+    pub fn set(&self, token: &str, endpoint: Endpoint, password: Option<String>) {
+        if let Ok(mut guard) = self.sessions.write() {
+            // Při zápisu rovnou uklidíme vypršelé overridy ostatních prohlížečů
+            guard.retain(|_, e| e.last_used.elapsed() <= DB_SESSION_TTL);
+            guard.insert(
+                token.to_string(),
+                DbOverride {
+                    endpoint,
+                    password,
+                    last_used: Instant::now(),
+                },
+            );
+        }
+    }
+
+    pub fn remove(&self, token: &str) -> Option<DbOverride> {
+        self.sessions.write().ok()?.remove(token)
+    }
+
+    /// Po úpravě nebo smazání endpointu jeho overridy zahodíme
+    pub fn remove_endpoint(&self, endpoint_id: i64) {
+        if let Ok(mut guard) = self.sessions.write() {
+            guard.retain(|_, e| e.endpoint.id != endpoint_id);
+        }
+    }
+}
+
+/// Token DB session aktuálního requestu (nebo jobu spuštěného přes `in_current_session`)
+pub fn current_token() -> Option<String> {
+    DB_SESSION.try_with(|t| t.clone()).ok().flatten()
+}
+
+/// Override databáze aktuálního requestu pro daný endpoint
+pub fn current_override(state: &AppState, endpoint_id: i64) -> Option<DbOverride> {
+    let token = current_token()?;
+    state
+        .db_sessions
+        .get(&token)
+        .filter(|o| o.endpoint.id == endpoint_id)
+}
+
+/// Future na pozadí (tokio::spawn) poběží se stejnou DB session jako request
+pub fn in_current_session<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    DB_SESSION.scope(current_token(), future)
+}
+
+pub fn db_session_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((DB_SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// Middleware: zpřístupní token z cookie handlerům i connect_pg
+pub async fn scope_db_session(
+    jar: CookieJar,
+    req: Request,
+    next: Next,
+) -> Response {
+    let token = jar.get(DB_SESSION_COOKIE).map(|c| c.value().to_string());
+    DB_SESSION.scope(token, next.run(req)).await
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
     response::{Html, IntoResponse, Redirect, Response},
     Form,
     http::StatusCode,
@@ -9,9 +9,10 @@ use askama::Template;
 use serde::Deserialize;
 use std::sync::Arc;
 
-use crate::db::models::{CreateEndpoint, UpdateEndpoint};
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, set_active_endpoint_cookie, AppState};
-use crate::templates::{EndpointsListTemplate, EndpointsTemplate};
+use crate::auth::{CurrentUser, Role};
+use crate::db::models::{CreateEndpoint, Endpoint, UpdateEndpoint, ENVIRONMENTS};
+use crate::handlers::{all_endpoints, base_path_url, build_ctx_with_endpoint, find_endpoint, connect_pg, endpoint_role, get_active_endpoint, set_active_endpoint_cookie, AppState};
+use crate::templates::{EndpointGroup, EndpointsListTemplate, EndpointsTemplate};
 
 #[derive(Deserialize)]
 pub struct CreateEndpointForm {
@@ -23,6 +24,19 @@ pub struct CreateEndpointForm {
     ssl_mode: Option<String>,
     search_path: Option<String>,
     enable_blueprint: Option<String>,
+    ssh_host: Option<String>,
+    ssh_port: Option<String>,
+    ssh_user: Option<String>,
+    ssh_key_path: Option<String>,
+    ssh_passphrase: Option<String>,
+    ssl_root_cert: Option<String>,
+    ssl_cert: Option<String>,
+    ssl_key: Option<String>,
+    group_name: Option<String>,
+    tags: Option<String>,
+    environment: Option<String>,
+    replica_urls: Option<String>,
+    replica_max_lag: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -35,37 +49,219 @@ pub struct UpdateEndpointForm {
     ssl_mode: Option<String>,
     search_path: Option<String>,
     enable_blueprint: Option<String>,
+    ssh_host: Option<String>,
+    ssh_port: Option<String>,
+    ssh_user: Option<String>,
+    ssh_key_path: Option<String>,
+    ssh_passphrase: Option<String>,
+    ssl_root_cert: Option<String>,
+    ssl_cert: Option<String>,
+    ssl_key: Option<String>,
+    clear_ssl_key: Option<String>,
+    clear_ssh_passphrase: Option<String>,
+    group_name: Option<String>,
+    tags: Option<String>,
+    environment: Option<String>,
+    replica_urls: Option<String>,
+    replica_max_lag: Option<String>,
 }
 
-fn render_list(endpoints: Vec<crate::db::models::Endpoint>, active_id: i64) -> Result<Response, (StatusCode, String)> {
-    let tpl = EndpointsListTemplate { endpoints, active_id };
+/// Filtr seznamu endpointů (/endpoints?q=&group=&tag=&env=)
+#[derive(Deserialize, Default)]
+pub struct EndpointsQuery {
+    q: Option<String>,
+    group: Option<String>,
+    tag: Option<String>,
+    env: Option<String>,
+}
+
+fn blank_to_none(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn validate_url(url: &str) -> Result<(), (StatusCode, String)> {
+    crate::handlers::conninfo::ConnInfo::parse(url)
+        .map(|_| ())
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid connection string: {}", e)))
+}
+
+/// Tagy jako "a, b,a" -> "a,b" (bez prázdných a duplicit)
+fn normalize_tags(value: Option<String>) -> Option<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value.unwrap_or_default().split(',') {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    if tags.is_empty() { None } else { Some(tags.join(",")) }
+}
+
+fn parse_environment(value: Option<String>) -> Result<Option<String>, (StatusCode, String)> {
+    match blank_to_none(value).map(|v| v.to_lowercase()) {
+        None => Ok(None),
+        Some(env) if ENVIRONMENTS.contains(&env.as_str()) => Ok(Some(env)),
+        Some(env) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid environment: {} (expected one of {})", env, ENVIRONMENTS.join(", ")),
+        )),
+    }
+}
+
+/// Repliky jako textarea: jeden connection string na řádek, každý musí jít rozparsovat
+fn normalize_replicas(value: Option<String>) -> Result<Option<String>, (StatusCode, String)> {
+    let mut urls = Vec::new();
+    for url in value.unwrap_or_default().lines().map(str::trim).filter(|u| !u.is_empty()) {
+        crate::handlers::conninfo::ConnInfo::parse(url)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid replica connection string {}: {}", url, e)))?;
+        urls.push(url.to_string());
+    }
+    Ok(if urls.is_empty() { None } else { Some(urls.join("\n")) })
+}
+
+fn parse_replica_max_lag(value: Option<String>) -> Result<Option<i64>, (StatusCode, String)> {
+    match blank_to_none(value) {
+        None => Ok(None),
+        Some(lag) => lag
+            .parse::<u32>()
+            .map(|l| Some(l as i64))
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid replica max lag: {}", lag))),
+    }
+}
+
+fn parse_ssh_port(value: Option<String>) -> Result<Option<i64>, (StatusCode, String)> {
+    match blank_to_none(value) {
+        None => Ok(None),
+        Some(port) => port
+            .parse::<u16>()
+            .map(|p| Some(p as i64))
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid SSH port: {}", port))),
+    }
+}
+
+fn render_list(
+    state: &Arc<AppState>,
+    user: &CurrentUser,
+    endpoints: Vec<Endpoint>,
+    active_id: i64,
+) -> Result<Response, (StatusCode, String)> {
+    let tpl = EndpointsListTemplate {
+        groups: group_endpoints(visible_endpoints(state, endpoints)),
+        active_id,
+        can_manage: user.is_admin,
+        filtered: false,
+    };
     tpl.render()
         .map(Html)
         .map(|h| h.into_response())
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
+/// Jen endpointy, na které má uživatel alespoň roli viewer
+fn visible_endpoints(state: &Arc<AppState>, endpoints: Vec<Endpoint>) -> Vec<Endpoint> {
+    endpoints
+        .into_iter()
+        .filter(|e| endpoint_role(state, e.id) >= Role::Viewer)
+        .collect()
+}
+
+fn matches_filter(endpoint: &Endpoint, query: &EndpointsQuery) -> bool {
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let q = q.to_lowercase();
+        if !endpoint.name.to_lowercase().contains(&q) && !endpoint.url.to_lowercase().contains(&q) {
+            return false;
+        }
+    }
+    if let Some(group) = query.group.as_deref().filter(|g| !g.is_empty())
+        && endpoint.group_name.as_deref().unwrap_or("") != group
+    {
+        return false;
+    }
+    if let Some(tag) = query.tag.as_deref().filter(|t| !t.is_empty())
+        && !endpoint.tag_list().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    {
+        return false;
+    }
+    if let Some(env) = query.env.as_deref().filter(|e| !e.is_empty())
+        && endpoint.environment_name() != env
+    {
+        return false;
+    }
+    true
+}
+
+/// Rozdělí endpointy do skupin (podle názvu skupiny), endpointy bez skupiny jsou na konci
+fn group_endpoints(endpoints: Vec<Endpoint>) -> Vec<EndpointGroup> {
+    let mut groups: Vec<EndpointGroup> = Vec::new();
+    let mut ungrouped = Vec::new();
+    for endpoint in endpoints {
+        let Some(name) = endpoint.group_name.clone().filter(|g| !g.is_empty()) else {
+            ungrouped.push(endpoint);
+            continue;
+        };
+        match groups.iter_mut().find(|g| g.name == name) {
+            Some(group) => group.endpoints.push(endpoint),
+            None => groups.push(EndpointGroup { name, endpoints: vec![endpoint] }),
+        }
+    }
+    groups.sort_by_key(|g| g.name.to_lowercase());
+    if !ungrouped.is_empty() {
+        groups.push(EndpointGroup { name: String::new(), endpoints: ungrouped });
+    }
+    groups
+}
+
+/// Správa endpointů (přidání, úprava, smazání) je jen pro administrátory
+fn require_manage(user: &CurrentUser) -> Result<(), (StatusCode, String)> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Only administrators can manage connections".to_string()))
+    }
+}
+
 pub async fn list_endpoints(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
+    Query(query): Query<EndpointsQuery>,
 ) -> Result<Response, (StatusCode, String)> {
-    if state.db.is_none() {
-        let target = base_path_url(&state, "/");
-        return Ok(Redirect::to(&target).into_response());
-    }
-    let db = state.db.as_ref().unwrap();
-    let endpoints = db
-        .get_endpoints()
+    let endpoints = all_endpoints(&state)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let active = get_active_endpoint(&state, &jar).await;
     let ctx = build_ctx_with_endpoint(&state, active.as_ref());
 
+    let endpoints = visible_endpoints(&state, endpoints);
+
+    let mut group_names: Vec<String> = endpoints.iter().filter_map(|e| e.group_name.clone()).collect();
+    group_names.sort_by_key(|g| g.to_lowercase());
+    group_names.dedup();
+    let mut tag_names: Vec<String> = endpoints
+        .iter()
+        .flat_map(|e| e.tag_list().into_iter().map(str::to_string).collect::<Vec<_>>())
+        .collect();
+    tag_names.sort_by_key(|t| t.to_lowercase());
+    tag_names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    let total = endpoints.len();
+    let filtered: Vec<Endpoint> = endpoints.into_iter().filter(|e| matches_filter(e, &query)).collect();
+
     let tpl = EndpointsTemplate {
         ctx,
-        endpoints,
+        filtered: filtered.len() != total,
+        groups: group_endpoints(filtered),
         active_id: active.as_ref().map(|e| e.id).unwrap_or(-1),
+        // Stateless endpointy jsou jen z konfigurace
+        can_manage: user.is_admin && state.db.is_some(),
+        group_names,
+        tag_names,
+        environments: ENVIRONMENTS.iter().map(|e| e.to_string()).collect(),
+        f_q: query.q.unwrap_or_default(),
+        f_group: query.group.unwrap_or_default(),
+        f_tag: query.tag.unwrap_or_default(),
+        f_env: query.env.unwrap_or_default(),
     };
 
     tpl.render()
@@ -77,12 +273,15 @@ pub async fn list_endpoints(
 pub async fn create_endpoint(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Form(form): Form<CreateEndpointForm>,
 ) -> Result<Response, (StatusCode, String)> {
+    require_manage(&user)?;
     if state.db.is_none() {
         return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
     }
     let db = state.db.as_ref().unwrap();
+    validate_url(&form.url)?;
     let create_endpoint = CreateEndpoint {
         name: form.name,
         url: form.url,
@@ -108,6 +307,19 @@ pub async fn create_endpoint(
             form.search_path
         },
         enable_blueprint: form.enable_blueprint.is_some(),
+        ssh_host: blank_to_none(form.ssh_host),
+        ssh_port: parse_ssh_port(form.ssh_port)?,
+        ssh_user: blank_to_none(form.ssh_user),
+        ssh_key_path: blank_to_none(form.ssh_key_path),
+        ssh_passphrase: form.ssh_passphrase.filter(|p| !p.is_empty()),
+        ssl_root_cert: blank_to_none(form.ssl_root_cert),
+        ssl_cert: blank_to_none(form.ssl_cert),
+        ssl_key: blank_to_none(form.ssl_key),
+        group_name: blank_to_none(form.group_name),
+        tags: normalize_tags(form.tags),
+        environment: parse_environment(form.environment)?,
+        replica_urls: normalize_replicas(form.replica_urls)?,
+        replica_max_lag: parse_replica_max_lag(form.replica_max_lag)?,
     };
 
     if let Err(e) = db.create_endpoint(create_endpoint).await {
@@ -121,7 +333,7 @@ pub async fn create_endpoint(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     let active = get_active_endpoint(&state, &jar).await;
 
-    render_list(endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
+    render_list(&state, &user, endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
 }
 
 pub async fn select_endpoint(
@@ -129,25 +341,16 @@ pub async fn select_endpoint(
     jar: CookieJar,
     axum::extract::Path(id): axum::extract::Path<i64>,
 ) -> Result<Response, (StatusCode, String)> {
-    if state.db.is_none() {
-        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
-    }
-    let db = state.db.as_ref().unwrap();
-    let endpoint = db
-        .get_endpoint(id)
+    let endpoint = find_endpoint(&state, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    if endpoint.is_none() {
+    if endpoint.is_none() || endpoint_role(&state, id) < Role::Viewer {
         return Err((StatusCode::NOT_FOUND, "Endpoint not found".to_string()));
     }
 
-    if let Ok(mut guard) = state.active_override.write() {
-        *guard = None;
-    }
-    if let Ok(mut guard) = state.active_override_password.write() {
-        *guard = None;
-    }
+    // Jiný endpoint - přepnutá databáze této session už neplatí
+    crate::handlers::databases::clear_db_session(&state, &jar).await;
 
     let jar = jar.add(set_active_endpoint_cookie(id));
     let target = base_path_url(&state, "/");
@@ -157,13 +360,16 @@ pub async fn select_endpoint(
 pub async fn update_endpoint(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     axum::extract::Path(id): axum::extract::Path<i64>,
     Form(form): Form<UpdateEndpointForm>,
 ) -> Result<Response, (StatusCode, String)> {
+    require_manage(&user)?;
     if state.db.is_none() {
         return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
     }
     let db = state.db.as_ref().unwrap();
+    validate_url(&form.url)?;
     let update = UpdateEndpoint {
         name: Some(form.name),
         url: Some(form.url),
@@ -189,12 +395,32 @@ pub async fn update_endpoint(
             form.search_path
         },
         enable_blueprint: Some(form.enable_blueprint.is_some()),
+        ssh_host: blank_to_none(form.ssh_host),
+        ssh_port: parse_ssh_port(form.ssh_port)?,
+        ssh_user: blank_to_none(form.ssh_user),
+        ssh_key_path: blank_to_none(form.ssh_key_path),
+        ssh_passphrase: form.ssh_passphrase.filter(|p| !p.is_empty()),
+        clear_ssh_passphrase: form.clear_ssh_passphrase.is_some(),
+        ssl_root_cert: blank_to_none(form.ssl_root_cert),
+        ssl_cert: blank_to_none(form.ssl_cert),
+        ssl_key: blank_to_none(form.ssl_key),
+        clear_ssl_key: form.clear_ssl_key.is_some(),
+        group_name: blank_to_none(form.group_name),
+        tags: normalize_tags(form.tags),
+        environment: parse_environment(form.environment)?,
+        replica_urls: normalize_replicas(form.replica_urls)?,
+        replica_max_lag: parse_replica_max_lag(form.replica_max_lag)?,
     };
 
     db
         .update_endpoint(id, update)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // URL nebo přihlašovací údaje se mohly změnit, pooly i tunel se založí znovu
+    state.pg_pools.invalidate_endpoint(id).await;
+    state.db_sessions.remove_endpoint(id);
+    state.ssh_tunnels.close(id).await;
+    crate::handlers::tls::remove_endpoint_files(id);
 
     let endpoints = db
         .get_endpoints()
@@ -202,14 +428,16 @@ pub async fn update_endpoint(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     let active = get_active_endpoint(&state, &jar).await;
 
-    render_list(endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
+    render_list(&state, &user, endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
 }
 
 pub async fn delete_endpoint(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     axum::extract::Path(id): axum::extract::Path<i64>,
 ) -> Result<Response, (StatusCode, String)> {
+    require_manage(&user)?;
     if state.db.is_none() {
         return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
     }
@@ -218,6 +446,14 @@ pub async fn delete_endpoint(
         .delete_endpoint(id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state.pg_pools.invalidate_endpoint(id).await;
+    state.db_sessions.remove_endpoint(id);
+    state.ssh_tunnels.close(id).await;
+    crate::handlers::tls::remove_endpoint_files(id);
+    // Role endpointu se smazaly kaskádou
+    if let Err(e) = crate::auth::reload_endpoint_roles(&state).await {
+        tracing::warn!("Failed to reload endpoint roles: {}", e);
+    }
 
     let endpoints = db
         .get_endpoints()
@@ -225,25 +461,20 @@ pub async fn delete_endpoint(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     let active = get_active_endpoint(&state, &jar).await;
 
-    render_list(endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
+    render_list(&state, &user, endpoints, active.as_ref().map(|e| e.id).unwrap_or(-1))
 }
 
 pub async fn test_endpoint(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(id): axum::extract::Path<i64>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    if state.db.is_none() {
-        return Err((StatusCode::BAD_REQUEST, "Stateless mode".to_string()));
-    }
-    let db = state.db.as_ref().unwrap();
-    let endpoint = db
-        .get_endpoint(id)
+    let endpoint = find_endpoint(&state, id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let endpoint = match endpoint {
-        Some(ep) => ep,
-        None => return Err((StatusCode::NOT_FOUND, "Endpoint not found".to_string())),
+        Some(ep) if endpoint_role(&state, ep.id) >= Role::Viewer => ep,
+        _ => return Err((StatusCode::NOT_FOUND, "Endpoint not found".to_string())),
     };
 
     match connect_pg(&state, &endpoint).await {
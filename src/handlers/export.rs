@@ -1,11 +1,10 @@
-use axum::extract::{Path, State, Multipart};
+use axum::extract::{Path, Query, State, Multipart};
 use axum::response::{Html, Sse, IntoResponse};
 use axum::http::{StatusCode, header};
 use axum::Json;
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
-use tokio::io::AsyncWriteExt;
-use std::collections::VecDeque;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::process::Command;
@@ -14,11 +13,17 @@ use futures::stream::{self, Stream};
 use std::convert::Infallible;
 use std::time::Duration;
 
-use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, AppState, ExportJob, JobStatus};
+use crate::auth::{require_role, CurrentUser, Role};
+use crate::handlers::conninfo::ConnInfo;
+use crate::handlers::tls::{self, TlsFiles};
+use crate::handlers::db_session::in_current_session;
+use crate::handlers::{audit, build_ctx_with_endpoint, get_active_endpoint, jobs, resolve_conninfo, targets, AppState, JobStatus};
 use crate::templates::ExportWizardTemplate;
+use crate::utils::format::bytes_to_human;
+use crate::utils::s3::S3Object;
 use askama::Template;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ExportRequest {
     pub scope: String,          // "full", "schema", "data", "tables"
     pub format: String,          // "custom", "plain", "directory", "tar"
@@ -30,6 +35,9 @@ pub struct ExportRequest {
     pub exclude_patterns: Option<String>,
     pub pg_version: Option<String>, // "auto", "16", "17", "18"
     pub selected_tables: Option<Vec<String>>, // for scope="tables"
+    /// Po dokončení nahrát dump do storage targetu (S3)
+    #[serde(default)]
+    pub target_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -46,7 +54,7 @@ pub struct JobStatusResponse {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ImportRequest {
     pub file_path: String,
     pub target_database: String,
@@ -78,19 +86,30 @@ pub async fn import_wizard(
     let active = get_active_endpoint(&state, &jar).await;
     let ctx = build_ctx_with_endpoint(&state, active.as_ref());
 
-    let tmpl = crate::templates::ImportWizardTemplate { ctx };
+    let tmpl = crate::templates::ImportWizardTemplate { ctx, targets: targets::target_options(&state).await };
 
     Html(tmpl.render().unwrap_or_else(|e| format!("Template error: {}", e)))
 }
 
 pub async fn upload_import_file(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, StatusCode> {
     tracing::info!("Upload handler called");
 
-    let upload_dir = "/tmp/postgres-explorer-imports";
-    if let Err(e) = std::fs::create_dir_all(upload_dir) {
+    let active = get_active_endpoint(&state, &jar)
+        .await
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    require_role(&state, &user, active.id, Role::Admin, "import").map_err(|_| StatusCode::FORBIDDEN)?;
+
+    if let Err((status, msg)) = state.storage.check_quota().await {
+        tracing::warn!("Upload refused: {}", msg);
+        return Err(status);
+    }
+    let upload_dir = state.storage.import_dir(active.id);
+    if let Err(e) = std::fs::create_dir_all(&upload_dir) {
         tracing::error!("Failed to create upload dir: {}", e);
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
@@ -107,11 +126,12 @@ pub async fn upload_import_file(
         tracing::info!("Got field: {}", name);
 
         if name == "file" {
+            // Od klienta bereme jen jméno souboru, cesta nesmí opustit import adresář
             let filename = field.file_name()
-                .map(|s| s.to_string())
+                .and_then(safe_file_name)
                 .unwrap_or_else(|| format!("import_{}.dump", uuid::Uuid::new_v4()));
 
-            let path = format!("{}/{}", upload_dir, filename);
+            let path = upload_dir.join(&filename).to_string_lossy().into_owned();
             let mut file = tokio::fs::File::create(&path).await.map_err(|e| {
                 tracing::error!("Failed to create file: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
@@ -156,6 +176,93 @@ pub async fn upload_import_file(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportObjectsQuery {
+    pub target_id: i64,
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FetchObjectRequest {
+    pub target_id: i64,
+    pub key: String,
+}
+
+/// Objekty ve storage targetu pro import (výchozí prefix je prefix targetu)
+pub async fn list_import_objects(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Query(query): Query<ImportObjectsQuery>,
+) -> Result<Json<Vec<S3Object>>, (StatusCode, String)> {
+    let active = get_active_endpoint(&state, &jar)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    require_role(&state, &user, active.id, Role::Admin, "import").map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    let (target, client) = targets::client(&state, query.target_id)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    let prefix = query
+        .prefix
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .or_else(|| target.prefix.map(|p| format!("{}/", p)))
+        .unwrap_or_default();
+    let objects = client
+        .list(&prefix)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+    Ok(Json(objects))
+}
+
+/// Stáhne objekt do adresáře importů aktivního endpointu; dál wizard pokračuje jako po uploadu
+pub async fn fetch_import_object(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Json(req): Json<FetchObjectRequest>,
+) -> Result<Json<UploadResponse>, (StatusCode, String)> {
+    let active = get_active_endpoint(&state, &jar)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    require_role(&state, &user, active.id, Role::Admin, "import").map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    state.storage.check_quota().await?;
+
+    let file_name = safe_file_name(&req.key)
+        .ok_or((StatusCode::BAD_REQUEST, "Object key must point to a file".to_string()))?;
+    let (_, client) = targets::client(&state, req.target_id)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    let upload_dir = state.storage.import_dir(active.id);
+    tokio::fs::create_dir_all(&upload_dir)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create upload dir: {}", e)))?;
+    let path = upload_dir.join(&file_name);
+
+    tracing::info!("Fetching s3://{}/{} to {}", client.bucket(), req.key, path.display());
+    let file_size = client
+        .download_file(&req.key, &path, &|_, _| {})
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+
+    // Formát podle hlavičky souboru
+    let mut header = Vec::new();
+    async {
+        let file = tokio::fs::File::open(&path).await?;
+        file.take(1024).read_to_end(&mut header).await
+    }
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let format = detect_dump_format(&header);
+
+    Ok(Json(UploadResponse {
+        file_path: path.to_string_lossy().into_owned(),
+        file_size,
+        format,
+    }))
+}
+
 pub async fn export_wizard(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
@@ -163,7 +270,15 @@ pub async fn export_wizard(
     let active = get_active_endpoint(&state, &jar).await;
     let ctx = build_ctx_with_endpoint(&state, active.as_ref());
 
-    let tmpl = ExportWizardTemplate { ctx };
+    let export_dir = match active.as_ref() {
+        Some(endpoint) => state.storage.export_dir(endpoint.id),
+        None => state.storage.root.join("exports"),
+    };
+    let tmpl = ExportWizardTemplate {
+        ctx,
+        export_dir: export_dir.display().to_string(),
+        targets: targets::target_options(&state).await,
+    };
 
     Html(tmpl.render().unwrap_or_else(|e| format!("Template error: {}", e)))
 }
@@ -171,90 +286,153 @@ pub async fn export_wizard(
 pub async fn start_export(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Json(req): Json<ExportRequest>,
 ) -> Result<Json<ExportResponse>, (StatusCode, String)> {
     let active = get_active_endpoint(&state, &jar)
         .await
         .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    let job_id = start_export_job(&state, active, user, req).await?;
+    Ok(Json(ExportResponse { job_id }))
+}
 
-    // Generate unique job ID
-    let job_id = format!("export_{}", uuid::Uuid::new_v4());
-
-    // Create job entry
-    let job = ExportJob {
-        job_id: job_id.clone(),
-        status: JobStatus::Running,
-        logs: VecDeque::new(),
-        started_at: SystemTime::now(),
-        completed_at: None,
-        file_path: None,
-        error: None,
-    };
+/// Ověří roli a spustí export na pozadí (i re-run z /jobs)
+pub async fn start_export_job(
+    state: &Arc<AppState>,
+    active: crate::db::models::Endpoint,
+    user: CurrentUser,
+    req: ExportRequest,
+) -> Result<String, (StatusCode, String)> {
+    require_role(state, &user, active.id, Role::Operator, "export")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    state.storage.check_quota().await?;
+    check_target(state, &req).await?;
 
-    state.export_jobs.write().await.insert(job_id.clone(), job);
+    let job_id = jobs::register(state, "export", &active, &user, &req, None).await;
 
     // Spawn background task
     let state_clone = state.clone();
     let job_id_clone = job_id.clone();
-    tokio::spawn(async move {
-        run_export_job(state_clone, job_id_clone, active, req).await;
-    });
+    tokio::spawn(in_current_session(async move {
+        run_export_job(state_clone.clone(), job_id_clone.clone(), active.clone(), req).await;
+        audit::record_job(&state_clone, &user, &active, "export", &job_id_clone).await;
+    }));
 
-    Ok(Json(ExportResponse { job_id }))
+    Ok(job_id)
 }
 
 pub async fn start_import(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Json(req): Json<ImportRequest>,
 ) -> Result<Json<ExportResponse>, (StatusCode, String)> {
     let active = get_active_endpoint(&state, &jar)
         .await
         .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    let job_id = start_import_job(&state, active, user, req).await?;
+    Ok(Json(ExportResponse { job_id }))
+}
 
-    // Verify file exists
-    if !tokio::fs::try_exists(&req.file_path).await.unwrap_or(false) {
-        return Err((StatusCode::BAD_REQUEST, "Import file not found".to_string()));
+/// Ověří roli a vstupní soubor a spustí import na pozadí (i re-run z /jobs)
+pub async fn start_import_job(
+    state: &Arc<AppState>,
+    active: crate::db::models::Endpoint,
+    user: CurrentUser,
+    mut req: ImportRequest,
+) -> Result<String, (StatusCode, String)> {
+    require_role(state, &user, active.id, Role::Admin, "import")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    // Soubor musí ležet v import adresáři endpointu (po úspěšném importu se maže)
+    req.file_path = import_file_path(state, active.id, &req.file_path).await?;
+
+    let job_id = jobs::register(state, "import", &active, &user, &req, None).await;
+    if let Some(job) = state.export_jobs.write().await.get_mut(&job_id) {
+        job.input_path = Some(req.file_path.clone());
     }
 
-    // Generate unique job ID
-    let job_id = format!("import_{}", uuid::Uuid::new_v4());
-
-    // Create job entry
-    let job = ExportJob {
-        job_id: job_id.clone(),
-        status: JobStatus::Running,
-        logs: VecDeque::new(),
-        started_at: SystemTime::now(),
-        completed_at: None,
-        file_path: None,
-        error: None,
-    };
-
-    state.export_jobs.write().await.insert(job_id.clone(), job);
-
     // Spawn background task
     let state_clone = state.clone();
     let job_id_clone = job_id.clone();
-    tokio::spawn(async move {
-        run_import_job(state_clone, job_id_clone, active, req).await;
-    });
+    tokio::spawn(in_current_session(async move {
+        run_import_job(state_clone.clone(), job_id_clone.clone(), active.clone(), req).await;
+        audit::record_job(&state_clone, &user, &active, "import", &job_id_clone).await;
+    }));
 
-    Ok(Json(ExportResponse { job_id }))
+    Ok(job_id)
+}
+
+/// Poslední složka cesty od klienta (jméno nahraného souboru, klíč objektu), bez `.` a `..`
+fn safe_file_name(name: &str) -> Option<String> {
+    name.rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .map(str::to_string)
+}
+
+/// Kanonická cesta importovaného souboru; odmítne cokoliv mimo import adresář endpointu
+async fn import_file_path(state: &AppState, endpoint_id: i64, file_path: &str) -> Result<String, (StatusCode, String)> {
+    let not_found = || (StatusCode::BAD_REQUEST, "Import file not found".to_string());
+    let import_dir = tokio::fs::canonicalize(state.storage.import_dir(endpoint_id))
+        .await
+        .map_err(|_| not_found())?;
+    let path = tokio::fs::canonicalize(file_path).await.map_err(|_| not_found())?;
+    if path == import_dir || !path.starts_with(&import_dir) {
+        tracing::warn!("Rejected import of {} outside {}", path.display(), import_dir.display());
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Import file must be uploaded or fetched for this connection".to_string(),
+        ));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Export mimo HTTP (headless CLI): počká na dokončení, výsledek je v export_jobs pod vráceným ID
+pub async fn run_export(
+    state: &Arc<AppState>,
+    endpoint: &crate::db::models::Endpoint,
+    req: ExportRequest,
+) -> anyhow::Result<String> {
+    state.storage.check_quota().await.map_err(|(_, msg)| anyhow::anyhow!(msg))?;
+    check_target(state, &req).await.map_err(|(_, msg)| anyhow::anyhow!(msg))?;
+    let user = crate::auth::request_user().unwrap_or_else(CurrentUser::anonymous);
+    let job_id = jobs::register(state, "export", endpoint, &user, &req, None).await;
+    run_export_job(state.clone(), job_id.clone(), endpoint.clone(), req).await;
+    Ok(job_id)
+}
+
+/// Storage target musí existovat; formát directory se nahrát nedá (není to jeden soubor)
+pub async fn check_target(state: &AppState, req: &ExportRequest) -> Result<(), (StatusCode, String)> {
+    let Some(target_id) = req.target_id else {
+        return Ok(());
+    };
+    if req.format == "directory" {
+        return Err((StatusCode::BAD_REQUEST, "Directory format cannot be uploaded to a storage target".to_string()));
+    }
+    let db = state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Storage targets are not available in stateless mode".to_string()))?;
+    match db.get_storage_target(target_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, format!("Storage target {} does not exist", target_id))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
 }
 
-async fn run_export_job(
+pub async fn run_export_job(
     state: Arc<AppState>,
     job_id: String,
     endpoint: crate::db::models::Endpoint,
     req: ExportRequest,
 ) {
-    let output_dir = "/tmp/postgres-explorer-exports";
-    std::fs::create_dir_all(output_dir).ok();
+    let output_dir = state.storage.export_dir(endpoint.id);
+    std::fs::create_dir_all(&output_dir).ok();
 
     let file_name = format!("{}.dump", job_id);
-    let file_path = format!("{}/{}", output_dir, file_name);
-    let log_file_path = format!("{}/{}.log", output_dir, job_id);
+    let file_path = output_dir.join(&file_name).to_string_lossy().into_owned();
+    let log_file_path = state.storage.log_path(endpoint.id, &job_id).to_string_lossy().into_owned();
 
     // Create log file
     let log_file = match tokio::fs::File::create(&log_file_path).await {
@@ -267,8 +445,30 @@ async fn run_export_job(
         }
     };
 
+    let info = match resolve_conninfo(&state, &endpoint).await {
+        Ok(info) => info,
+        Err(e) => {
+            let error = format!("Failed to prepare connection: {:#}", e);
+            append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+    let tls = match tls::endpoint_tls_files(&state, &endpoint).await {
+        Ok(tls) => tls,
+        Err(e) => {
+            let error = format!("Failed to prepare TLS certificates: {:#}", e);
+            append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+
     // Build pg_dump command
-    let mut cmd = build_pg_dump_command(&endpoint, &req, &file_path, &state).await;
+    let mut cmd = build_pg_dump_command(&endpoint, &info, &tls, &req, &file_path, &state).await;
+    set_job_command(&state, &job_id, command_line(&cmd)).await;
+    jobs::own_process_group(&mut cmd);
+    let cancel = jobs::cancel_token(&state, &job_id).await;
 
     append_log_with_file(&state, &job_id, &log_file, "🚀 Starting PostgreSQL export...".to_string()).await;
     append_log_with_file(&state, &job_id, &log_file, format!("📝 Scope: {:?}", req.scope)).await;
@@ -312,14 +512,42 @@ async fn run_export_job(
                 }
             });
 
-            match child.wait().await {
-                Ok(status) => {
+            match jobs::wait_or_cancel(&mut child, &cancel).await {
+                None => {
+                    // Nedokončený dump nemá cenu nechávat (u formátu directory je to adresář)
+                    let removed = match tokio::fs::metadata(&file_path).await {
+                        Ok(meta) if meta.is_dir() => tokio::fs::remove_dir_all(&file_path).await,
+                        Ok(_) => tokio::fs::remove_file(&file_path).await,
+                        Err(_) => Ok(()),
+                    };
+                    if let Err(e) = removed {
+                        tracing::warn!("Failed to remove partial dump {}: {}", file_path, e);
+                    }
+                    append_log_with_file(&state, &job_id, &log_file, "".to_string()).await;
+                    append_log_with_file(&state, &job_id, &log_file, "🛑 Export cancelled, partial dump removed".to_string()).await;
+                    complete_job(&state, &job_id, None, Some(jobs::CANCELLED.to_string())).await;
+                }
+                Some(Ok(status)) => {
                     if status.success() {
                         append_log_with_file(&state, &job_id, &log_file, "".to_string()).await;
                         append_log_with_file(&state, &job_id, &log_file, "✅ Export completed successfully!".to_string()).await;
                         append_log_with_file(&state, &job_id, &log_file, format!("📦 Dump file: {}", file_path)).await;
                         append_log_with_file(&state, &job_id, &log_file, format!("📋 Log file: {}", log_file_path)).await;
-                        complete_job(&state, &job_id, Some(file_path), None).await;
+                        match req.target_id {
+                            None => complete_job(&state, &job_id, Some(file_path), None).await,
+                            Some(target_id) => {
+                                let uploaded =
+                                    upload_dump(&state, &job_id, &log_file, &endpoint, target_id, &file_path, &cancel).await;
+                                match uploaded {
+                                    Ok(true) => complete_job(&state, &job_id, None, None).await,
+                                    Ok(false) => complete_job(&state, &job_id, Some(file_path), None).await,
+                                    Err(error) => {
+                                        append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
+                                        complete_job(&state, &job_id, Some(file_path), Some(error)).await;
+                                    }
+                                }
+                            }
+                        }
                     } else {
                         let error = format!("Export failed with exit code: {:?}", status.code());
                         append_log_with_file(&state, &job_id, &log_file, "".to_string()).await;
@@ -328,7 +556,7 @@ async fn run_export_job(
                         complete_job(&state, &job_id, None, Some(error)).await;
                     }
                 }
-                Err(e) => {
+                Some(Err(e)) => {
                     let error = format!("Failed to wait for process: {}", e);
                     append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
                     append_log_with_file(&state, &job_id, &log_file, format!("📋 Log file: {}", log_file_path)).await;
@@ -344,16 +572,84 @@ async fn run_export_job(
     }
 }
 
+/// Nahraje hotový dump do storage targetu; Ok(true) = lokální kopie smazána (delete_local)
+async fn upload_dump(
+    state: &Arc<AppState>,
+    job_id: &str,
+    log_file: &Arc<tokio::sync::Mutex<tokio::fs::File>>,
+    endpoint: &crate::db::models::Endpoint,
+    target_id: i64,
+    file_path: &str,
+    cancel: &tokio_util::sync::CancellationToken,
+) -> Result<bool, String> {
+    let (target, client) = targets::client(state, target_id)
+        .await
+        .map_err(|e| format!("Upload failed: {:#}", e))?;
+    let file_name = std::path::Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{}.dump", job_id));
+    let key = targets::object_key(&target, endpoint, &file_name);
+    let location = format!("s3://{}/{}", client.bucket(), key);
+    append_log_with_file(state, job_id, log_file, "".to_string()).await;
+    append_log_with_file(state, job_id, log_file, format!("☁️ Uploading to {} ({})", location, target.name)).await;
+
+    // Progress callback je synchronní, řádky logu jdou přes kanál po 10 %
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let logger = {
+        let state = state.clone();
+        let job_id = job_id.to_string();
+        let log_file = log_file.clone();
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                append_log_with_file(&state, &job_id, &log_file, line).await;
+            }
+        })
+    };
+    let last_step = std::sync::atomic::AtomicU64::new(0);
+    let progress = move |done: u64, total: u64| {
+        let step = (done * 10).checked_div(total).unwrap_or(10);
+        if step > last_step.swap(step, std::sync::atomic::Ordering::Relaxed) {
+            tx.send(format!("☁️ Uploaded {} / {} ({}%)", bytes_to_human(done as i64), bytes_to_human(total as i64), step * 10)).ok();
+        }
+    };
+    let uploaded = client.upload_file(std::path::Path::new(file_path), &key, cancel, &progress).await;
+    drop(progress);
+    logger.await.ok();
+    let size = uploaded.map_err(|e| format!("Upload to {} failed: {:#}", location, e))?;
+
+    append_log_with_file(state, job_id, log_file, format!("✅ Uploaded {} to {}, checksum verified", bytes_to_human(size as i64), location)).await;
+    if let Some(db) = state.db.as_ref()
+        && let Err(e) = db.set_job_remote(job_id, target_id, &key).await
+    {
+        tracing::error!("Failed to record upload of job {}: {}", job_id, e);
+        return Ok(false);
+    }
+    if !target.delete_local {
+        return Ok(false);
+    }
+    match tokio::fs::remove_file(file_path).await {
+        Ok(()) => {
+            append_log_with_file(state, job_id, log_file, "🗑️ Local copy removed".to_string()).await;
+            Ok(true)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to remove local copy {}: {}", file_path, e);
+            Ok(false)
+        }
+    }
+}
+
 async fn run_import_job(
     state: Arc<AppState>,
     job_id: String,
     endpoint: crate::db::models::Endpoint,
     req: ImportRequest,
 ) {
-    let output_dir = "/tmp/postgres-explorer-exports";
-    std::fs::create_dir_all(output_dir).ok();
+    let output_dir = state.storage.export_dir(endpoint.id);
+    std::fs::create_dir_all(&output_dir).ok();
 
-    let log_file_path = format!("{}/{}.log", output_dir, job_id);
+    let log_file_path = state.storage.log_path(endpoint.id, &job_id).to_string_lossy().into_owned();
 
     // Create log file
     let log_file = match tokio::fs::File::create(&log_file_path).await {
@@ -366,6 +662,25 @@ async fn run_import_job(
         }
     };
 
+    let info = match resolve_conninfo(&state, &endpoint).await {
+        Ok(info) => info,
+        Err(e) => {
+            let error = format!("Failed to prepare connection: {:#}", e);
+            append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+    let tls = match tls::endpoint_tls_files(&state, &endpoint).await {
+        Ok(tls) => tls,
+        Err(e) => {
+            let error = format!("Failed to prepare TLS certificates: {:#}", e);
+            append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
+            complete_job(&state, &job_id, None, Some(error)).await;
+            return;
+        }
+    };
+
     append_log_with_file(&state, &job_id, &log_file, "🚀 Starting PostgreSQL import...".to_string()).await;
     append_log_with_file(&state, &job_id, &log_file, format!("📦 File: {}", req.file_path)).await;
     append_log_with_file(&state, &job_id, &log_file, format!("🎯 Target: {}", req.target_database)).await;
@@ -375,28 +690,15 @@ async fn run_import_job(
     if req.create_db && !req.target_database.is_empty() {
         append_log_with_file(&state, &job_id, &log_file, format!("📝 Creating database '{}'...", req.target_database)).await;
 
-        let password = if let Some(db) = &state.db {
-            db.get_endpoint_password(&endpoint).await
-        } else {
-            state.stateless_password.clone()
-        };
+        let password = crate::handlers::connection_password(&state, &endpoint).await;
 
-        let conn_parts = parse_connection_url(&endpoint.url);
         let mut create_cmd = Command::new("psql");
 
-        if let Some(ref pw) = password {
-            create_cmd.env("PGPASSWORD", pw);
-        }
-
-        create_cmd.arg("-h").arg(&conn_parts.host);
-        create_cmd.arg("-p").arg(&conn_parts.port);
-        create_cmd.arg("-d").arg(&conn_parts.database);
-
-        if let Some(username) = &endpoint.username {
-            create_cmd.arg("-U").arg(username);
-        }
+        tls.apply_env(&mut create_cmd, &endpoint);
+        set_connection_args(&mut create_cmd, &info, password.as_deref());
 
         create_cmd.arg("-c").arg(format!("CREATE DATABASE \"{}\"", req.target_database));
+        set_job_command(&state, &job_id, command_line(&create_cmd)).await;
 
         match create_cmd.output().await {
             Ok(output) => {
@@ -421,7 +723,10 @@ async fn run_import_job(
     }
 
     // Step 2: Build pg_restore command (without --create now)
-    let mut cmd = build_pg_restore_command(&endpoint, &req, &state).await;
+    let mut cmd = build_pg_restore_command(&endpoint, &info, &tls, &req, &state).await;
+    set_job_command(&state, &job_id, command_line(&cmd)).await;
+    jobs::own_process_group(&mut cmd);
+    let cancel = jobs::cancel_token(&state, &job_id).await;
 
     tracing::info!("Import command: {:?}", cmd);
 
@@ -469,8 +774,14 @@ async fn run_import_job(
                 error_lines
             });
 
-            match child.wait().await {
-                Ok(status) => {
+            match jobs::wait_or_cancel(&mut child, &cancel).await {
+                None => {
+                    // Import soubor zůstává pro re-run; bez --single-transaction je část dat už v databázi
+                    append_log_with_file(&state, &job_id, &log_file, "".to_string()).await;
+                    append_log_with_file(&state, &job_id, &log_file, "🛑 Import cancelled, the target database may be partially restored".to_string()).await;
+                    complete_job(&state, &job_id, None, Some(jobs::CANCELLED.to_string())).await;
+                }
+                Some(Ok(status)) => {
                     // Wait for stderr processing to complete
                     let error_lines = stderr_handle.await.unwrap_or_default();
 
@@ -502,7 +813,7 @@ async fn run_import_job(
                         }
                     }
                 }
-                Err(e) => {
+                Some(Err(e)) => {
                     let error = format!("Failed to wait for process: {}", e);
                     append_log_with_file(&state, &job_id, &log_file, format!("❌ {}", error)).await;
                     complete_job(&state, &job_id, None, Some(error)).await;
@@ -519,6 +830,8 @@ async fn run_import_job(
 
 async fn build_pg_restore_command(
     endpoint: &crate::db::models::Endpoint,
+    info: &ConnInfo,
+    tls: &TlsFiles,
     req: &ImportRequest,
     state: &Arc<AppState>,
 ) -> Command {
@@ -533,35 +846,21 @@ async fn build_pg_restore_command(
     };
 
     // Get password
-    let password = if let Some(db) = &state.db {
-        db.get_endpoint_password(endpoint).await
-    } else {
-        state.stateless_password.clone()
-    };
+    let password = crate::handlers::connection_password(state, endpoint).await;
 
-    let conn_parts = parse_connection_url(&endpoint.url);
-    let target_db = if !req.target_database.is_empty() {
-        &req.target_database
-    } else {
-        &conn_parts.database
-    };
+    // Always connect to target DB (created in step 1 if needed)
+    let mut info = info.clone();
+    if !req.target_database.is_empty() {
+        info.dbname = Some(req.target_database.clone());
+    }
 
     // Use psql for plain SQL, pg_restore for other formats
     if req.format == "plain" {
         // psql -h host -p port -U user -d database -f file.sql
         let mut cmd = Command::new("psql");
 
-        if let Some(ref pw) = password {
-            cmd.env("PGPASSWORD", pw);
-        }
-
-        cmd.arg("-h").arg(&conn_parts.host);
-        cmd.arg("-p").arg(&conn_parts.port);
-        cmd.arg("-d").arg(target_db);
-
-        if let Some(username) = &endpoint.username {
-            cmd.arg("-U").arg(username);
-        }
+        tls.apply_env(&mut cmd, endpoint);
+        set_connection_args(&mut cmd, &info, password.as_deref());
 
         if req.single_transaction {
             cmd.arg("--single-transaction");
@@ -576,19 +875,8 @@ async fn build_pg_restore_command(
     // pg_restore for custom/directory/tar formats
     let mut cmd = Command::new("pg_restore");
 
-    if let Some(ref pw) = password {
-        cmd.env("PGPASSWORD", pw);
-    }
-
-    cmd.arg("-h").arg(&conn_parts.host);
-    cmd.arg("-p").arg(&conn_parts.port);
-
-    if let Some(username) = &endpoint.username {
-        cmd.arg("-U").arg(username);
-    }
-
-    // Always connect to target DB (created in step 1 if needed)
-    cmd.arg("-d").arg(target_db);
+    tls.apply_env(&mut cmd, endpoint);
+    set_connection_args(&mut cmd, &info, password.as_deref());
 
     // Options
     if req.clean {
@@ -621,6 +909,8 @@ async fn build_pg_restore_command(
 
 async fn build_pg_dump_command(
     endpoint: &crate::db::models::Endpoint,
+    info: &ConnInfo,
+    tls: &TlsFiles,
     req: &ExportRequest,
     output_path: &str,
     state: &Arc<AppState>,
@@ -641,26 +931,11 @@ async fn build_pg_dump_command(
     let mut cmd = Command::new(&binary);
 
     // Get password from DB or stateless config
-    let password = if let Some(db) = &state.db {
-        db.get_endpoint_password(endpoint).await
-    } else {
-        state.stateless_password.clone()
-    };
+    let password = crate::handlers::connection_password(state, endpoint).await;
 
-    // Set PGPASSWORD environment variable if password exists
-    if let Some(ref pw) = password {
-        cmd.env("PGPASSWORD", pw);
-    }
-
-    // Build connection string with host/port/database format (no password in URL)
-    let conn_parts = parse_connection_url(&endpoint.url);
-    cmd.arg("-h").arg(&conn_parts.host);
-    cmd.arg("-p").arg(&conn_parts.port);
-    cmd.arg("-d").arg(&conn_parts.database);
-
-    if let Some(username) = &endpoint.username {
-        cmd.arg("-U").arg(username);
-    }
+    // Connection string bez hesla (heslo jde přes PGPASSWORD)
+    tls.apply_env(&mut cmd, endpoint);
+    set_connection_args(&mut cmd, info, password.as_deref());
 
     // Format
     match req.format.as_str() {
@@ -766,40 +1041,15 @@ fn detect_dump_format(data: &[u8]) -> String {
     "custom".to_string()
 }
 
-pub struct ConnectionParts {
-    pub host: String,
-    pub port: String,
-    pub database: String,
-}
-
-pub fn parse_connection_url(url: &str) -> ConnectionParts {
-    // Parse postgres://[user:pass@]host[:port]/database
-    let without_scheme = url.strip_prefix("postgres://")
-        .or_else(|| url.strip_prefix("postgresql://"))
-        .unwrap_or(url);
-
-    // Remove user:pass@ if present
-    let host_part = if let Some(at_pos) = without_scheme.find('@') {
-        &without_scheme[at_pos + 1..]
-    } else {
-        without_scheme
-    };
-
-    // Split host:port/database
-    let parts: Vec<&str> = host_part.splitn(2, '/').collect();
-    let host_port = parts.get(0).unwrap_or(&"localhost:5432");
-    let database = parts.get(1).unwrap_or(&"postgres").to_string();
-
-    // Split host:port
-    let hp: Vec<&str> = host_port.splitn(2, ':').collect();
-    let host = hp.get(0).unwrap_or(&"localhost").to_string();
-    let port = hp.get(1).unwrap_or(&"5432").to_string();
-
-    ConnectionParts {
-        host,
-        port,
-        database,
+/// Spojení pro psql/pg_dump/pg_restore: conninfo přes -d (umí sockety i multi-host),
+/// heslo přes PGPASSWORD, aby nebylo vidět v příkazové řádce
+pub fn set_connection_args(cmd: &mut Command, info: &ConnInfo, password: Option<&str>) {
+    let mut info = info.clone();
+    let password = info.password.take().or(password.map(str::to_string));
+    if let Some(pw) = password {
+        cmd.env("PGPASSWORD", pw);
     }
+    cmd.arg("-d").arg(info.to_keyword_value());
 }
 
 async fn append_log(state: &Arc<AppState>, job_id: &str, line: String) {
@@ -829,20 +1079,58 @@ async fn append_log_with_file(
     }
 }
 
+/// Příkazová řádka bez env proměnných (heslo jde přes PGPASSWORD)
+fn command_line(cmd: &Command) -> String {
+    let cmd = cmd.as_std();
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn set_job_command(state: &Arc<AppState>, job_id: &str, command: String) {
+    let mut jobs = state.export_jobs.write().await;
+    if let Some(job) = jobs.get_mut(job_id) {
+        match job.command.as_mut() {
+            Some(existing) => {
+                existing.push('\n');
+                existing.push_str(&command);
+            }
+            None => job.command = Some(command),
+        }
+    }
+}
+
 async fn complete_job(state: &Arc<AppState>, job_id: &str, file_path: Option<String>, error: Option<String>) {
     let mut jobs = state.export_jobs.write().await;
     if let Some(job) = jobs.get_mut(job_id) {
-        job.status = if error.is_some() { JobStatus::Failed } else { JobStatus::Completed };
+        job.status = match &error {
+            None => JobStatus::Completed,
+            Some(_) if job.cancel.is_cancelled() => JobStatus::Cancelled,
+            Some(_) => JobStatus::Failed,
+        };
         job.completed_at = Some(SystemTime::now());
         job.file_path = file_path;
         job.error = error;
     }
+    drop(jobs);
+    jobs::finish(state, job_id).await;
 }
 
 pub async fn get_job_status(
     State(state): State<Arc<AppState>>,
+    user: CurrentUser,
     Path(job_id): Path<String>,
 ) -> Result<Json<JobStatusResponse>, (StatusCode, String)> {
+    let role = jobs::job_role(&state, &user, &job_id, Role::Viewer, "view job status").await?;
     let jobs = state.export_jobs.read().await;
     let job = jobs.get(&job_id)
         .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))?;
@@ -851,15 +1139,18 @@ pub async fn get_job_status(
         status: format!("{:?}", job.status),
         started_at: job.started_at,
         completed_at: job.completed_at,
-        file_path: job.file_path.clone(),
+        // Cesta k výsledku jen pro ty, kdo si ho smí stáhnout
+        file_path: job.file_path.clone().filter(|_| role >= Role::Operator),
         error: job.error.clone(),
     }))
 }
 
 pub async fn stream_logs(
     State(state): State<Arc<AppState>>,
+    user: CurrentUser,
     Path(job_id): Path<String>,
-) -> Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>>, (StatusCode, String)> {
+    jobs::job_role(&state, &user, &job_id, Role::Viewer, "view job logs").await?;
     let stream = stream::unfold(0usize, move |last_index| {
         let state = state.clone();
         let job_id = job_id.clone();
@@ -872,7 +1163,7 @@ pub async fn stream_logs(
                 if let Some(job) = jobs.get(&job_id) {
                     let logs: Vec<String> = job.logs.iter().skip(last_index).cloned().collect();
                     let new_index = last_index + logs.len();
-                    let is_done = matches!(job.status, JobStatus::Completed | JobStatus::Failed);
+                    let is_done = job.status.is_done();
                     drop(jobs); // Release lock before potentially waiting
 
                     if !logs.is_empty() {
@@ -899,18 +1190,16 @@ pub async fn stream_logs(
         }
     });
 
-    Sse::new(stream)
+    Ok(Sse::new(stream))
 }
 
 pub async fn download_export(
     State(state): State<Arc<AppState>>,
+    user: CurrentUser,
     Path(job_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let jobs = state.export_jobs.read().await;
-    let job = jobs.get(&job_id)
-        .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))?;
-
-    let file_path = job.file_path.clone()
+    let (file_path, _) = jobs::job_files(&state, &user, &job_id).await?;
+    let file_path = file_path
         .ok_or((StatusCode::NOT_FOUND, "Export file not found".to_string()))?;
 
     // Read file
@@ -932,15 +1221,10 @@ pub async fn download_export(
 
 pub async fn download_log(
     State(state): State<Arc<AppState>>,
+    user: CurrentUser,
     Path(job_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let jobs = state.export_jobs.read().await;
-    let _job = jobs.get(&job_id)
-        .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))?;
-    drop(jobs);
-
-    // Log file path
-    let log_file_path = format!("/tmp/postgres-explorer-exports/{}.log", job_id);
+    let (_, log_file_path) = jobs::job_files(&state, &user, &job_id).await?;
 
     // Check if log file exists
     if !tokio::fs::try_exists(&log_file_path).await.unwrap_or(false) {
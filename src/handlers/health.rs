@@ -1,6 +1,156 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
 use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::auth::CurrentUser;
+use crate::handlers::{all_endpoints, connect_pg, AppState};
+
+/// Limit pro jednu kontrolu readiness probe
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+/// Nástroje pro export, import a konzoli
+const PG_TOOLS: [&str; 3] = ["pg_dump", "pg_restore", "psql"];
+/// Jak dlouho platí výsledek readiness kontrol (probe se ptá často, kontroly spouští procesy)
+const READY_CACHE_TTL: Duration = Duration::from_secs(5);
 
 /// Simple health check endpoint that returns OK
 pub async fn healthz() -> (StatusCode, &'static str) {
     (StatusCode::OK, "OK")
 }
+
+#[derive(Debug, Serialize)]
+pub struct ReadyCheck {
+    pub name: String,
+    pub ok: bool,
+    pub duration_ms: u128,
+    /// Verze nástroje nebo chyba
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<ReadyCheck>,
+}
+
+/// Veřejná odpověď /readyz: jen výsledek, bez názvů endpointů a chyb
+#[derive(Debug, Serialize)]
+pub struct ReadySummary {
+    pub ready: bool,
+}
+
+/// Čas kontroly a její výsledek
+type CachedReadiness = Option<(Instant, Arc<Readiness>)>;
+
+/// Poslední výsledek kontrol; zámek se drží i během kontrol, souběžné probe počkají na jeden běh
+#[derive(Clone, Default)]
+pub struct ReadyCache {
+    last: Arc<Mutex<CachedReadiness>>,
+}
+
+/// Spustí kontrolu s timeoutem a změří ji
+async fn check<F>(name: String, future: F) -> ReadyCheck
+where
+    F: Future<Output = anyhow::Result<Option<String>>>,
+{
+    let started = Instant::now();
+    let result = match tokio::time::timeout(READY_CHECK_TIMEOUT, future).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("Timed out after {}s", READY_CHECK_TIMEOUT.as_secs())),
+    };
+    let duration_ms = started.elapsed().as_millis();
+    match result {
+        Ok(detail) => ReadyCheck { name, ok: true, duration_ms, detail },
+        Err(e) => ReadyCheck { name, ok: false, duration_ms, detail: Some(format!("{:#}", e)) },
+    }
+}
+
+/// První řádek `<tool> --version`, např. "pg_dump (PostgreSQL) 17.2"
+async fn tool_version(tool: &str) -> anyhow::Result<Option<String>> {
+    let output = tokio::process::Command::new(tool)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("{} not available: {}", tool, e))?;
+    if !output.status.success() {
+        anyhow::bail!("{} --version failed with {}", tool, output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().map(|line| line.trim().to_string()))
+}
+
+/// GET /readyz: jen ready/not ready, pro probe bez přihlášení
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<ReadySummary>) {
+    let readiness = readiness(&state).await;
+    (status_of(&readiness), Json(ReadySummary { ready: readiness.ready }))
+}
+
+/// GET /readyz/details: rozpis kontrol s chybami, jen pro adminy
+pub async fn readyz_details(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+) -> Result<(StatusCode, Json<Arc<Readiness>>), (StatusCode, String)> {
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, "Only administrators can see readiness details".to_string()));
+    }
+    let readiness = readiness(&state).await;
+    Ok((status_of(&readiness), Json(readiness)))
+}
+
+fn status_of(readiness: &Readiness) -> StatusCode {
+    if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE }
+}
+
+/// Výsledek kontrol z cache, nebo nový běh po vypršení READY_CACHE_TTL
+async fn readiness(state: &Arc<AppState>) -> Arc<Readiness> {
+    let mut last = state.readyz_cache.last.lock().await;
+    if let Some((checked_at, readiness)) = last.as_ref()
+        && checked_at.elapsed() < READY_CACHE_TTL
+    {
+        return readiness.clone();
+    }
+    let readiness = Arc::new(run_checks(state).await);
+    *last = Some((Instant::now(), readiness.clone()));
+    readiness
+}
+
+/// SQLite, nástroje pg_* a volitelně `SELECT 1` na endpointy (--readyz-endpoints)
+async fn run_checks(state: &Arc<AppState>) -> Readiness {
+    let mut checks = Vec::new();
+
+    if let Some(db) = &state.db {
+        checks.push(check("sqlite".to_string(), async { db.check_writable().await.map(|_| None) }).await);
+    }
+
+    let tools = PG_TOOLS.iter().map(|tool| check(tool.to_string(), tool_version(tool)));
+    checks.extend(futures::future::join_all(tools).await);
+
+    if state.readyz_endpoints {
+        match all_endpoints(state).await {
+            Ok(endpoints) => {
+                let probes = endpoints.iter().map(|endpoint| {
+                    check(format!("endpoint:{}", endpoint.name), async {
+                        let pg = connect_pg(state, endpoint).await?;
+                        sqlx::query("SELECT 1").execute(&pg).await?;
+                        Ok(None)
+                    })
+                });
+                checks.extend(futures::future::join_all(probes).await);
+            }
+            Err(e) => checks.push(ReadyCheck {
+                name: "endpoints".to_string(),
+                ok: false,
+                duration_ms: 0,
+                detail: Some(e.to_string()),
+            }),
+        }
+    }
+
+    let ready = checks.iter().all(|c| c.ok);
+    Readiness { ready, checks }
+}
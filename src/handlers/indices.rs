@@ -6,21 +6,23 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 use sqlx::Row;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, cache_key, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::db_session::in_current_session;
+use crate::handlers::replica::{connect_pg_read, note_served_by, with_served_by};
 use crate::templates::{IndexRow, IndicesTemplate, IndicesTableTemplate};
 use crate::utils::format::bytes_to_human;
 use crate::utils::filter::{matches_simple_terms, parse_simple_terms};
 use std::time::Instant;
 
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Clone, serde::Serialize)]
 pub struct IndexRowDb {
-    schema: String,
-    table_name: String,
-    index_name: String,
-    size_bytes: i64,
-    scans: Option<i64>,
-    idx_tup_read: Option<i64>,
-    idx_tup_fetch: Option<i64>,
+    pub schema: String,
+    pub table_name: String,
+    pub index_name: String,
+    pub size_bytes: i64,
+    pub scans: Option<i64>,
+    pub idx_tup_read: Option<i64>,
+    pub idx_tup_fetch: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -49,11 +51,11 @@ fn default_per_page() -> usize { 50 }
 fn default_sort_by() -> String { "size".to_string() }
 fn default_sort_order() -> String { "desc".to_string() }
 
-async fn fetch_indices_from_db(
+pub async fn fetch_indices_from_db(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> Result<Vec<IndexRowDb>, String> {
-    match connect_pg(state, active).await {
+    match connect_pg_read(state, active).await {
         Ok(pg) => match sqlx::query_as::<_, IndexRowDb>(
             r#"
             SELECT n.nspname as schema,
@@ -82,7 +84,7 @@ async fn fetch_indices_from_db(
     }
 }
 
-async fn get_cached_indices(
+pub async fn get_cached_indices(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> (Vec<IndexRowDb>, bool) {
@@ -90,32 +92,38 @@ async fn get_cached_indices(
     let mut should_refresh = false;
     let (data, fetching) = {
         let mut cache = state.indices_cache.write().await;
-        match cache.get_mut(&active.id) {
+        match cache.get_mut(&cache_key(active)) {
             Some(entry) => {
                 let stale = now.duration_since(entry.fetched_at) > CACHE_TTL;
                 if stale && !entry.fetching {
                     entry.fetching = true;
                     should_refresh = true;
                 }
+                state.metrics.cache_lookup("indices", true);
                 tracing::debug!(
                     "indices cache hit id={} stale={} fetching={}",
                     active.id,
                     stale,
                     entry.fetching
                 );
+                if let Some(label) = &entry.served_by {
+                    note_served_by(label.clone());
+                }
                 (entry.data.clone(), entry.fetching)
             }
             None => {
                 cache.insert(
-                    active.id,
+                    cache_key(active),
                     CacheEntry {
                         data: Vec::new(),
                         fetched_at: now,
                         fetching: true,
+                        served_by: None,
                     },
                 );
                 should_refresh = true;
                 tracing::debug!("indices cache miss id={}, scheduling refresh", active.id);
+                state.metrics.cache_lookup("indices", false);
                 (Vec::new(), true)
             }
         }
@@ -124,17 +132,18 @@ async fn get_cached_indices(
     if should_refresh {
         let state = state.clone();
         let active = active.clone();
-        tokio::spawn(async move {
-            let result = fetch_indices_from_db(&state, &active).await;
+        tokio::spawn(in_current_session(async move {
+            let (result, served_by) = with_served_by(fetch_indices_from_db(&state, &active)).await;
             let mut cache = state.indices_cache.write().await;
-            if let Some(entry) = cache.get_mut(&active.id) {
+            if let Some(entry) = cache.get_mut(&cache_key(&active)) {
                 if let Ok(rows) = result {
                     entry.data = rows;
+                    entry.served_by = served_by;
                     entry.fetched_at = Instant::now();
                 }
                 entry.fetching = false;
             }
-        });
+        }));
     }
 
     (data, fetching)
@@ -507,7 +516,7 @@ pub async fn index_info(
     let mut html = String::new();
     html.push_str("<div class='card'><div class='card-body'>");
 
-    match connect_pg(&state, &active).await {
+    match connect_pg_read(&state, &active).await {
         Ok(pg) => {
             if let Ok(row_opt) = sqlx::query(
                 r#"
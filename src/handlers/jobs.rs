@@ -0,0 +1,430 @@
+//! Historie background jobů (export, import, console) v SQLite - přežije restart serveru
+
+use askama::Template;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::{require_role, resolve_role, CurrentUser, Role};
+use crate::db::models::{Endpoint, JobFilter, JobRecord, NewJob};
+use crate::handlers::{audit, base_path_url, build_ctx_with_endpoint, console, export, get_active_endpoint, AppState, ExportJob};
+use crate::templates::JobsTemplate;
+
+pub const KINDS: &[&str] = &["export", "import", "console"];
+pub const STATUSES: &[&str] = &["running", "completed", "failed", "cancelled", "interrupted"];
+/// Chyba zrušeného jobu (v paměti, v tabulce jobs i v audit logu)
+pub const CANCELLED: &str = "Cancelled by user";
+
+const PAGE_LIMIT: i64 = 200;
+/// Jak dlouho po SIGTERM čekat, než proces dostane SIGKILL
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Založí job v paměti (průběh, logy) a v tabulce jobs; vrací job_id, např. export_<uuid>
+pub async fn register<P: Serialize>(
+    state: &AppState,
+    kind: &str,
+    endpoint: &Endpoint,
+    user: &CurrentUser,
+    params: &P,
+    command: Option<String>,
+) -> String {
+    let job_id = format!("{}_{}", kind, uuid::Uuid::new_v4());
+    let mut job = ExportJob::new(&job_id);
+    job.command = command.clone();
+    job.endpoint_id = Some(endpoint.id);
+    state.export_jobs.write().await.insert(job_id.clone(), job);
+
+    let Some(db) = state.db.as_ref() else {
+        return job_id;
+    };
+    let record = NewJob {
+        id: job_id.clone(),
+        kind: kind.to_string(),
+        endpoint_id: Some(endpoint.id),
+        endpoint_name: Some(endpoint.name.clone()),
+        database_name: audit::database_name(&endpoint.url),
+        username: user.audit_name(),
+        params: serde_json::to_string(params).unwrap_or_else(|_| "{}".to_string()),
+        command,
+    };
+    if let Err(e) = db.insert_job(&record).await {
+        tracing::error!("Failed to persist job {}: {}", job_id, e);
+    }
+    job_id
+}
+
+/// Token pro zrušení jobu (job mimo registr nejde zrušit)
+pub async fn cancel_token(state: &AppState, job_id: &str) -> CancellationToken {
+    state
+        .export_jobs
+        .read()
+        .await
+        .get(job_id)
+        .map(|job| job.cancel.clone())
+        .unwrap_or_default()
+}
+
+/// pg_dump/pg_restore/psql poběží ve vlastní process group, aby šly ukončit i s potomky
+pub fn own_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+}
+
+/// Počká na proces; při zrušení jobu ho ukončí a vrátí None
+pub async fn wait_or_cancel(child: &mut Child, cancel: &CancellationToken) -> Option<std::io::Result<ExitStatus>> {
+    let waited = tokio::select! {
+        status = child.wait() => Some(status),
+        _ = cancel.cancelled() => None,
+    };
+    if waited.is_none() {
+        terminate(child).await;
+    }
+    waited
+}
+
+/// SIGINT celé process group (pg_dump -j má workery) - jako Ctrl+C pošlou nástroje serveru
+/// cancel request, jinak by dotaz na serveru doběhl; po KILL_GRACE SIGKILL
+pub async fn terminate(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        if let Some(pid) = child.id() {
+            let group = -(pid as libc::pid_t);
+            // SAFETY: kill() jen posílá signál, group je naše vlastní process group potomka
+            unsafe { libc::kill(group, libc::SIGINT) };
+            if tokio::time::timeout(KILL_GRACE, child.wait()).await.is_ok() {
+                return;
+            }
+            // SAFETY: viz výše
+            unsafe { libc::kill(group, libc::SIGKILL) };
+        }
+    }
+    child.start_kill().ok();
+    child.wait().await.ok();
+}
+
+/// Log jobu leží vedle dumpů jeho endpointu
+fn log_path(state: &AppState, endpoint_id: Option<i64>, job_id: &str) -> PathBuf {
+    match endpoint_id {
+        Some(endpoint_id) => state.storage.log_path(endpoint_id, job_id),
+        None => state.storage.root.join("exports").join(format!("{}.log", job_id)),
+    }
+}
+
+/// Zapíše výsledek dokončeného jobu; console job log soubor nemá, uloží se z paměti
+pub async fn finish(state: &AppState, job_id: &str) {
+    let Some(db) = state.db.as_ref() else {
+        return;
+    };
+    let job = state.export_jobs.read().await.get(job_id).cloned();
+    let Some(job) = job else {
+        return;
+    };
+
+    let log_path = log_path(state, job.endpoint_id, job_id);
+    if !tokio::fs::try_exists(&log_path).await.unwrap_or(false) {
+        let content: String = job.logs.iter().map(|line| format!("{}\n", line)).collect();
+        let dir = log_path.parent().unwrap_or(&state.storage.root);
+        let written = match tokio::fs::create_dir_all(dir).await {
+            Ok(()) => tokio::fs::write(&log_path, content).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            tracing::warn!("Failed to write log of job {}: {}", job_id, e);
+        }
+    }
+
+    if let Err(e) = db
+        .finish_job(
+            job_id,
+            job.status.as_str(),
+            job.command.as_deref(),
+            job.error.as_deref(),
+            job.file_path.as_deref(),
+            Some(&log_path.to_string_lossy()),
+        )
+        .await
+    {
+        tracing::error!("Failed to persist result of job {}: {}", job_id, e);
+    }
+}
+
+/// Výsledek a log jobu - z paměti, po restartu z tabulky jobs; vyžaduje operátora na endpointu jobu
+pub async fn job_files(
+    state: &AppState,
+    user: &CurrentUser,
+    job_id: &str,
+) -> Result<(Option<String>, String), (StatusCode, String)> {
+    let record = match state.db.as_ref() {
+        Some(db) => db
+            .get_job(job_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        None => None,
+    };
+    let in_memory = state
+        .export_jobs
+        .read()
+        .await
+        .get(job_id)
+        .map(|job| (job.file_path.clone(), job.endpoint_id));
+    if in_memory.is_none() && record.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Job not found".to_string()));
+    }
+
+    // Bez lokálního úložiště (nebo po chybě zápisu) záznam chybí, endpoint pak bereme z paměti
+    let endpoint_id = record
+        .as_ref()
+        .and_then(|r| r.endpoint_id)
+        .or_else(|| in_memory.as_ref().and_then(|(_, endpoint_id)| *endpoint_id));
+    let Some(endpoint_id) = endpoint_id else {
+        return Err((StatusCode::FORBIDDEN, "Job is not linked to a connection".to_string()));
+    };
+    require_role(state, user, endpoint_id, Role::Operator, "download job files")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    let log = record
+        .as_ref()
+        .and_then(|r| r.log_path.clone())
+        .unwrap_or_else(|| log_path(state, Some(endpoint_id), job_id).to_string_lossy().into_owned());
+    let file_path = match in_memory {
+        Some((file_path, _)) => file_path,
+        None => record.and_then(|r| r.artifact_path),
+    };
+    Ok((file_path, log))
+}
+
+/// Role uživatele na endpointu běžícího jobu (stav, log); neznámý job i nedostatečná role jsou 404,
+/// aby se cizí joby nedaly ani zjistit
+pub async fn job_role(
+    state: &AppState,
+    user: &CurrentUser,
+    job_id: &str,
+    required: Role,
+    action: &str,
+) -> Result<Role, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Job not found".to_string());
+    let endpoint_id = state
+        .export_jobs
+        .read()
+        .await
+        .get(job_id)
+        .and_then(|job| job.endpoint_id)
+        .ok_or_else(not_found)?;
+    require_role(state, user, endpoint_id, required, action).map_err(|_| not_found())?;
+    Ok(resolve_role(state, user, endpoint_id))
+}
+
+/// Role potřebná ke zrušení jobu - stejná jako na jeho spuštění (import admin, jinak operátor)
+pub fn cancel_role(job_id: &str) -> Role {
+    if job_id.starts_with("import_") { Role::Admin } else { Role::Operator }
+}
+
+/// Zruší běžící job; právo jako na jeho spuštění (import admin, jinak operátor)
+async fn cancel_job(state: &AppState, user: &CurrentUser, job_id: &str) -> Result<(), (StatusCode, String)> {
+    let (endpoint_id, token, done) = {
+        let jobs = state.export_jobs.read().await;
+        let job = jobs
+            .get(job_id)
+            .ok_or((StatusCode::NOT_FOUND, "Job not found or no longer running".to_string()))?;
+        (job.endpoint_id, job.cancel.clone(), job.status.is_done())
+    };
+    if let Some(endpoint_id) = endpoint_id {
+        require_role(state, user, endpoint_id, cancel_role(job_id), "cancel jobs").map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    }
+    if done {
+        return Err((StatusCode::CONFLICT, "Job is not running".to_string()));
+    }
+    token.cancel();
+    tracing::info!("Job {} cancelled by {}", job_id, user.audit_name());
+    Ok(())
+}
+
+/// POST /maintenance/export|import/{job_id}/cancel, /dev/{job_id}/cancel
+pub async fn cancel(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(job_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    cancel_job(&state, &user, &job_id).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// POST /jobs/{job_id}/cancel ze stránky /jobs
+pub async fn cancel_from_page(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(job_id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    cancel_job(&state, &user, &job_id).await?;
+    Ok(Redirect::to(&base_path_url(&state, "/jobs")).into_response())
+}
+
+/// Řádek na stránce /jobs
+pub struct JobRow {
+    pub job: JobRecord,
+    pub has_artifact: bool,
+    /// s3://bucket/key nahraného dumpu
+    pub remote: Option<String>,
+    pub has_log: bool,
+    pub can_rerun: bool,
+    /// Běží v tomto procesu (po restartu už zrušit nejde)
+    pub can_cancel: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct JobsQuery {
+    pub kind: Option<String>,
+    pub status: Option<String>,
+}
+
+fn path_exists(path: Option<&str>) -> bool {
+    path.is_some_and(|p| std::path::Path::new(p).exists())
+}
+
+pub async fn jobs_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Query(query): Query<JobsQuery>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let db = state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Job history is not available in stateless mode".to_string()))?;
+
+    let non_empty = |v: &Option<String>| v.as_ref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let filter = JobFilter {
+        kind: non_empty(&query.kind),
+        status: non_empty(&query.status),
+        limit: Some(PAGE_LIMIT),
+    };
+    let records = db
+        .get_jobs(&filter)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let limit_reached = records.len() as i64 >= PAGE_LIMIT;
+
+    let live: Vec<String> = state
+        .export_jobs
+        .read()
+        .await
+        .values()
+        .filter(|job| !job.status.is_done())
+        .map(|job| job.job_id.clone())
+        .collect();
+
+    let buckets: HashMap<i64, String> = db
+        .get_storage_targets()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .map(|t| (t.id, t.bucket))
+        .collect();
+
+    // Job vidí operátoři jeho endpointu, joby bez endpointu jen administrátor
+    let jobs = records
+        .into_iter()
+        .filter_map(|job| {
+            let role = match job.endpoint_id {
+                Some(id) => resolve_role(&state, &user, id),
+                None if user.is_admin => Role::Admin,
+                None => Role::None,
+            };
+            if role < Role::Operator {
+                return None;
+            }
+            let required = if job.kind == "import" { Role::Admin } else { Role::Operator };
+            Some(JobRow {
+                has_artifact: path_exists(job.artifact_path.as_deref()),
+                remote: job.remote_key.as_ref().map(|key| match job.target_id.and_then(|id| buckets.get(&id)) {
+                    Some(bucket) => format!("s3://{}/{}", bucket, key),
+                    None => format!("{} (deleted target)", key),
+                }),
+                has_log: path_exists(job.log_path.as_deref()),
+                can_rerun: job.status != "running" && role >= required,
+                can_cancel: role >= required && live.contains(&job.id),
+                job,
+            })
+        })
+        .collect();
+
+    let active = get_active_endpoint(&state, &jar).await;
+    let tpl = JobsTemplate {
+        ctx: build_ctx_with_endpoint(&state, active.as_ref()),
+        title: "Jobs | Postgres Explorer".to_string(),
+        jobs,
+        kinds: KINDS.iter().map(|k| k.to_string()).collect(),
+        statuses: STATUSES.iter().map(|s| s.to_string()).collect(),
+        f_kind: query.kind.clone().unwrap_or_default(),
+        f_status: query.status.clone().unwrap_or_default(),
+        limit_reached,
+    };
+    tpl.render()
+        .map(Html)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// POST /jobs/{id}/rerun: stejné parametry na aktivním spojení, které musí odpovídat původnímu jobu
+pub async fn rerun(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Path(job_id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Job history is not available in stateless mode".to_string()))?;
+    let job = db
+        .get_job(&job_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))?;
+    if job.status == "running" {
+        return Err((StatusCode::CONFLICT, "Job is still running".to_string()));
+    }
+
+    // Parametry (cesty, názvy tabulek) platí jen pro původní spojení a databázi
+    let active = get_active_endpoint(&state, &jar)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    if job.endpoint_id != Some(active.id) || job.database_name != audit::database_name(&active.url) {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Select connection '{}' and database '{}' before re-running this job",
+                job.endpoint_name.as_deref().unwrap_or("-"),
+                job.database_name.as_deref().unwrap_or("-")
+            ),
+        ));
+    }
+
+    let invalid = |e: serde_json::Error| (StatusCode::BAD_REQUEST, format!("Stored job parameters are invalid: {}", e));
+    let new_job_id = match job.kind.as_str() {
+        "export" => {
+            let req = serde_json::from_str(&job.params).map_err(invalid)?;
+            export::start_export_job(&state, active, user, req).await?
+        }
+        "import" => {
+            let req = serde_json::from_str(&job.params).map_err(invalid)?;
+            export::start_import_job(&state, active, user, req).await?
+        }
+        "console" => {
+            let req = serde_json::from_str(&job.params).map_err(invalid)?;
+            console::start_query(&state, active, user, req).await?
+        }
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unknown job kind: {}", other))),
+    };
+    tracing::info!("Job {} re-run as {}", job_id, new_job_id);
+
+    Ok(Redirect::to(&base_path_url(&state, "/jobs")).into_response())
+}
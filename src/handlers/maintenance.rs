@@ -5,7 +5,8 @@ use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::handlers::{connect_pg, get_active_endpoint, AppState};
+use crate::auth::{require_role, CurrentUser, Role};
+use crate::handlers::{audit, connect_pg, get_active_endpoint, AppState};
 
 #[derive(Serialize)]
 pub struct MaintenanceResponse {
@@ -17,9 +18,10 @@ pub struct MaintenanceResponse {
 pub async fn reindex_index(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, index_name)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
-    execute_maintenance(&state, &jar, &format!(
+    execute_maintenance(&state, &jar, &user, &format!(
         "REINDEX INDEX {}.{}",
         quote_ident(&schema),
         quote_ident(&index_name)
@@ -30,9 +32,10 @@ pub async fn reindex_index(
 pub async fn reindex_table(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
-    execute_maintenance(&state, &jar, &format!(
+    execute_maintenance(&state, &jar, &user, &format!(
         "REINDEX TABLE {}.{}",
         quote_ident(&schema),
         quote_ident(&table)
@@ -43,9 +46,10 @@ pub async fn reindex_table(
 pub async fn vacuum_table(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
-    execute_maintenance(&state, &jar, &format!(
+    execute_maintenance(&state, &jar, &user, &format!(
         "VACUUM {}.{}",
         quote_ident(&schema),
         quote_ident(&table)
@@ -56,9 +60,10 @@ pub async fn vacuum_table(
 pub async fn vacuum_full_table(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
-    execute_maintenance(&state, &jar, &format!(
+    execute_maintenance(&state, &jar, &user, &format!(
         "VACUUM FULL {}.{}",
         quote_ident(&schema),
         quote_ident(&table)
@@ -69,9 +74,10 @@ pub async fn vacuum_full_table(
 pub async fn analyze_table(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
-    execute_maintenance(&state, &jar, &format!(
+    execute_maintenance(&state, &jar, &user, &format!(
         "ANALYZE {}.{}",
         quote_ident(&schema),
         quote_ident(&table)
@@ -81,6 +87,7 @@ pub async fn analyze_table(
 async fn execute_maintenance(
     state: &Arc<AppState>,
     jar: &CookieJar,
+    user: &CurrentUser,
     sql: &str,
     operation: &str,
 ) -> Json<MaintenanceResponse> {
@@ -94,6 +101,13 @@ async fn execute_maintenance(
         }
     };
 
+    if let Err(e) = require_role(state, user, active.id, Role::Operator, operation) {
+        return Json(MaintenanceResponse {
+            success: false,
+            error: Some(e),
+        });
+    }
+
     let pg = match connect_pg(state, &active).await {
         Ok(p) => p,
         Err(e) => {
@@ -104,7 +118,11 @@ async fn execute_maintenance(
         }
     };
 
-    match sqlx::query(sql).execute(&pg).await {
+    let result = sqlx::query(sql).execute(&pg).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    audit::record(state, user, &active, "maintenance", sql, error.as_deref()).await;
+
+    match result {
         Ok(_) => {
             tracing::info!("Successfully executed: {}", sql);
             Json(MaintenanceResponse {
@@ -136,6 +154,7 @@ pub struct AutovacuumSettings {
 pub async fn set_autovacuum(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
     AxumJson(settings): AxumJson<AutovacuumSettings>,
 ) -> Json<MaintenanceResponse> {
@@ -149,6 +168,13 @@ pub async fn set_autovacuum(
         }
     };
 
+    if let Err(e) = require_role(&state, &user, active.id, Role::Operator, "set autovacuum") {
+        return Json(MaintenanceResponse {
+            success: false,
+            error: Some(e),
+        });
+    }
+
     let pg = match connect_pg(&state, &active).await {
         Ok(p) => p,
         Err(e) => {
@@ -173,11 +199,13 @@ pub async fn set_autovacuum(
     for sql in &sqls {
         if let Err(e) = sqlx::query(sql).execute(&pg).await {
             tracing::error!("Failed to set autovacuum: {}", e);
+            audit::record(&state, &user, &active, "autovacuum", sql, Some(&e.to_string())).await;
             return Json(MaintenanceResponse {
                 success: false,
                 error: Some(format!("Failed to set autovacuum: {}", e)),
             });
         }
+        audit::record(&state, &user, &active, "autovacuum", sql, None).await;
     }
 
     tracing::info!("Successfully configured autovacuum for {}.{}", schema, table);
@@ -191,6 +219,7 @@ pub async fn set_autovacuum(
 pub async fn reset_autovacuum(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    user: CurrentUser,
     Path((schema, table)): Path<(String, String)>,
 ) -> Json<MaintenanceResponse> {
     let active = match get_active_endpoint(&state, &jar).await {
@@ -203,6 +232,13 @@ pub async fn reset_autovacuum(
         }
     };
 
+    if let Err(e) = require_role(&state, &user, active.id, Role::Operator, "reset autovacuum") {
+        return Json(MaintenanceResponse {
+            success: false,
+            error: Some(e),
+        });
+    }
+
     let pg = match connect_pg(&state, &active).await {
         Ok(p) => p,
         Err(e) => {
@@ -216,7 +252,11 @@ pub async fn reset_autovacuum(
     let table_name = format!("{}.{}", quote_ident(&schema), quote_ident(&table));
     let sql = format!("ALTER TABLE {} RESET (autovacuum_vacuum_scale_factor, autovacuum_vacuum_threshold)", table_name);
 
-    match sqlx::query(&sql).execute(&pg).await {
+    let result = sqlx::query(&sql).execute(&pg).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    audit::record(&state, &user, &active, "autovacuum", &sql, error.as_deref()).await;
+
+    match result {
         Ok(_) => {
             tracing::info!("Successfully reset autovacuum settings for {}.{}", schema, table);
             Json(MaintenanceResponse {
@@ -0,0 +1,315 @@
+//! Prometheus metriky (/metrics): HTTP requesty, joby, cache seznamů, pooly
+//! a volitelně stav spravovaných Postgresů (--metrics-database-stats, sbírá se na pozadí).
+//! Endpoint vyžaduje přihlášení nebo API token se scope `read`; řady konkrétních
+//! endpointů vidí jen uživatel s alespoň rolí viewer.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sqlx::Row;
+
+use crate::auth::{resolve_role, CurrentUser, Role};
+use crate::handlers::dashboard::{CACHE_HIT_RATIO_SQL, CONNECTIONS_SQL, OVERVIEW_SQL};
+use crate::handlers::replica::connect_pg_read;
+use crate::handlers::{all_endpoints, AppState};
+
+/// Hranice histogramu latence v sekundách
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+/// Jak dlouho čekat na jeden Postgres při sběru
+const DATABASE_STATS_TIMEOUT: Duration = Duration::from_secs(5);
+/// Jak často se na pozadí sbírají metriky Postgresů
+pub const DATABASE_STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// (metoda, route, status)
+type RequestKey = (String, String, u16);
+
+#[derive(Default)]
+struct RequestStats {
+    count: u64,
+    sum_secs: f64,
+    /// Kumulativní počty pro LATENCY_BUCKETS
+    buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+/// Čítače sbírané za běhu (ostatní metriky se počítají při scrapu)
+#[derive(Clone, Default)]
+pub struct Metrics {
+    requests: Arc<Mutex<BTreeMap<RequestKey, RequestStats>>>,
+    /// (cache, hit/miss)
+    cache_lookups: Arc<Mutex<BTreeMap<(&'static str, &'static str), u64>>>,
+    /// Poslední výsledek sběru --metrics-database-stats (scrape se k Postgresům nepřipojuje)
+    databases: Arc<Mutex<Vec<DatabaseSample>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let Ok(mut requests) = self.requests.lock() else {
+            return;
+        };
+        let stats = requests.entry((method.to_string(), route.to_string(), status)).or_default();
+        let secs = elapsed.as_secs_f64();
+        stats.count += 1;
+        stats.sum_secs += secs;
+        for (bucket, le) in stats.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= le {
+                *bucket += 1;
+            }
+        }
+    }
+
+    /// Přístup do cache seznamu (schemas, tables, indices); miss = endpoint ještě v cache není
+    pub fn cache_lookup(&self, cache: &'static str, hit: bool) {
+        if let Ok(mut lookups) = self.cache_lookups.lock() {
+            *lookups.entry((cache, if hit { "hit" } else { "miss" })).or_default() += 1;
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        if let Ok(requests) = self.requests.lock() {
+            family(out, "pgexplorer_http_requests_total", "counter", "HTTP requests by route and status");
+            for ((method, route, status), stats) in requests.iter() {
+                let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\"", method, escape(route), status);
+                sample(out, "pgexplorer_http_requests_total", &labels, stats.count as f64);
+            }
+            family(out, "pgexplorer_http_request_duration_seconds", "histogram", "HTTP request latency by route");
+            for ((method, route), stats) in merge_statuses(&requests) {
+                let labels = format!("method=\"{}\",route=\"{}\"", method, escape(&route));
+                for (le, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                    let bucket_labels = format!("{},le=\"{}\"", labels, le);
+                    sample(out, "pgexplorer_http_request_duration_seconds_bucket", &bucket_labels, count as f64);
+                }
+                let inf = format!("{},le=\"+Inf\"", labels);
+                sample(out, "pgexplorer_http_request_duration_seconds_bucket", &inf, stats.count as f64);
+                sample(out, "pgexplorer_http_request_duration_seconds_sum", &labels, stats.sum_secs);
+                sample(out, "pgexplorer_http_request_duration_seconds_count", &labels, stats.count as f64);
+            }
+        }
+        if let Ok(lookups) = self.cache_lookups.lock() {
+            family(out, "pgexplorer_cache_lookups_total", "counter", "List cache lookups by cache and result");
+            for ((cache, result), count) in lookups.iter() {
+                let labels = format!("cache=\"{}\",result=\"{}\"", cache, result);
+                sample(out, "pgexplorer_cache_lookups_total", &labels, *count as f64);
+            }
+        }
+    }
+}
+
+/// Histogram je per route (status by zbytečně násobil počet řad)
+fn merge_statuses(
+    requests: &BTreeMap<RequestKey, RequestStats>,
+) -> BTreeMap<(String, String), RequestStats> {
+    let mut merged: BTreeMap<(String, String), RequestStats> = BTreeMap::new();
+    for ((method, route, _), stats) in requests {
+        let entry = merged.entry((method.clone(), route.clone())).or_default();
+        entry.count += stats.count;
+        entry.sum_secs += stats.sum_secs;
+        for (total, count) in entry.buckets.iter_mut().zip(stats.buckets) {
+            *total += count;
+        }
+    }
+    merged
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: f64) {
+    if labels.is_empty() {
+        let _ = writeln!(out, "{} {}", name, value);
+    } else {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Middleware: počet a latence requestů podle route šablony (ne konkrétní URL)
+pub async fn track_requests(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+    let response = next.run(req).await;
+    state.metrics.observe_request(&method, &route, response.status().as_u16(), started.elapsed());
+    response
+}
+
+/// GET /metrics
+pub async fn metrics(State(state): State<Arc<AppState>>, user: CurrentUser) -> impl IntoResponse {
+    let visible = |endpoint_id: i64| resolve_role(&state, &user, endpoint_id) >= Role::Viewer;
+    let mut out = String::new();
+    state.metrics.render(&mut out);
+    render_jobs(&state, &mut out).await;
+    render_pools(&state, &mut out, &visible).await;
+    if state.metrics_database_stats {
+        render_databases(&state, &mut out, &visible);
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")], out)
+}
+
+async fn render_jobs(state: &AppState, out: &mut String) {
+    let mut counts: BTreeMap<(String, &'static str), u64> = BTreeMap::new();
+    for job in state.export_jobs.read().await.values() {
+        // ID jobu začíná druhem (export_..., import_...)
+        let kind = job.job_id.split('_').next().unwrap_or("job").to_string();
+        *counts.entry((kind, job.status.as_str())).or_default() += 1;
+    }
+    family(out, "pgexplorer_jobs", "gauge", "Export and import jobs kept in memory by status");
+    for ((kind, status), count) in counts {
+        sample(out, "pgexplorer_jobs", &format!("kind=\"{}\",status=\"{}\"", escape(&kind), status), count as f64);
+    }
+}
+
+async fn render_pools(state: &AppState, out: &mut String, visible: &(dyn Fn(i64) -> bool + Sync)) {
+    let mut stats = state.pg_pools.stats().await;
+    stats.retain(|pool| visible(pool.endpoint_id));
+    family(out, "pgexplorer_pg_pool_connections", "gauge", "Open Postgres pool connections by state");
+    for pool in &stats {
+        let labels = format!("endpoint_id=\"{}\",pool=\"{}\"", pool.endpoint_id, escape(&pool.label));
+        let idle = pool.idle as f64;
+        sample(out, "pgexplorer_pg_pool_connections", &format!("{},state=\"idle\"", labels), idle);
+        sample(out, "pgexplorer_pg_pool_connections", &format!("{},state=\"active\"", labels), pool.size as f64 - idle);
+    }
+    family(out, "pgexplorer_pg_pool_max_connections", "gauge", "Maximum connections per Postgres pool");
+    for pool in &stats {
+        let labels = format!("endpoint_id=\"{}\",pool=\"{}\"", pool.endpoint_id, escape(&pool.label));
+        sample(out, "pgexplorer_pg_pool_max_connections", &labels, pool.max as f64);
+    }
+}
+
+#[derive(Clone, Default)]
+struct DatabaseGauges {
+    size_bytes: Option<i64>,
+    cache_hit_ratio: Option<f64>,
+    active_connections: Option<i64>,
+    max_connections: Option<i32>,
+}
+
+/// Stejné dotazy jako dashboard; None = endpoint nedostupný
+async fn database_gauges(state: &Arc<AppState>, endpoint: &crate::db::models::Endpoint) -> Option<DatabaseGauges> {
+    let pg = connect_pg_read(state, endpoint).await.ok()?;
+    let mut gauges = DatabaseGauges::default();
+    if let Ok(row) = sqlx::query(OVERVIEW_SQL).fetch_one(&pg).await {
+        gauges.size_bytes = row.try_get("total_size").ok();
+    }
+    if let Ok(row) = sqlx::query(CACHE_HIT_RATIO_SQL).fetch_one(&pg).await {
+        gauges.cache_hit_ratio = row.try_get::<f64, _>("cache_hit_ratio").ok().map(|pct| pct / 100.0);
+    }
+    if let Ok(row) = sqlx::query(CONNECTIONS_SQL).fetch_one(&pg).await {
+        gauges.active_connections = row.try_get("active_connections").ok();
+        gauges.max_connections = row.try_get("max_connections").ok();
+    }
+    Some(gauges)
+}
+
+/// Výsledek sběru pro jeden endpoint; gauges None = endpoint nedostupný
+struct DatabaseSample {
+    endpoint_id: i64,
+    endpoint_name: String,
+    gauges: Option<DatabaseGauges>,
+}
+
+/// Dotáže se všech endpointů (paralelně, každý s timeoutem)
+async fn collect_databases(state: &Arc<AppState>) -> anyhow::Result<Vec<DatabaseSample>> {
+    let endpoints = all_endpoints(state).await?;
+    let results = futures::future::join_all(endpoints.iter().map(|endpoint| async move {
+        tokio::time::timeout(DATABASE_STATS_TIMEOUT, database_gauges(state, endpoint))
+            .await
+            .ok()
+            .flatten()
+    }))
+    .await;
+    Ok(endpoints
+        .into_iter()
+        .zip(results)
+        .map(|(endpoint, gauges)| DatabaseSample {
+            endpoint_id: endpoint.id,
+            endpoint_name: endpoint.name,
+            gauges,
+        })
+        .collect())
+}
+
+/// Periodicky sbírá metriky Postgresů pro /metrics (jen s --metrics-database-stats)
+pub fn spawn_database_stats_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DATABASE_STATS_INTERVAL);
+        loop {
+            interval.tick().await;
+            match collect_databases(&state).await {
+                Ok(samples) => {
+                    if let Ok(mut databases) = state.metrics.databases.lock() {
+                        *databases = samples;
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to load endpoints for metrics: {}", e),
+            }
+        }
+    });
+}
+
+fn render_databases(state: &AppState, out: &mut String, visible: &(dyn Fn(i64) -> bool + Sync)) {
+    let Ok(databases) = state.metrics.databases.lock() else {
+        return;
+    };
+    let samples: Vec<&DatabaseSample> = databases.iter().filter(|s| visible(s.endpoint_id)).collect();
+    let labels = |sample: &DatabaseSample| {
+        format!("endpoint_id=\"{}\",endpoint=\"{}\"", sample.endpoint_id, escape(&sample.endpoint_name))
+    };
+    family(out, "pgexplorer_pg_up", "gauge", "Whether the Postgres endpoint answered the metrics queries");
+    for sample in &samples {
+        let up = if sample.gauges.is_some() { 1.0 } else { 0.0 };
+        self::sample(out, "pgexplorer_pg_up", &labels(sample), up);
+    }
+    type Gauge = (&'static str, &'static str, fn(&DatabaseGauges) -> Option<f64>);
+    let metrics: [Gauge; 4] = [
+        ("pgexplorer_pg_database_size_bytes", "Size of the current database", |g| g.size_bytes.map(|v| v as f64)),
+        ("pgexplorer_pg_cache_hit_ratio", "Buffer cache hit ratio of the current database", |g| g.cache_hit_ratio),
+        ("pgexplorer_pg_active_connections", "Active backends", |g| g.active_connections.map(|v| v as f64)),
+        ("pgexplorer_pg_max_connections", "max_connections setting", |g| g.max_connections.map(f64::from)),
+    ];
+    for (name, help, value) in metrics {
+        family(out, name, "gauge", help);
+        for sample in &samples {
+            if let Some(v) = sample.gauges.as_ref().and_then(value) {
+                self::sample(out, name, &labels(sample), v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_requests_and_cache() {
+        let metrics = Metrics::new();
+        metrics.observe_request("GET", "/tables/{schema}/{filter}", 200, Duration::from_millis(30));
+        metrics.observe_request("GET", "/tables/{schema}/{filter}", 500, Duration::from_secs(20));
+        metrics.cache_lookup("tables", false);
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("pgexplorer_http_requests_total{method=\"GET\",route=\"/tables/{schema}/{filter}\",status=\"500\"} 1\n"));
+        assert!(out.contains("pgexplorer_http_request_duration_seconds_bucket{method=\"GET\",route=\"/tables/{schema}/{filter}\",le=\"0.05\"} 1\n"));
+        assert!(out.contains("pgexplorer_http_request_duration_seconds_bucket{method=\"GET\",route=\"/tables/{schema}/{filter}\",le=\"+Inf\"} 2\n"));
+        assert!(out.contains("pgexplorer_cache_lookups_total{cache=\"tables\",result=\"miss\"} 1\n"));
+    }
+}
@@ -1,17 +1,33 @@
+pub mod api;
+pub mod audit;
+pub mod auth;
 pub mod blueprint;
+pub mod catalog;
+pub mod conninfo;
 pub mod console;
 pub mod dashboard;
 pub mod databases;
+pub mod db_session;
 pub mod endpoints;
 pub mod export;
 pub mod health;
 pub mod indices;
+pub mod jobs;
 pub mod maintenance;
+pub mod metrics;
 pub mod patroni;
+pub mod pool;
+pub mod replica;
+pub mod schedules;
 pub mod schemas;
+pub mod storage;
 pub mod table_detail;
 pub mod tables;
+pub mod targets;
+pub mod tls;
+pub mod tokens;
 pub mod tuning;
+pub mod tunnel;
 
 use std::sync::{Arc, RwLock as StdRwLock};
 use std::time::{Duration, Instant, SystemTime};
@@ -20,33 +36,57 @@ use tokio::sync::RwLock;
 use crate::templates::AppContext;
 use axum_extra::extract::CookieJar;
 use axum_extra::extract::cookie::Cookie;
-use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Option<crate::db::Database>,
     pub base_path: String,
-    pub stateless_endpoint: Option<crate::db::models::Endpoint>,
-    pub stateless_password: Option<String>,
-    pub active_override: Arc<StdRwLock<Option<crate::db::models::Endpoint>>>,
-    pub active_override_password: Arc<StdRwLock<Option<String>>>,
+    /// Endpointy ze --conf-* parametrů nebo --config souboru (jen ve --stateless režimu)
+    pub stateless_endpoints: Vec<crate::config::stateless::StatelessEndpoint>,
+    /// Přepnutí databáze per prohlížeč (cookie pg_db_session)
+    pub db_sessions: db_session::DbSessionStore,
     pub databases_menu: Arc<StdRwLock<HashMap<i64, bool>>>,
-    pub schemas_cache: Arc<RwLock<HashMap<i64, CacheEntry<crate::handlers::schemas::SchemaRowDb>>>>,
-    pub tables_cache: Arc<RwLock<HashMap<i64, CacheEntry<crate::handlers::tables::TableRowDb>>>>,
-    pub indices_cache: Arc<RwLock<HashMap<i64, CacheEntry<crate::handlers::indices::IndexRowDb>>>>,
+    pub schemas_cache: Arc<RwLock<HashMap<CacheKey, CacheEntry<crate::handlers::schemas::SchemaRowDb>>>>,
+    pub tables_cache: Arc<RwLock<HashMap<CacheKey, CacheEntry<crate::handlers::tables::TableRowDb>>>>,
+    pub indices_cache: Arc<RwLock<HashMap<CacheKey, CacheEntry<crate::handlers::indices::IndexRowDb>>>>,
     pub export_jobs: Arc<RwLock<HashMap<String, ExportJob>>>,
+    pub pg_pools: pool::PgPoolRegistry,
+    pub ssh_tunnels: tunnel::SshTunnelRegistry,
+    /// Stav replik (dostupnost, lag) pro směrování čtecích dotazů
+    pub replicas: replica::ReplicaRegistry,
     pub patroni_urls: Option<Vec<String>>,
     pub enable_destructive_commands: bool,
+    pub auth_mode: crate::auth::AuthMode,
+    pub proxy_auth: Option<crate::auth::ProxyAuth>,
+    pub default_role: crate::auth::Role,
+    pub endpoint_roles: Arc<StdRwLock<Vec<crate::db::models::EndpointRole>>>,
+    pub metrics: metrics::Metrics,
+    /// /metrics dotazuje i všechny endpointy (velikost, spojení, cache hit ratio)
+    pub metrics_database_stats: bool,
+    /// /readyz ověřuje i spojení na endpointy
+    pub readyz_endpoints: bool,
+    pub readyz_cache: health::ReadyCache,
+    /// Kořen úložiště dumpů a logů, retence a kvóta
+    pub storage: storage::Storage,
 }
 
 pub const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
 pub const JOB_CLEANUP_AGE: Duration = Duration::from_secs(60 * 60); // 1 hour
 
+/// Cache seznamů je per endpoint a databáze (URL se mění při přepnutí databáze)
+pub type CacheKey = (i64, String);
+
+pub fn cache_key(endpoint: &crate::db::models::Endpoint) -> CacheKey {
+    (endpoint.id, endpoint.url.clone())
+}
+
 pub struct CacheEntry<T> {
     pub data: Vec<T>,
     pub fetched_at: Instant,
     pub fetching: bool,
+    /// Uzel, ze kterého data pochází (jen u endpointů s replikami)
+    pub served_by: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +94,24 @@ pub enum JobStatus {
     Running,
     Completed,
     Failed,
+    /// Zrušeno uživatelem (POST .../cancel nebo zavřený log stream)
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Hodnota sloupce jobs.status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        !matches!(self, JobStatus::Running)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -65,33 +123,60 @@ pub struct ExportJob {
     pub completed_at: Option<SystemTime>,
     pub file_path: Option<String>,
     pub error: Option<String>,
+    /// SQL / příkazová řádka jobu (pro audit log)
+    pub command: Option<String>,
+    pub endpoint_id: Option<i64>,
+    /// Vstupní soubor jobu (import), retence ho během běhu nesmaže
+    pub input_path: Option<String>,
+    /// Zrušení běžícího jobu
+    pub cancel: tokio_util::sync::CancellationToken,
+}
+
+impl ExportJob {
+    pub fn new(job_id: &str) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            status: JobStatus::Running,
+            logs: VecDeque::new(),
+            started_at: SystemTime::now(),
+            completed_at: None,
+            file_path: None,
+            error: None,
+            command: None,
+            endpoint_id: None,
+            input_path: None,
+            cancel: tokio_util::sync::CancellationToken::new(),
+        }
+    }
 }
 
 pub fn build_ctx(state: &Arc<AppState>) -> AppContext {
-    let in_memory_active = state
-        .active_override
-        .read()
-        .ok()
-        .and_then(|g| g.clone())
-        .is_some();
+    let user = crate::auth::request_user();
     AppContext {
         base_path: state.base_path.clone(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         active_endpoint_name: "No connection".to_string(),
         show_databases: false,
-        in_memory_active,
+        in_memory_active: false,
         show_patroni: state.patroni_urls.is_some(),
         show_blueprint: false,
+        can_operate: false,
+        can_admin: false,
+        current_user: user.as_ref().map(|u| u.username.clone()).unwrap_or_default(),
+        is_admin: user.as_ref().map(|u| u.is_admin).unwrap_or(false),
+        auth_enabled: state.auth_mode != crate::auth::AuthMode::None,
+        local_auth: state.auth_mode == crate::auth::AuthMode::Local,
+        show_users: state.auth_mode != crate::auth::AuthMode::None && state.db.is_some(),
+        show_audit: state.db.is_some(),
+        environment: String::new(),
+        served_by: String::new(),
     }
 }
 
 pub fn build_ctx_with_endpoint(state: &Arc<AppState>, endpoint: Option<&crate::db::models::Endpoint>) -> AppContext {
-    let in_memory_active = state
-        .active_override
-        .read()
-        .ok()
-        .and_then(|g| g.clone())
-        .is_some();
+    let in_memory_active = endpoint
+        .map(|e| db_session::current_override(state, e.id).is_some())
+        .unwrap_or(false);
     let show_databases = endpoint
         .and_then(|e| {
             state
@@ -101,14 +186,28 @@ pub fn build_ctx_with_endpoint(state: &Arc<AppState>, endpoint: Option<&crate::d
                 .and_then(|map| map.get(&e.id).copied())
         })
         .unwrap_or(false);
+    let role = endpoint
+        .map(|e| endpoint_role(state, e.id))
+        .unwrap_or(crate::auth::Role::None);
+    let user = crate::auth::request_user();
     AppContext {
         base_path: state.base_path.clone(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         active_endpoint_name: endpoint.map(|e| e.name.clone()).unwrap_or_else(|| "No connection".to_string()),
         show_databases,
         in_memory_active,
-        show_patroni: state.patroni_urls.is_some(),
+        show_patroni: patroni_urls(state, endpoint).is_some(),
         show_blueprint: endpoint.map(|e| e.enable_blueprint).unwrap_or(false),
+        can_operate: role >= crate::auth::Role::Operator,
+        can_admin: role >= crate::auth::Role::Admin,
+        current_user: user.as_ref().map(|u| u.username.clone()).unwrap_or_default(),
+        is_admin: user.as_ref().map(|u| u.is_admin).unwrap_or(false),
+        auth_enabled: state.auth_mode != crate::auth::AuthMode::None,
+        local_auth: state.auth_mode == crate::auth::AuthMode::Local,
+        show_users: state.auth_mode != crate::auth::AuthMode::None && state.db.is_some(),
+        show_audit: state.db.is_some(),
+        environment: endpoint.map(|e| e.environment_name().to_string()).unwrap_or_default(),
+        served_by: replica::served_by().unwrap_or_default(),
     }
 }
 
@@ -124,18 +223,39 @@ pub async fn get_active_endpoint(
     state: &Arc<AppState>,
     jar: &CookieJar,
 ) -> Option<crate::db::models::Endpoint> {
-    if let Ok(guard) = state.active_override.read() {
-        if let Some(endpoint) = guard.clone() {
-            return Some(endpoint);
-        }
-    }
-    if let Some(endpoint) = &state.stateless_endpoint {
-        return Some(endpoint.clone());
+    // Endpoint bez přidělené role se chová jako nevybraný
+    find_active_endpoint(state, jar)
+        .await
+        .filter(|endpoint| endpoint_role(state, endpoint.id) >= crate::auth::Role::Viewer)
+}
+
+async fn find_active_endpoint(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+) -> Option<crate::db::models::Endpoint> {
+    let endpoint = find_selected_endpoint(state, jar).await?;
+    // Přepnutá databáze z /databases platí jen pro tuto session
+    match db_session::current_override(state, endpoint.id) {
+        Some(session) => Some(session.endpoint),
+        None => Some(endpoint),
     }
-    let db = state.db.as_ref()?;
+}
+
+async fn find_selected_endpoint(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+) -> Option<crate::db::models::Endpoint> {
     let id = jar
         .get("pg_active_endpoint")
         .and_then(|c| c.value().parse::<i64>().ok());
+    if !state.stateless_endpoints.is_empty() {
+        // Bez cookie (nebo s neznámým ID) je aktivní první endpoint z konfigurace
+        return id
+            .and_then(|id| state.stateless_entry(id))
+            .or_else(|| state.stateless_endpoints.first())
+            .map(|entry| entry.endpoint.clone());
+    }
+    let db = state.db.as_ref()?;
     if let Some(id) = id {
         if let Ok(Some(endpoint)) = db.get_endpoint(id).await {
             return Some(endpoint);
@@ -144,6 +264,73 @@ pub async fn get_active_endpoint(
     None
 }
 
+impl AppState {
+    pub fn stateless_entry(&self, endpoint_id: i64) -> Option<&crate::config::stateless::StatelessEndpoint> {
+        self.stateless_endpoints.iter().find(|e| e.endpoint.id == endpoint_id)
+    }
+}
+
+/// Všechny endpointy (SQLite nebo stateless konfigurace)
+pub async fn all_endpoints(state: &AppState) -> anyhow::Result<Vec<crate::db::models::Endpoint>> {
+    match &state.db {
+        Some(db) => db.get_endpoints().await,
+        None => Ok(state.stateless_endpoints.iter().map(|e| e.endpoint.clone()).collect()),
+    }
+}
+
+pub async fn find_endpoint(state: &AppState, id: i64) -> anyhow::Result<Option<crate::db::models::Endpoint>> {
+    match &state.db {
+        Some(db) => db.get_endpoint(id).await,
+        None => Ok(state.stateless_entry(id).map(|e| e.endpoint.clone())),
+    }
+}
+
+/// Uložené heslo endpointu (dešifrované z SQLite nebo ze stateless konfigurace)
+pub async fn endpoint_password(state: &AppState, endpoint: &crate::db::models::Endpoint) -> Option<String> {
+    match &state.db {
+        Some(db) => db.get_endpoint_password(endpoint).await,
+        None => state.stateless_entry(endpoint.id).and_then(|e| e.password.clone()),
+    }
+}
+
+/// Heslo pro připojení: zadané při přepnutí databáze v této session, jinak uložené heslo endpointu
+pub async fn connection_password(state: &AppState, endpoint: &crate::db::models::Endpoint) -> Option<String> {
+    match db_session::current_override(state, endpoint.id) {
+        Some(session) if session.endpoint.url == endpoint.url => session.password,
+        _ => endpoint_password(state, endpoint).await,
+    }
+}
+
+pub async fn endpoint_ssh_passphrase(state: &AppState, endpoint: &crate::db::models::Endpoint) -> Option<String> {
+    match &state.db {
+        Some(db) => db.get_endpoint_ssh_passphrase(endpoint).await,
+        None => state.stateless_entry(endpoint.id).and_then(|e| e.ssh_passphrase.clone()),
+    }
+}
+
+pub async fn endpoint_ssl_key(state: &AppState, endpoint: &crate::db::models::Endpoint) -> Option<String> {
+    match &state.db {
+        Some(db) => db.get_endpoint_ssl_key(endpoint).await,
+        None => state.stateless_entry(endpoint.id).and_then(|e| e.ssl_key.clone()),
+    }
+}
+
+/// Patroni URL pro endpoint: vlastní ze stateless konfigurace, jinak globální --patroni-urls
+pub fn patroni_urls(state: &AppState, endpoint: Option<&crate::db::models::Endpoint>) -> Option<Vec<String>> {
+    endpoint
+        .and_then(|e| state.stateless_entry(e.id))
+        .map(|e| e.patroni_urls.clone())
+        .filter(|urls| !urls.is_empty())
+        .or_else(|| state.patroni_urls.clone())
+}
+
+/// Role přihlášeného uživatele na endpointu (mimo request vrací None)
+pub fn endpoint_role(state: &Arc<AppState>, endpoint_id: i64) -> crate::auth::Role {
+    crate::auth::request_user()
+        .map(|user| crate::auth::resolve_role(state, &user, endpoint_id))
+        .unwrap_or(crate::auth::Role::None)
+}
+
 pub fn set_active_endpoint_cookie(id: i64) -> Cookie<'static> {
     Cookie::build(("pg_active_endpoint", id.to_string()))
         .path("/")
@@ -155,120 +342,129 @@ pub async fn connect_pg(
     state: &Arc<AppState>,
     endpoint: &crate::db::models::Endpoint,
 ) -> anyhow::Result<PgPool> {
-    let override_password = state
-        .active_override
-        .read()
-        .ok()
-        .and_then(|guard| guard.clone())
-        .and_then(|override_ep| {
-            if override_ep.id == endpoint.id && override_ep.url == endpoint.url {
-                state
-                    .active_override_password
-                    .read()
-                    .ok()
-                    .and_then(|p| p.clone())
-            } else {
-                None
-            }
-        });
-
-    let password = if override_password.is_some() {
-        override_password
-    } else if let Some(db) = &state.db {
-        db.get_endpoint_password(endpoint).await
-    } else {
-        state.stateless_password.clone()
+    connect_node(state, endpoint, None).await
+}
+
+/// Pool na primární server (`replica: None`) nebo na repliku s daným indexem
+pub async fn connect_node(
+    state: &Arc<AppState>,
+    endpoint: &crate::db::models::Endpoint,
+    replica: Option<usize>,
+) -> anyhow::Result<PgPool> {
+    // Override platí jen pro endpoint s přepnutou URL z aktuální session
+    let session = db_session::current_token().filter(|_| {
+        db_session::current_override(state, endpoint.id)
+            .is_some_and(|o| o.endpoint.url == endpoint.url)
+    });
+    let key = pool::PoolKey {
+        endpoint_id: endpoint.id,
+        db_session: session,
+        replica,
     };
-    let mut url = build_pg_url(&endpoint.url, endpoint.username.as_deref(), password.as_deref());
+
+    // Tunel se řeší vždy (restart tunelu zneplatní pooly endpointu)
+    let mut info = resolve_node_conninfo(state, endpoint, replica).await?;
+    if let Some(pool) = state.pg_pools.get(&key).await {
+        return Ok(pool);
+    }
+
+    // Heslo a TLS soubory připravujeme jen pro nový pool
+    let password = connection_password(state, endpoint).await;
+    if info.password.is_none() {
+        info.password = password;
+    }
 
     // Aplikuj SSL mode a další parametry
-    url = apply_connection_params(
-        url,
+    info.apply_endpoint_params(
         endpoint.ssl_mode.as_deref(),
         endpoint.search_path.as_deref(),
         endpoint.insecure,
     );
+    tls::endpoint_tls_files(state, endpoint).await?.apply_to(&mut info);
 
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .acquire_timeout(std::time::Duration::from_secs(10))
-        .connect(&url)
-        .await?;
-
-    // Nastav statement_timeout na 30 sekund
-    sqlx::query("SET statement_timeout = '30s'")
-        .execute(&pool)
-        .await
-        .ok();
-
-    Ok(pool)
+    let label = match replica {
+        Some(index) => format!("{} (replica {})", endpoint.name, index + 1),
+        None => endpoint.name.clone(),
+    };
+    // Pool se sdílí mezi requesty, statement_timeout nastavuje pool pro každé spojení
+    state.pg_pools.get_or_connect(key, &label, &info).await
 }
 
-pub fn build_pg_url(base: &str, username: Option<&str>, password: Option<&str>) -> String {
-    let mut url = base.to_string();
-    if !url.contains("://") {
-        url = format!("postgres://{}", url);
-    }
-    if url.contains('@') || username.is_none() {
-        return url;
-    }
-    let user = username.unwrap_or("");
-    let pass = password.unwrap_or("");
-    if let Some(pos) = url.find("://") {
-        let (scheme, rest) = url.split_at(pos + 3);
-        if pass.is_empty() {
-            format!("{}{}@{}", scheme, user, rest)
-        } else {
-            format!("{}{}:{}@{}", scheme, user, pass, rest)
-        }
-    } else {
-        url
-    }
+/// Rozparsovaný connection string endpointu (uživatel doplněn z endpointu, heslo ne).
+/// Při SSH tunelu jsou hosty přesměrované na lokální konce tunelu.
+pub async fn resolve_conninfo(
+    state: &AppState,
+    endpoint: &crate::db::models::Endpoint,
+) -> anyhow::Result<conninfo::ConnInfo> {
+    resolve_node_conninfo(state, endpoint, None).await
 }
 
-pub fn apply_connection_params(
-    mut url: String,
-    ssl_mode: Option<&str>,
-    search_path: Option<&str>,
-    insecure: bool,
-) -> String {
-    let separator = if url.contains('?') { '&' } else { '?' };
-    let mut params = Vec::new();
-
-    // Aplikuj SSL mode
-    let ssl_mode = ssl_mode.and_then(|m| {
-        let trimmed = m.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed)
-        }
-    });
-    if let Some(mode) = ssl_mode {
-        params.push(format!("sslmode={}", urlencoding::encode(mode)));
+/// Jako resolve_conninfo, ale pro primární server nebo jednu z replik endpointu
+pub async fn resolve_node_conninfo(
+    state: &AppState,
+    endpoint: &crate::db::models::Endpoint,
+    replica: Option<usize>,
+) -> anyhow::Result<conninfo::ConnInfo> {
+    let mut primary = conninfo::ConnInfo::parse(&endpoint.url)?;
+    if primary.user.is_none() {
+        primary.user = endpoint.username.clone().filter(|u| !u.is_empty());
     }
+    let replicas = endpoint
+        .replica_list()
+        .into_iter()
+        .map(|url| replica::replica_conninfo(&primary, url))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut info = match replica {
+        Some(index) => replicas
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Endpoint {} has no replica #{}", endpoint.name, index + 1))?,
+        None => primary.clone(),
+    };
 
-    // Pokud je insecure=true a není explicitní ssl mode, nastav require (bez verifikace)
-    // Pokud je insecure=true a je verify-ca/verify-full, ponech (user ví co dělá)
-    if insecure && ssl_mode.is_none() {
-        params.push("sslmode=require".to_string());
+    let Some(ssh_host) = endpoint.ssh_host.as_ref().filter(|h| !h.trim().is_empty()) else {
+        return Ok(info);
+    };
+    if std::iter::once(&primary)
+        .chain(&replicas)
+        .any(|node| node.hosts.iter().any(|h| h.is_socket()))
+    {
+        anyhow::bail!("SSH tunnel cannot be used with Unix socket hosts");
     }
 
-    // Aplikuj search_path jako OPTIONS (sqlx/tokio-postgres ignorují přímý search_path)
-    if let Some(path) = search_path {
-        let trimmed = path.trim();
-        if !trimmed.is_empty() {
-            params.push(format!(
-                "options={}",
-                urlencoding::encode(&format!("-c search_path={}", trimmed))
-            ));
-        }
-    }
+    let passphrase = endpoint_ssh_passphrase(state, endpoint).await;
+    let config = tunnel::SshTunnelConfig {
+        host: ssh_host.trim().to_string(),
+        port: endpoint.ssh_port.and_then(|p| u16::try_from(p).ok()).unwrap_or(22),
+        user: endpoint.ssh_user.clone().filter(|u| !u.trim().is_empty()),
+        key_path: endpoint.ssh_key_path.clone().filter(|k| !k.trim().is_empty()),
+        passphrase: passphrase.filter(|p| !p.is_empty()),
+    };
+    // Jeden tunel pro primární server i všechny repliky (forward pro každý host)
+    let targets: Vec<tunnel::TunnelTarget> = std::iter::once(&primary)
+        .chain(&replicas)
+        .flat_map(|node| node.hosts.iter())
+        .map(|h| tunnel::TunnelTarget {
+            host: h.host.clone(),
+            port: h.port,
+        })
+        .collect();
 
-    if !params.is_empty() {
-        url.push(separator);
-        url.push_str(&params.join("&"));
+    let (local_ports, restarted) = state
+        .ssh_tunnels
+        .local_ports(endpoint.id, &config, &targets)
+        .await?;
+    if restarted {
+        // Pooly mohly držet spojení přes starý tunel
+        state.pg_pools.invalidate_endpoint(endpoint.id).await;
     }
-
-    url
+    let offset = match replica {
+        Some(index) => primary.hosts.len() + replicas[..index].iter().map(|r| r.hosts.len()).sum::<usize>(),
+        None => 0,
+    };
+    for (host, port) in info.hosts.iter_mut().zip(local_ports.into_iter().skip(offset)) {
+        host.host = "127.0.0.1".to_string();
+        host.port = port;
+    }
+    Ok(info)
 }
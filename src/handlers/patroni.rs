@@ -7,7 +7,7 @@ use axum::http::StatusCode;
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
 
-use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::handlers::{build_ctx_with_endpoint, get_active_endpoint, patroni_urls, AppState};
 use crate::templates::PatroniTemplate;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -157,14 +157,13 @@ pub async fn patroni_view(
     State(state): State<Arc<AppState>>,
     jar: axum_extra::extract::CookieJar,
 ) -> Result<Html<String>, (StatusCode, String)> {
-    if state.patroni_urls.is_none() {
+    let active = get_active_endpoint(&state, &jar).await;
+    if patroni_urls(&state, active.as_ref()).is_none() {
         return Err((
             StatusCode::NOT_FOUND,
             "Patroni monitoring is not enabled. Use --enable-patroni flag.".to_string(),
         ));
     }
-
-    let active = get_active_endpoint(&state, &jar).await;
     let ctx = build_ctx_with_endpoint(&state, active.as_ref());
 
     let tmpl = PatroniTemplate {
@@ -179,22 +178,32 @@ pub async fn patroni_view(
 
 pub async fn patroni_status(
     State(state): State<Arc<AppState>>,
+    jar: CookieJar,
 ) -> Result<Json<Vec<PatroniNodeStatus>>, (StatusCode, String)> {
-    let urls = state.patroni_urls.as_ref().ok_or_else(|| {
+    let active = get_active_endpoint(&state, &jar).await;
+    let urls = patroni_urls(&state, active.as_ref()).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             "Patroni monitoring is not enabled".to_string(),
         )
     })?;
 
+    let statuses = fetch_cluster_status(&urls)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(statuses))
+}
+
+/// Stav clusteru z REST API Patroni (nedostupné uzly jsou v seznamu jako offline)
+pub async fn fetch_cluster_status(urls: &[String]) -> Result<Vec<PatroniNodeStatus>, reqwest::Error> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(5))
-        .build()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .build()?;
 
     let mut statuses = Vec::new();
 
-    for url in urls {
+    for url in urls.iter() {
         let cluster_url = format!("{}/cluster", url.trim_end_matches('/'));
 
         match client.get(&cluster_url).send().await {
@@ -371,5 +380,5 @@ pub async fn patroni_status(
         }
     }
 
-    Ok(Json(statuses))
+    Ok(statuses)
 }
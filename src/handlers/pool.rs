@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgPoolOptions};
+
+use crate::handlers::conninfo::ConnInfo;
+use sqlx::{Connection, Executor, PgPool};
+use tokio::sync::RwLock;
+
+/// Jak dlouho může pool ležet bez použití, než ho zavřeme
+pub const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Jak často kontrolujeme nepoužívané pooly
+pub const POOL_EVICT_INTERVAL: Duration = Duration::from_secs(60);
+pub const POOL_MAX_CONNECTIONS: u32 = 10;
+
+/// Pool je sdílený pro endpoint (a každou jeho repliku); přepnutá databáze má vlastní pool pro každou DB session
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub endpoint_id: i64,
+    /// Token DB session s přepnutou databází (viz db_session)
+    pub db_session: Option<String>,
+    /// Index repliky endpointu (None = primární server)
+    pub replica: Option<usize>,
+}
+
+struct PoolEntry {
+    pool: PgPool,
+    label: String,
+    created_at: Instant,
+    last_used: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolStats {
+    pub label: String,
+    pub endpoint_id: i64,
+    pub is_override: bool,
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+    pub age_secs: u64,
+    pub idle_secs: u64,
+}
+
+/// Registry of Postgres pools shared across requests
+#[derive(Clone, Default)]
+pub struct PgPoolRegistry {
+    pools: Arc<RwLock<HashMap<PoolKey, PoolEntry>>>,
+}
+
+impl PgPoolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Vrátí existující pool (a označí ho jako použitý)
+    pub async fn get(&self, key: &PoolKey) -> Option<PgPool> {
+        let mut guard = self.pools.write().await;
+        let entry = guard.get_mut(key)?;
+        entry.last_used = Instant::now();
+        Some(entry.pool.clone())
+    }
+
+    /// Vrátí existující pool, nebo založí nový a ověří spojení
+    pub async fn get_or_connect(
+        &self,
+        key: PoolKey,
+        label: &str,
+        info: &ConnInfo,
+    ) -> anyhow::Result<PgPool> {
+        if let Some(pool) = self.get(&key).await {
+            return Ok(pool);
+        }
+
+        let pool_options = PgPoolOptions::new()
+            .max_connections(POOL_MAX_CONNECTIONS)
+            .acquire_timeout(Duration::from_secs(10))
+            .idle_timeout(Duration::from_secs(5 * 60))
+            .after_connect(|conn, _meta| {
+                Box::pin(async move {
+                    // Nastav statement_timeout na 30 sekund pro každé nové spojení
+                    conn.execute("SET statement_timeout = '30s'").await?;
+                    Ok(())
+                })
+            });
+        let pool = open_pool(info, pool_options).await?;
+
+        let mut guard = self.pools.write().await;
+        // Souběžný request mohl mezitím založit stejný pool - použij ten jeho
+        if let Some(entry) = guard.get_mut(&key) {
+            entry.last_used = Instant::now();
+            let existing = entry.pool.clone();
+            drop(guard);
+            close_all(vec![pool]);
+            return Ok(existing);
+        }
+        let now = Instant::now();
+        guard.insert(
+            key,
+            PoolEntry {
+                pool: pool.clone(),
+                label: label.to_string(),
+                created_at: now,
+                last_used: now,
+            },
+        );
+        Ok(pool)
+    }
+
+    /// Zavře všechny pooly endpointu (po úpravě nebo smazání endpointu)
+    pub async fn invalidate_endpoint(&self, endpoint_id: i64) {
+        let removed: Vec<PgPool> = {
+            let mut guard = self.pools.write().await;
+            let keys: Vec<PoolKey> = guard
+                .keys()
+                .filter(|k| k.endpoint_id == endpoint_id)
+                .cloned()
+                .collect();
+            keys.iter()
+                .filter_map(|k| guard.remove(k))
+                .map(|e| e.pool)
+                .collect()
+        };
+        close_all(removed);
+    }
+
+    /// Zavře pooly přepnuté databáze jedné DB session, ostatní nechá běžet
+    pub async fn invalidate_session(&self, token: &str) {
+        let removed: Vec<PgPool> = {
+            let mut guard = self.pools.write().await;
+            let keys: Vec<PoolKey> = guard
+                .keys()
+                .filter(|k| k.db_session.as_deref() == Some(token))
+                .cloned()
+                .collect();
+            keys.iter()
+                .filter_map(|k| guard.remove(k))
+                .map(|e| e.pool)
+                .collect()
+        };
+        close_all(removed);
+    }
+
+    /// Zavře pooly, které nebyly použity déle než `max_idle`
+    pub async fn evict_idle(&self, max_idle: Duration) -> usize {
+        let removed: Vec<PgPool> = {
+            let mut guard = self.pools.write().await;
+            let keys: Vec<PoolKey> = guard
+                .iter()
+                .filter(|(_, e)| e.last_used.elapsed() > max_idle)
+                .map(|(k, _)| k.clone())
+                .collect();
+            keys.iter()
+                .filter_map(|k| guard.remove(k))
+                .map(|e| e.pool)
+                .collect()
+        };
+        let count = removed.len();
+        close_all(removed);
+        count
+    }
+
+    pub async fn stats(&self) -> Vec<PoolStats> {
+        let guard = self.pools.read().await;
+        let mut stats: Vec<PoolStats> = guard
+            .iter()
+            .map(|(key, entry)| PoolStats {
+                label: entry.label.clone(),
+                endpoint_id: key.endpoint_id,
+                is_override: key.db_session.is_some(),
+                size: entry.pool.size(),
+                idle: entry.pool.num_idle(),
+                max: POOL_MAX_CONNECTIONS,
+                age_secs: entry.created_at.elapsed().as_secs(),
+                idle_secs: entry.last_used.elapsed().as_secs(),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.label.cmp(&b.label));
+        stats
+    }
+
+    /// Periodicky zavírá nepoužívané pooly
+    pub fn spawn_eviction_task(&self) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POOL_EVICT_INTERVAL);
+            loop {
+                interval.tick().await;
+                let evicted = registry.evict_idle(POOL_IDLE_TIMEOUT).await;
+                if evicted > 0 {
+                    tracing::info!("Closed {} idle Postgres pool(s)", evicted);
+                }
+            }
+        });
+    }
+}
+
+/// Zavírání čeká na vrácení běžících spojení, proto běží na pozadí
+fn close_all(pools: Vec<PgPool>) {
+    for pool in pools {
+        tokio::spawn(async move { pool.close().await });
+    }
+}
+
+/// Otevře pool na první host, který vyhovuje target_session_attrs (jako libpq u multi-host URL)
+pub async fn open_pool(info: &ConnInfo, pool_options: PgPoolOptions) -> anyhow::Result<PgPool> {
+    let attrs = info.target_session_attrs().to_string();
+    // prefer-standby: nejdřív zkus standby, pak cokoliv
+    let passes: &[&str] = if attrs == "prefer-standby" { &["standby", "any"] } else { &[attrs.as_str()] };
+
+    let mut errors: Vec<(String, String)> = Vec::new();
+    for wanted in passes {
+        for index in 0..info.hosts.len().max(1) {
+            let single = info.with_single_host(index);
+            let host = single
+                .hosts
+                .first()
+                .map(|h| format!("{}:{}", h.host, h.port))
+                .unwrap_or_default();
+            let options: PgConnectOptions = single.to_sqlx_url().parse()?;
+            // Jedno přímé spojení selže hned (pool by čekal do acquire_timeout)
+            let mut conn = match PgConnection::connect_with(&options).await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    errors.push((host, e.to_string()));
+                    continue;
+                }
+            };
+            let matches = session_matches(&mut conn, wanted).await;
+            let _ = conn.close().await;
+            match matches {
+                Ok(true) => return Ok(pool_options.clone().connect_with(options).await?),
+                Ok(false) => errors.push((host, format!("server does not match target_session_attrs={}", wanted))),
+                Err(e) => errors.push((host, e.to_string())),
+            }
+        }
+    }
+
+    if let [(_, error)] = errors.as_slice() {
+        anyhow::bail!("{}", error);
+    }
+    let errors: Vec<String> = errors.iter().map(|(host, e)| format!("{}: {}", host, e)).collect();
+    anyhow::bail!("No suitable host found: {}", errors.join("; "))
+}
+
+async fn session_matches(conn: &mut PgConnection, wanted: &str) -> Result<bool, sqlx::Error> {
+    Ok(match wanted {
+        "read-write" | "read-only" => {
+            let read_only: String = sqlx::query_scalar("SHOW transaction_read_only")
+                .fetch_one(&mut *conn)
+                .await?;
+            (read_only == "on") == (wanted == "read-only")
+        }
+        "primary" | "standby" => {
+            let in_recovery: bool = sqlx::query_scalar("SELECT pg_is_in_recovery()")
+                .fetch_one(&mut *conn)
+                .await?;
+            in_recovery == (wanted == "standby")
+        }
+        _ => true,
+    })
+}
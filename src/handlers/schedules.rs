@@ -0,0 +1,533 @@
+//! Plánované opakované exporty: cron v UTC, spouští je scheduler v procesu přes run_export_job
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth::{require_role, resolve_role, CurrentUser, Role};
+use crate::db::models::{ExportSchedule, NewExportSchedule};
+use crate::handlers::export::{self, ExportRequest};
+use crate::handlers::targets::{self, TargetOption};
+use crate::handlers::{all_endpoints, audit, base_path_url, build_ctx_with_endpoint, get_active_endpoint, jobs, storage, AppState};
+use crate::templates::SchedulesTemplate;
+
+/// Jak často scheduler hledá plány, které mají běžet
+const TICK: Duration = Duration::from_secs(30);
+const MAX_KEEP_LAST: i64 = 365;
+const SCOPES: [&str; 4] = ["full", "schema", "data", "tables"];
+const FORMATS: [&str; 4] = ["custom", "plain", "directory", "tar"];
+
+/// Cron s 5 poli (minuta až den v týdnu) nebo 6-7 poli se sekundami a rokem
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule, String> {
+    let expr = expr.split_whitespace().collect::<Vec<_>>().join(" ");
+    let full = match expr.split(' ').count() {
+        5 => format!("0 {}", expr),
+        6 | 7 => expr.clone(),
+        _ => return Err("Cron expression needs 5 fields (minute hour day month weekday)".to_string()),
+    };
+    cron::Schedule::from_str(&full).map_err(|_| format!("Invalid cron expression '{}'", expr))
+}
+
+/// Časy v SQLite jsou datetime('now'), tj. UTC bez zóny
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|t| t.and_utc())
+}
+
+/// Další běh po posledním spuštění (nebo po vytvoření plánu)
+pub fn next_run(schedule: &ExportSchedule) -> Option<DateTime<Utc>> {
+    let after = parse_time(schedule.last_run_at.as_deref().unwrap_or(&schedule.created_at))?;
+    parse_cron(&schedule.cron).ok()?.after(&after).next()
+}
+
+pub fn spawn_scheduler(state: Arc<AppState>) {
+    if state.db.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            run_due(&state).await;
+        }
+    });
+}
+
+/// Spustí plány, jejichž čas nastal; zmeškané běhy (server neběžel) se doženou jedním během
+async fn run_due(state: &Arc<AppState>) {
+    let Some(db) = state.db.as_ref() else {
+        return;
+    };
+    let schedules = match db.get_export_schedules().await {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            tracing::error!("Scheduler: {:#}", e);
+            return;
+        }
+    };
+    let now = Utc::now();
+    for schedule in schedules {
+        if !schedule.enabled || next_run(&schedule).is_none_or(|next| next > now) {
+            continue;
+        }
+        if is_running(state, &schedule).await {
+            tracing::warn!("Scheduler: previous run of '{}' is still running, skipping", schedule.name);
+            if let Err(e) = db.start_schedule_run(schedule.id, schedule.last_job_id.as_deref()).await {
+                tracing::error!("Scheduler: {:#}", e);
+            }
+            continue;
+        }
+        // Chyba startu je zalogovaná a v last_error plánu
+        run_schedule(state, schedule).await.ok();
+    }
+}
+
+async fn is_running(state: &AppState, schedule: &ExportSchedule) -> bool {
+    let Some(job_id) = &schedule.last_job_id else {
+        return false;
+    };
+    state.export_jobs.read().await.get(job_id).is_some_and(|job| !job.status.is_done())
+}
+
+/// Spustí export plánu na pozadí; vrací job_id, nebo chybu, která skončí i v last_error plánu
+pub async fn run_schedule(state: &Arc<AppState>, schedule: ExportSchedule) -> Result<String, String> {
+    let db = state.db.as_ref().ok_or("Schedules are not available in stateless mode")?;
+    let user = CurrentUser {
+        username: format!("schedule:{}", schedule.name),
+        ..CurrentUser::anonymous()
+    };
+
+    let prepared = async {
+        let endpoint = db
+            .get_endpoint(schedule.endpoint_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("The connection of this schedule no longer exists")?;
+        let req: ExportRequest =
+            serde_json::from_str(&schedule.options).map_err(|e| format!("Invalid schedule options: {}", e))?;
+        state.storage.check_quota().await.map_err(|(_, msg)| msg)?;
+        export::check_target(state, &req).await.map_err(|(_, msg)| msg)?;
+        Ok::<_, String>((endpoint, req))
+    }
+    .await;
+    let (endpoint, req) = match prepared {
+        Ok(prepared) => prepared,
+        Err(error) => {
+            tracing::warn!("Scheduled export '{}' failed to start: {}", schedule.name, error);
+            if let Err(e) = db.start_schedule_run(schedule.id, None).await {
+                tracing::error!("Scheduler: {:#}", e);
+            }
+            if let Err(e) = db.finish_schedule_run(schedule.id, "failed", Some(&error)).await {
+                tracing::error!("Scheduler: {:#}", e);
+            }
+            return Err(error);
+        }
+    };
+
+    let job_id = jobs::register(state, "export", &endpoint, &user, &req, None).await;
+    if let Err(e) = db.start_schedule_run(schedule.id, Some(&job_id)).await {
+        tracing::error!("Scheduler: {:#}", e);
+    }
+    tracing::info!("Scheduled export '{}' started as {}", schedule.name, job_id);
+
+    let state = state.clone();
+    let result = job_id.clone();
+    tokio::spawn(async move {
+        export::run_export_job(state.clone(), job_id.clone(), endpoint.clone(), req).await;
+        audit::record_job(&state, &user, &endpoint, "export", &job_id).await;
+
+        let job = state.export_jobs.read().await.get(&job_id).cloned();
+        let Some(job) = job else {
+            return;
+        };
+        let Some(db) = state.db.as_ref() else {
+            return;
+        };
+        if let Err(e) = db.finish_schedule_run(schedule.id, job.status.as_str(), job.error.as_deref()).await {
+            tracing::error!("Scheduler: {:#}", e);
+        }
+        if job.error.is_none() {
+            prune(&state, &schedule).await;
+        }
+    });
+    Ok(result)
+}
+
+/// Ponechá posledních keep_last dumpů plánu, starší smaže lokálně i ze storage targetu
+/// (logy a záznamy jobů zůstávají)
+async fn prune(state: &AppState, schedule: &ExportSchedule) {
+    let Some(db) = state.db.as_ref() else {
+        return;
+    };
+    let artifacts = match db.get_schedule_artifacts(schedule.id).await {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            tracing::error!("Scheduler: {:#}", e);
+            return;
+        }
+    };
+    for job in artifacts.into_iter().skip(schedule.keep_last.max(1) as usize) {
+        if let (Some(target_id), Some(key)) = (job.target_id, job.remote_key.as_deref()) {
+            let removed = async {
+                let (_, client) = targets::client(state, target_id).await?;
+                client.delete(key).await
+            }
+            .await;
+            match removed {
+                Ok(()) => {
+                    tracing::info!("Schedule '{}': removed old object {}", schedule.name, key);
+                    if let Err(e) = db.clear_job_remote(&job.id).await {
+                        tracing::error!("Scheduler: {:#}", e);
+                    }
+                }
+                Err(e) => tracing::warn!("Schedule '{}': failed to remove object {}: {:#}", schedule.name, key, e),
+            }
+        }
+        let Some(path) = job.artifact_path else {
+            continue;
+        };
+        match storage::remove(std::path::Path::new(&path)).await {
+            Ok(()) => tracing::info!("Schedule '{}': removed old dump {}", schedule.name, path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!("Schedule '{}': failed to remove {}: {}", schedule.name, path, e);
+                continue;
+            }
+        }
+        if let Err(e) = db.clear_job_artifact(&job.id).await {
+            tracing::error!("Scheduler: {:#}", e);
+        }
+    }
+}
+
+/// Poslední běhy plánů, které selhaly (pro dashboard); jen plány, na jejichž endpoint má uživatel operátora
+pub async fn failed_schedules(state: &AppState, user: &CurrentUser) -> Vec<ExportSchedule> {
+    let Some(db) = state.db.as_ref() else {
+        return Vec::new();
+    };
+    match db.get_export_schedules().await {
+        Ok(schedules) => schedules
+            .into_iter()
+            .filter(|s| s.enabled && matches!(s.last_status.as_deref(), Some("failed" | "interrupted")))
+            .filter(|s| resolve_role(state, user, s.endpoint_id) >= Role::Operator)
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Failed to load export schedules: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Řádek na stránce /schedules
+pub struct ScheduleRow {
+    pub schedule: ExportSchedule,
+    pub endpoint: String,
+    /// Stručný popis ExportRequest (rozsah, formát, tabulky)
+    pub summary: String,
+    pub next_run: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateScheduleForm {
+    pub name: String,
+    pub cron: String,
+    pub keep_last: i64,
+    pub scope: String,
+    pub format: String,
+    /// schema.table oddělené čárkou nebo řádky (scope=tables)
+    pub tables: Option<String>,
+    pub exclude_patterns: Option<String>,
+    pub compress: Option<String>,
+    pub include_ownership: Option<String>,
+    pub include_drop: Option<String>,
+    pub include_create_db: Option<String>,
+    /// Prázdné = dump zůstane jen lokálně
+    pub target_id: Option<String>,
+}
+
+impl CreateScheduleForm {
+    fn export_request(&self) -> Result<ExportRequest, String> {
+        if !SCOPES.contains(&self.scope.as_str()) || !FORMATS.contains(&self.format.as_str()) {
+            return Err("Invalid scope or format".to_string());
+        }
+        let tables: Vec<String> = self
+            .tables
+            .as_deref()
+            .unwrap_or("")
+            .split([',', '\n'])
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if self.scope == "tables" && tables.is_empty() {
+            return Err("Select at least one table (schema.table)".to_string());
+        }
+        let exclude = self.exclude_patterns.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        let target_id = match self.target_id.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(id) => Some(id.parse::<i64>().map_err(|_| "Invalid storage target".to_string())?),
+            None => None,
+        };
+        Ok(ExportRequest {
+            scope: self.scope.clone(),
+            format: self.format.clone(),
+            compress: self.compress.is_some(),
+            include_ownership: self.include_ownership.is_some(),
+            include_drop: self.include_drop.is_some(),
+            include_create_db: self.include_create_db.is_some(),
+            verbose: false,
+            exclude_patterns: exclude.map(str::to_string),
+            pg_version: None,
+            selected_tables: (self.scope == "tables").then_some(tables),
+            target_id,
+        })
+    }
+}
+
+fn summary(options: &str, targets: &[TargetOption]) -> String {
+    match serde_json::from_str::<ExportRequest>(options) {
+        Ok(req) => {
+            let mut parts = vec![format!("{} / {}", req.scope, req.format)];
+            if let Some(tables) = req.selected_tables.filter(|t| !t.is_empty()) {
+                parts.push(tables.join(", "));
+            }
+            if let Some(exclude) = req.exclude_patterns {
+                parts.push(format!("no data: {}", exclude));
+            }
+            if let Some(target_id) = req.target_id {
+                match targets.iter().find(|t| t.id == target_id) {
+                    Some(target) => parts.push(format!("upload to {}", target.name)),
+                    None => parts.push(format!("upload to deleted target #{}", target_id)),
+                }
+            }
+            parts.join("; ")
+        }
+        Err(_) => "invalid options".to_string(),
+    }
+}
+
+fn schedules_db(state: &AppState) -> Result<&crate::db::Database, (StatusCode, String)> {
+    state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Schedules are not available in stateless mode".to_string()))
+}
+
+async fn render_schedules(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+    user: &CurrentUser,
+    error: Option<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = schedules_db(state)?;
+    let targets = targets::target_options(state).await;
+    let endpoints: HashMap<i64, String> = all_endpoints(state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .map(|e| (e.id, e.name))
+        .collect();
+    let schedules = db
+        .get_export_schedules()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .filter(|s| resolve_role(state, user, s.endpoint_id) >= Role::Operator)
+        .map(|schedule| ScheduleRow {
+            endpoint: endpoints.get(&schedule.endpoint_id).cloned().unwrap_or_else(|| format!("#{}", schedule.endpoint_id)),
+            summary: summary(&schedule.options, &targets),
+            next_run: match (schedule.enabled, next_run(&schedule)) {
+                (false, _) => "disabled".to_string(),
+                (true, Some(next)) => next.format("%Y-%m-%d %H:%M:%S").to_string(),
+                (true, None) => "-".to_string(),
+            },
+            schedule,
+        })
+        .collect();
+
+    let active = get_active_endpoint(state, jar).await;
+    let can_create = active
+        .as_ref()
+        .is_some_and(|endpoint| resolve_role(state, user, endpoint.id) >= Role::Operator);
+    let status = if error.is_some() { StatusCode::BAD_REQUEST } else { StatusCode::OK };
+    let tpl = SchedulesTemplate {
+        ctx: build_ctx_with_endpoint(state, active.as_ref()),
+        title: "Schedules | Postgres Explorer".to_string(),
+        schedules,
+        active_endpoint: active.map(|endpoint| endpoint.name),
+        can_create,
+        targets,
+        error,
+    };
+    let html = tpl
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((status, Html(html)).into_response())
+}
+
+pub async fn schedules_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Response, (StatusCode, String)> {
+    render_schedules(&state, &jar, &user, None).await
+}
+
+/// Nový plán pro aktivní endpoint (jeho výchozí databázi)
+pub async fn create_schedule(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<CreateScheduleForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = schedules_db(&state)?;
+    let active = get_active_endpoint(&state, &jar)
+        .await
+        .ok_or((StatusCode::BAD_REQUEST, "No active connection".to_string()))?;
+    require_role(&state, &user, active.id, Role::Operator, "schedule exports")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    let name = form.name.trim();
+    let cron = form.cron.split_whitespace().collect::<Vec<_>>().join(" ");
+    let validated = if name.is_empty() || name.len() > 100 {
+        Err("Schedule name is required (max. 100 characters)".to_string())
+    } else if !(1..=MAX_KEEP_LAST).contains(&form.keep_last) {
+        Err(format!("Keep last must be between 1 and {}", MAX_KEEP_LAST))
+    } else {
+        parse_cron(&cron).and_then(|_| form.export_request())
+    };
+    let req = match validated {
+        Ok(req) => req,
+        Err(error) => return render_schedules(&state, &jar, &user, Some(error)).await,
+    };
+    if let Err((_, error)) = export::check_target(&state, &req).await {
+        return render_schedules(&state, &jar, &user, Some(error)).await;
+    }
+
+    let record = NewExportSchedule {
+        name: name.to_string(),
+        endpoint_id: active.id,
+        cron: cron.clone(),
+        options: serde_json::to_string(&req).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        keep_last: form.keep_last,
+        created_by: user.audit_name(),
+    };
+    db.create_export_schedule(&record)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let command = format!(
+        "create schedule '{}' ({}, keep last {}): {}",
+        record.name,
+        record.cron,
+        record.keep_last,
+        summary(&record.options, &targets::target_options(&state).await)
+    );
+    audit::record(&state, &user, &active, "schedule", &command, None).await;
+
+    Ok(Redirect::to(&base_path_url(&state, "/schedules")).into_response())
+}
+
+/// Plán podle ID s kontrolou operátora na jeho endpointu
+async fn schedule_for(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+) -> Result<(ExportSchedule, crate::db::models::Endpoint), (StatusCode, String)> {
+    let db = schedules_db(state)?;
+    let schedule = db
+        .get_export_schedule(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Schedule not found".to_string()))?;
+    require_role(state, user, schedule.endpoint_id, Role::Operator, "manage schedules")
+        .map_err(|e| (StatusCode::FORBIDDEN, e))?;
+    let endpoint = db
+        .get_endpoint(schedule.endpoint_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Connection not found".to_string()))?;
+    Ok((schedule, endpoint))
+}
+
+/// POST /schedules/{id}/run - spustí plán hned (mimo cron)
+pub async fn run_now(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    let (schedule, _) = schedule_for(&state, &user, id).await?;
+    if is_running(&state, &schedule).await {
+        return Err((StatusCode::CONFLICT, "The previous run is still running".to_string()));
+    }
+    state.storage.check_quota().await?;
+    run_schedule(&state, schedule).await.map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Redirect::to(&base_path_url(&state, "/schedules")).into_response())
+}
+
+pub async fn toggle_schedule(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    let (schedule, endpoint) = schedule_for(&state, &user, id).await?;
+    let db = schedules_db(&state)?;
+    db.set_export_schedule_enabled(id, !schedule.enabled)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let command = format!("{} schedule '{}'", if schedule.enabled { "disable" } else { "enable" }, schedule.name);
+    audit::record(&state, &user, &endpoint, "schedule", &command, None).await;
+    Ok(Redirect::to(&base_path_url(&state, "/schedules")).into_response())
+}
+
+/// Smaže plán; jeho dumpy zůstanou v úložišti (retence /storage)
+pub async fn delete_schedule(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    let (schedule, endpoint) = schedule_for(&state, &user, id).await?;
+    let db = schedules_db(&state)?;
+    db.delete_export_schedule(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    audit::record(&state, &user, &endpoint, "schedule", &format!("delete schedule '{}'", schedule.name), None).await;
+    Ok(Redirect::to(&base_path_url(&state, "/schedules")).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_and_next_run() {
+        assert!(parse_cron("0 2 * * *").is_ok());
+        assert!(parse_cron("30 0 2 * * Mon-Fri").is_ok());
+        assert!(parse_cron("0 2 * *").is_err());
+        assert!(parse_cron("0 25 * * *").is_err());
+
+        let mut schedule = ExportSchedule {
+            id: 1,
+            name: "nightly".to_string(),
+            endpoint_id: 1,
+            cron: "0 2 * * *".to_string(),
+            options: "{}".to_string(),
+            keep_last: 7,
+            enabled: true,
+            created_by: "admin".to_string(),
+            last_run_at: None,
+            last_job_id: None,
+            last_status: None,
+            last_error: None,
+            created_at: "2026-01-10 12:00:00".to_string(),
+        };
+        assert_eq!(next_run(&schedule).unwrap().to_string(), "2026-01-11 02:00:00 UTC");
+        schedule.last_run_at = Some("2026-01-11 02:00:05".to_string());
+        assert_eq!(next_run(&schedule).unwrap().to_string(), "2026-01-12 02:00:00 UTC");
+    }
+}
@@ -5,7 +5,9 @@ use serde::Deserialize;
 use std::sync::Arc;
 use axum_extra::extract::CookieJar;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, cache_key, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::db_session::in_current_session;
+use crate::handlers::replica::{connect_pg_read, note_served_by, with_served_by};
 use crate::templates::{SchemaRow, SchemasTemplate, SchemasTableTemplate};
 use crate::utils::filter::{matches_simple_terms, parse_simple_terms};
 use std::time::Instant;
@@ -44,19 +46,19 @@ fn default_sort_order() -> String {
     "asc".to_string()
 }
 
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Clone, serde::Serialize)]
 pub struct SchemaRowDb {
-    name: String,
-    table_count: i64,
-    index_count: i64,
-    total_size: i64,
+    pub name: String,
+    pub table_count: i64,
+    pub index_count: i64,
+    pub total_size: i64,
 }
 
-async fn fetch_schemas_from_db(
+pub async fn fetch_schemas_from_db(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> Result<Vec<SchemaRowDb>, String> {
-    match connect_pg(state, active).await {
+    match connect_pg_read(state, active).await {
         Ok(pg) => match sqlx::query_as::<_, SchemaRowDb>(
             r#"
             SELECT n.nspname as name,
@@ -84,7 +86,8 @@ async fn fetch_schemas_from_db(
     }
 }
 
-async fn get_cached_schemas(
+/// Schémata z cache, zastaralá se obnoví na pozadí
+pub async fn get_cached_schemas(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> (Vec<SchemaRowDb>, bool) {
@@ -92,32 +95,38 @@ async fn get_cached_schemas(
     let mut should_refresh = false;
     let (data, fetching) = {
         let mut cache = state.schemas_cache.write().await;
-        match cache.get_mut(&active.id) {
+        match cache.get_mut(&cache_key(active)) {
             Some(entry) => {
                 let stale = now.duration_since(entry.fetched_at) > CACHE_TTL;
                 if stale && !entry.fetching {
                     entry.fetching = true;
                     should_refresh = true;
                 }
+                state.metrics.cache_lookup("schemas", true);
                 tracing::debug!(
                     "schemas cache hit id={} stale={} fetching={}",
                     active.id,
                     stale,
                     entry.fetching
                 );
+                if let Some(label) = &entry.served_by {
+                    note_served_by(label.clone());
+                }
                 (entry.data.clone(), entry.fetching)
             }
             None => {
                 cache.insert(
-                    active.id,
+                    cache_key(active),
                     CacheEntry {
                         data: Vec::new(),
                         fetched_at: now,
                         fetching: true,
+                        served_by: None,
                     },
                 );
                 should_refresh = true;
                 tracing::debug!("schemas cache miss id={}, scheduling refresh", active.id);
+                state.metrics.cache_lookup("schemas", false);
                 (Vec::new(), true)
             }
         }
@@ -126,17 +135,19 @@ async fn get_cached_schemas(
     if should_refresh {
         let state = state.clone();
         let active = active.clone();
-        tokio::spawn(async move {
-            let result = fetch_schemas_from_db(&state, &active).await;
+        // Obnova poběží se stejnou přepnutou databází jako request
+        tokio::spawn(in_current_session(async move {
+            let (result, served_by) = with_served_by(fetch_schemas_from_db(&state, &active)).await;
             let mut cache = state.schemas_cache.write().await;
-            if let Some(entry) = cache.get_mut(&active.id) {
+            if let Some(entry) = cache.get_mut(&cache_key(&active)) {
                 if let Ok(rows) = result {
                     entry.data = rows;
+                    entry.served_by = served_by;
                     entry.fetched_at = Instant::now();
                 }
                 entry.fetching = false;
             }
-        });
+        }));
     }
 
     (data, fetching)
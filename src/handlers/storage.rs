@@ -0,0 +1,447 @@
+//! Úložiště dumpů, logů jobů a nahraných importů: kořen s podadresáři per endpoint, retence a kvóta
+
+use askama::Template;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::auth::CurrentUser;
+use crate::db::models::JobFilter;
+use crate::handlers::{all_endpoints, audit, base_path_url, build_ctx_with_endpoint, get_active_endpoint, AppState, JOB_CLEANUP_AGE};
+use crate::templates::StorageTemplate;
+use crate::utils::format::bytes_to_human;
+
+pub const DEFAULT_STORAGE_DIR: &str = "/tmp/postgres-explorer";
+/// Podadresáře kořene; v každém je adresář per endpoint (ID)
+pub const AREAS: [&str; 2] = ["exports", "imports"];
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone)]
+pub struct Storage {
+    pub root: PathBuf,
+    /// Artefakty starší než tohle se mažou
+    pub max_age: Option<Duration>,
+    /// Limit na endpoint, nad ním se mažou nejstarší artefakty
+    pub max_endpoint_size: Option<u64>,
+    /// Nad touto celkovou velikostí se nové exporty a uploady odmítnou
+    pub quota: Option<u64>,
+}
+
+/// Soubor nebo adresář (dump ve formátu directory) v úložišti
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub path: PathBuf,
+    /// Cesta relativně ke kořeni (identifikace pro smazání)
+    pub relative: String,
+    pub area: &'static str,
+    pub endpoint_id: Option<i64>,
+    pub name: String,
+    pub size: u64,
+    pub modified: SystemTime,
+    /// Job, ze kterého artefakt vznikl (podle názvu export_<uuid>.dump, console_<uuid>.log...)
+    pub job_id: Option<String>,
+}
+
+/// Běžící joby a jejich vstupní soubory; nahrané importy mají jméno od klienta, ne ID jobu
+#[derive(Debug, Default)]
+pub struct RunningJobs {
+    pub ids: HashSet<String>,
+    /// Vstupní soubory relativně ke kořeni úložiště (jako Artifact::relative)
+    pub inputs: HashSet<String>,
+}
+
+impl RunningJobs {
+    pub fn holds(&self, relative: &str, job_id: Option<&str>) -> bool {
+        job_id.is_some_and(|id| self.ids.contains(id)) || self.inputs.contains(relative)
+    }
+}
+
+impl Storage {
+    pub fn export_dir(&self, endpoint_id: i64) -> PathBuf {
+        self.root.join("exports").join(endpoint_id.to_string())
+    }
+
+    pub fn import_dir(&self, endpoint_id: i64) -> PathBuf {
+        self.root.join("imports").join(endpoint_id.to_string())
+    }
+
+    pub fn log_path(&self, endpoint_id: i64, job_id: &str) -> PathBuf {
+        self.export_dir(endpoint_id).join(format!("{}.log", job_id))
+    }
+
+    /// Všechny artefakty, nejnovější první (blokující průchod adresářů)
+    pub fn list(&self) -> Vec<Artifact> {
+        let mut artifacts = Vec::new();
+        for area in AREAS {
+            let Ok(endpoint_dirs) = std::fs::read_dir(self.root.join(area)) else {
+                continue;
+            };
+            for endpoint_dir in endpoint_dirs.flatten() {
+                let endpoint_name = endpoint_dir.file_name().to_string_lossy().into_owned();
+                let Ok(entries) = std::fs::read_dir(endpoint_dir.path()) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let Ok(meta) = entry.metadata() else {
+                        continue;
+                    };
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    artifacts.push(Artifact {
+                        path: entry.path(),
+                        relative: format!("{}/{}/{}", area, endpoint_name, name),
+                        area,
+                        endpoint_id: endpoint_name.parse().ok(),
+                        size: if meta.is_dir() { dir_size(&entry.path()) } else { meta.len() },
+                        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                        job_id: job_id_of(&name),
+                        name,
+                    });
+                }
+            }
+        }
+        artifacts.sort_by_key(|a| std::cmp::Reverse(a.modified));
+        artifacts
+    }
+
+    pub fn used(&self) -> u64 {
+        self.list().iter().map(|a| a.size).sum()
+    }
+
+    /// Kvóta se kontroluje před startem exportu a uploadu
+    pub async fn check_quota(&self) -> Result<(), (StatusCode, String)> {
+        let Some(quota) = self.quota else {
+            return Ok(());
+        };
+        let storage = self.clone();
+        let used = tokio::task::spawn_blocking(move || storage.used()).await.unwrap_or(0);
+        if used >= quota {
+            return Err((
+                StatusCode::INSUFFICIENT_STORAGE,
+                format!(
+                    "Storage quota exceeded ({} of {} used), delete old artifacts first",
+                    format_size(used),
+                    format_size(quota)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Artefakty k smazání podle stáří a limitu na endpoint (zvlášť exporty a importy);
+    /// soubory běžících jobů zůstávají
+    pub fn expired(&self, artifacts: &[Artifact], running: &RunningJobs, now: SystemTime) -> Vec<PathBuf> {
+        let mut expired = Vec::new();
+        let mut endpoint_usage: HashMap<(&str, Option<i64>), u64> = HashMap::new();
+        // artifacts jsou seřazené od nejnovějších, limit se naplní těmi novějšími
+        for artifact in artifacts {
+            if running.holds(&artifact.relative, artifact.job_id.as_deref()) {
+                continue;
+            }
+            let too_old = self
+                .max_age
+                .is_some_and(|max_age| now.duration_since(artifact.modified).unwrap_or_default() > max_age);
+            let usage = endpoint_usage.entry((artifact.area, artifact.endpoint_id)).or_default();
+            *usage += artifact.size;
+            let over_limit = self.max_endpoint_size.is_some_and(|limit| *usage > limit);
+            if too_old || over_limit {
+                *usage -= artifact.size;
+                expired.push(artifact.path.clone());
+            }
+        }
+        expired
+    }
+
+    /// Relativní cesta ze stránky /storage -> cesta uvnitř kořene (bez `..` a absolutních cest)
+    pub fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let relative = Path::new(relative);
+        let depth = relative.components().count();
+        let plain = relative.components().all(|c| matches!(c, Component::Normal(_)));
+        let area = relative.components().next()?.as_os_str().to_str()?;
+        (plain && depth == 3 && AREAS.contains(&area)).then(|| self.root.join(relative))
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn job_id_of(name: &str) -> Option<String> {
+    let stem = name.split('.').next()?;
+    crate::handlers::jobs::KINDS
+        .iter()
+        .any(|kind| stem.starts_with(&format!("{}_", kind)))
+        .then(|| stem.to_string())
+}
+
+pub async fn remove(path: &Path) -> std::io::Result<()> {
+    if tokio::fs::metadata(path).await?.is_dir() {
+        tokio::fs::remove_dir_all(path).await
+    } else {
+        tokio::fs::remove_file(path).await
+    }
+}
+
+/// "500M", "10G", "1.5T" nebo počet bajtů
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("Invalid size: {}", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("Invalid size unit in {} (use K, M, G or T)", value)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn format_size(bytes: u64) -> String {
+    bytes_to_human(bytes as i64)
+}
+
+/// Joby, které ještě běží (jejich soubory se nesmí mazat)
+async fn running_jobs(state: &AppState) -> RunningJobs {
+    let root = tokio::fs::canonicalize(&state.storage.root)
+        .await
+        .unwrap_or_else(|_| state.storage.root.clone());
+    let mut running = RunningJobs::default();
+    for job in state.export_jobs.read().await.values().filter(|job| !job.status.is_done()) {
+        running.ids.insert(job.job_id.clone());
+        // Vstup importu je kanonická cesta (import_file_path)
+        if let Some(relative) = job.input_path.as_deref().and_then(|path| Path::new(path).strip_prefix(&root).ok()) {
+            running.inputs.insert(relative.to_string_lossy().into_owned());
+        }
+    }
+    running
+}
+
+/// Periodicky maže artefakty podle retence a z paměti dokončené joby starší než JOB_CLEANUP_AGE
+pub fn spawn_cleanup_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            cleanup(&state).await;
+        }
+    });
+}
+
+async fn cleanup(state: &AppState) {
+    let now = SystemTime::now();
+    state.export_jobs.write().await.retain(|_, job| {
+        job.completed_at
+            .is_none_or(|completed| now.duration_since(completed).unwrap_or_default() < JOB_CLEANUP_AGE)
+    });
+
+    if state.storage.max_age.is_none() && state.storage.max_endpoint_size.is_none() {
+        return;
+    }
+    let running = running_jobs(state).await;
+    let storage = state.storage.clone();
+    let expired = tokio::task::spawn_blocking(move || {
+        let artifacts = storage.list();
+        storage.expired(&artifacts, &running, now)
+    })
+    .await
+    .unwrap_or_default();
+    for path in expired {
+        match remove(&path).await {
+            Ok(()) => tracing::info!("Retention: removed {}", path.display()),
+            Err(e) => tracing::warn!("Retention: failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn require_admin(user: &CurrentUser) -> Result<(), (StatusCode, String)> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Administrator role required".to_string()))
+    }
+}
+
+/// Řádek na stránce /storage
+pub struct ArtifactRow {
+    pub relative: String,
+    pub area: String,
+    pub endpoint: String,
+    pub name: String,
+    pub size: String,
+    pub modified: String,
+    pub job_id: Option<String>,
+    /// Druh a stav jobu z tabulky jobs
+    pub job_status: Option<String>,
+    pub running: bool,
+}
+
+pub async fn storage_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Html<String>, (StatusCode, String)> {
+    require_admin(&user)?;
+
+    let storage = state.storage.clone();
+    let artifacts = tokio::task::spawn_blocking(move || storage.list())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let endpoints: HashMap<i64, String> = all_endpoints(&state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .map(|e| (e.id, e.name))
+        .collect();
+    let jobs: HashMap<String, String> = match state.db.as_ref() {
+        Some(db) => db
+            .get_jobs(&JobFilter::default())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .into_iter()
+            .map(|job| (job.id, format!("{} {}", job.kind, job.status)))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let running = running_jobs(&state).await;
+
+    let used: u64 = artifacts.iter().map(|a| a.size).sum();
+    let rows = artifacts
+        .into_iter()
+        .map(|a| ArtifactRow {
+            endpoint: a
+                .endpoint_id
+                .and_then(|id| endpoints.get(&id).cloned())
+                .unwrap_or_else(|| format!("#{} (deleted)", a.endpoint_id.map(|id| id.to_string()).unwrap_or_default())),
+            job_status: a.job_id.as_ref().and_then(|id| jobs.get(id).cloned()),
+            running: running.holds(&a.relative, a.job_id.as_deref()),
+            modified: chrono::DateTime::<chrono::Utc>::from(a.modified).format("%Y-%m-%d %H:%M:%S").to_string(),
+            size: format_size(a.size),
+            area: a.area.to_string(),
+            relative: a.relative,
+            name: a.name,
+            job_id: a.job_id,
+        })
+        .collect();
+
+    let active = get_active_endpoint(&state, &jar).await;
+    let tpl = StorageTemplate {
+        ctx: build_ctx_with_endpoint(&state, active.as_ref()),
+        title: "Storage | Postgres Explorer".to_string(),
+        root: state.storage.root.display().to_string(),
+        used: format_size(used),
+        quota: state.storage.quota.map(format_size),
+        quota_exceeded: state.storage.quota.is_some_and(|quota| used >= quota),
+        max_age_days: state.storage.max_age.map(|age| age.as_secs() / 86400),
+        max_endpoint_size: state.storage.max_endpoint_size.map(format_size),
+        artifacts: rows,
+    };
+    tpl.render()
+        .map(Html)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteArtifactForm {
+    pub path: String,
+}
+
+pub async fn delete_artifact(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Form(form): Form<DeleteArtifactForm>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin(&user)?;
+    let path = state
+        .storage
+        .resolve(&form.path)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid artifact path".to_string()))?;
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let running = running_jobs(&state).await;
+    if running.holds(&form.path, job_id_of(&name).as_deref()) {
+        return Err((StatusCode::CONFLICT, "The job of this artifact is still running".to_string()));
+    }
+
+    let result = remove(&path).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    audit::record_global(&state, &user, "storage", &format!("DELETE {}", form.path), error.as_deref()).await;
+    result.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete {}: {}", form.path, e)))?;
+
+    Ok(Redirect::to(&base_path_url(&state, "/storage")).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(name: &str, endpoint_id: i64, size: u64, age_days: u64, now: SystemTime) -> Artifact {
+        let area = if name.starts_with("export_") { "exports" } else { "imports" };
+        Artifact {
+            path: PathBuf::from(name),
+            relative: format!("{}/{}/{}", area, endpoint_id, name),
+            area,
+            endpoint_id: Some(endpoint_id),
+            name: name.to_string(),
+            size,
+            modified: now - Duration::from_secs(age_days * 86400),
+            job_id: job_id_of(name),
+        }
+    }
+
+    #[test]
+    fn test_parse_size_and_retention() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size("1.5G").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2 GiB").unwrap(), 2 << 30);
+        assert!(parse_size("10X").is_err());
+
+        let now = SystemTime::now();
+        let storage = Storage {
+            max_age: Some(Duration::from_secs(7 * 86400)),
+            max_endpoint_size: Some(100),
+            quota: None,
+            root: PathBuf::from("/tmp/x"),
+        };
+        // Nejnovější první: na endpointu 1 se do exportů vejdou a, b; c je nad limitem, d je staré.
+        // Importy mají vlastní limit; upload.sql používá běžící import.
+        let artifacts = vec![
+            artifact("export_a.dump", 1, 60, 0, now),
+            artifact("shop.sql", 1, 90, 0, now),
+            artifact("export_b.dump", 1, 40, 1, now),
+            artifact("export_c.dump", 1, 10, 2, now),
+            artifact("export_r.dump", 2, 500, 3, now),
+            artifact("upload.sql", 2, 500, 20, now),
+            artifact("export_d.dump", 2, 1, 30, now),
+        ];
+        let running = RunningJobs {
+            ids: HashSet::from(["export_r".to_string()]),
+            inputs: HashSet::from(["imports/2/upload.sql".to_string()]),
+        };
+        let expired = storage.expired(&artifacts, &running, now);
+        assert_eq!(expired, vec![PathBuf::from("export_c.dump"), PathBuf::from("export_d.dump")]);
+
+        assert!(storage.resolve("exports/1/export_a.dump").is_some());
+        assert!(storage.resolve("exports/../../etc/passwd").is_none());
+        assert!(storage.resolve("/etc/passwd").is_none());
+        assert!(storage.resolve("other/1/x").is_none());
+    }
+}
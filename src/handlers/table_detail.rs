@@ -7,8 +7,11 @@ use axum_extra::extract::CookieJar;
 use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
 use regex::Regex;
+use serde::Serialize;
+use sqlx::PgPool;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::handlers::replica::connect_pg_read;
 use crate::templates::{ColumnMeta, FkMeta, TableDataTemplate, TableDetailTemplate};
 use crate::utils::format::bytes_to_human;
 
@@ -44,7 +47,7 @@ pub async fn table_detail(
     let mut last_analyze = "-".to_string();
     let mut comment = "".to_string();
 
-    match connect_pg(&state, &active).await {
+    match connect_pg_read(&state, &active).await {
         Ok(pg) => {
             if schema == "*" {
                 let schemas = sqlx::query_scalar::<_, String>(
@@ -186,11 +189,110 @@ fn default_per_page() -> usize {
     50
 }
 
-fn is_safe_ident(value: &str) -> bool {
+/// Jen jednoduché identifikátory (skládají se přímo do SQL)
+pub fn is_safe_ident(value: &str) -> bool {
     let re = Regex::new(r"^[A-Za-z0-9_]+$").unwrap();
     re.is_match(value)
 }
 
+/// Sloupec v náhledu dat (FK slouží pro náhled odkazovaného řádku)
+#[derive(Debug, Clone, Serialize)]
+pub struct DataColumn {
+    pub name: String,
+    pub data_type: String,
+    pub foreign_key: Option<FkMeta>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableData {
+    pub columns: Vec<DataColumn>,
+    /// Řádky jako JSON objekty (to_jsonb)
+    pub rows: Vec<JsonValue>,
+}
+
+/// Stránka dat tabulky; schema a name musí projít is_safe_ident
+pub async fn fetch_table_data(
+    pg: &PgPool,
+    schema: &str,
+    name: &str,
+    limit: usize,
+    offset: usize,
+) -> Result<TableData, sqlx::Error> {
+    let cols = sqlx::query(
+        r#"
+        SELECT column_name, data_type
+        FROM information_schema.columns
+        WHERE table_schema = $1 AND table_name = $2
+        ORDER BY ordinal_position
+        "#,
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
+    let mut columns: Vec<DataColumn> = cols
+        .iter()
+        .map(|r| DataColumn {
+            name: r.get("column_name"),
+            data_type: r.get("data_type"),
+            foreign_key: None,
+        })
+        .collect();
+    if columns.is_empty() {
+        return Ok(TableData { columns, rows: Vec::new() });
+    }
+
+    if let Ok(fk_rows) = sqlx::query(
+        r#"
+        SELECT
+            kcu.column_name as column_name,
+            ccu.table_schema as foreign_schema,
+            ccu.table_name as foreign_table,
+            ccu.column_name as foreign_column
+        FROM information_schema.table_constraints tc
+        JOIN information_schema.key_column_usage kcu
+          ON tc.constraint_name = kcu.constraint_name
+         AND tc.constraint_schema = kcu.constraint_schema
+        JOIN information_schema.constraint_column_usage ccu
+          ON ccu.constraint_name = tc.constraint_name
+         AND ccu.constraint_schema = tc.constraint_schema
+        WHERE tc.constraint_type = 'FOREIGN KEY'
+          AND tc.table_schema = $1
+          AND tc.table_name = $2
+        "#,
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await
+    {
+        for row in fk_rows {
+            let col: String = row.get("column_name");
+            if let Some(column) = columns.iter_mut().find(|c| c.name == col) {
+                column.foreign_key = Some(FkMeta {
+                    schema: row.get("foreign_schema"),
+                    table: row.get("foreign_table"),
+                    column: row.get("foreign_column"),
+                });
+            }
+        }
+    }
+
+    let sql = format!(
+        "SELECT to_jsonb(t) as row FROM \"{}\".\"{}\" t LIMIT $1 OFFSET $2",
+        schema, name
+    );
+    let rows = sqlx::query(&sql)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(pg)
+        .await?
+        .iter()
+        .map(|row| row.get::<JsonValue, _>("row"))
+        .collect();
+    Ok(TableData { columns, rows })
+}
+
 pub async fn table_data(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
@@ -211,119 +313,38 @@ pub async fn table_data(
     let per_page = query.per_page.clamp(10, 500);
     let offset = (page - 1) * per_page;
 
-    let mut columns: Vec<String> = Vec::new();
-    let mut rows: Vec<Vec<String>> = Vec::new();
-    let mut fk_map: std::collections::HashMap<String, (String, String, String)> = std::collections::HashMap::new();
-    let mut col_meta: Vec<ColumnMeta> = Vec::new();
-    let mut json_cols: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    match connect_pg(&state, &active).await {
-        Ok(pg) => {
-            let cols = sqlx::query(
-                r#"
-                SELECT column_name, data_type
-                FROM information_schema.columns
-                WHERE table_schema = $1 AND table_name = $2
-                ORDER BY ordinal_position
-                "#,
-            )
-            .bind(&schema)
-            .bind(&name)
-            .fetch_all(&pg)
+    let data = match connect_pg_read(&state, &active).await {
+        Ok(pg) => fetch_table_data(&pg, &schema, &name, per_page, offset)
             .await
-            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-            for r in cols {
-                let col_name: String = r.get("column_name");
-                let data_type: String = r.get("data_type");
-                if data_type == "json" || data_type == "jsonb" {
-                    json_cols.insert(col_name.clone());
-                }
-                columns.push(col_name);
-            }
-
-            if !columns.is_empty() {
-                if let Ok(fk_rows) = sqlx::query(
-                    r#"
-                    SELECT
-                        kcu.column_name as column_name,
-                        ccu.table_schema as foreign_schema,
-                        ccu.table_name as foreign_table,
-                        ccu.column_name as foreign_column
-                    FROM information_schema.table_constraints tc
-                    JOIN information_schema.key_column_usage kcu
-                      ON tc.constraint_name = kcu.constraint_name
-                     AND tc.constraint_schema = kcu.constraint_schema
-                    JOIN information_schema.constraint_column_usage ccu
-                      ON ccu.constraint_name = tc.constraint_name
-                     AND ccu.constraint_schema = tc.constraint_schema
-                    WHERE tc.constraint_type = 'FOREIGN KEY'
-                      AND tc.table_schema = $1
-                      AND tc.table_name = $2
-                    "#,
-                )
-                .bind(&schema)
-                .bind(&name)
-                .fetch_all(&pg)
-                .await
-                {
-                    for row in fk_rows {
-                        let col: String = row.get("column_name");
-                        let fs: String = row.get("foreign_schema");
-                        let ft: String = row.get("foreign_table");
-                        let fc: String = row.get("foreign_column");
-                        fk_map.insert(col, (fs, ft, fc));
-                    }
-                }
-            }
-
-            if !columns.is_empty() {
-                let sql = format!(
-                    "SELECT to_jsonb(t) as row FROM \"{}\".\"{}\" t LIMIT $1 OFFSET $2",
-                    schema, name
-                );
-                let data_rows = sqlx::query(&sql)
-                    .bind(per_page as i64)
-                    .bind(offset as i64)
-                    .fetch_all(&pg)
-                    .await
-                    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-                for row in data_rows {
-                    let json: JsonValue = row.get("row");
-                    let mut out = Vec::with_capacity(columns.len());
-                    for col in &columns {
-                        match json.get(col) {
-                            Some(JsonValue::Null) | None => out.push("".to_string()),
-                            Some(JsonValue::String(s)) => out.push(s.clone()),
-                            Some(v) => out.push(v.to_string()),
-                        }
-                    }
-                    rows.push(out);
-                }
-            }
-        }
+            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
         Err(e) => {
             return Ok(Html(format!("<div class='alert alert-danger'>Failed to connect: {}</div>", e)).into_response());
         }
-    }
+    };
 
-    if !columns.is_empty() {
-        col_meta = columns
-            .iter()
-            .map(|col| {
-                let fk = fk_map.get(col).map(|(fs, ft, fc)| FkMeta {
-                    schema: fs.clone(),
-                    table: ft.clone(),
-                    column: fc.clone(),
-                });
-                ColumnMeta {
-                    is_json: json_cols.contains(col),
-                    fk,
-                }
-            })
-            .collect();
-    }
+    let columns: Vec<String> = data.columns.iter().map(|c| c.name.clone()).collect();
+    let rows: Vec<Vec<String>> = data
+        .rows
+        .iter()
+        .map(|json| {
+            columns
+                .iter()
+                .map(|col| match json.get(col) {
+                    Some(JsonValue::Null) | None => "".to_string(),
+                    Some(JsonValue::String(s)) => s.clone(),
+                    Some(v) => v.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    let col_meta: Vec<ColumnMeta> = data
+        .columns
+        .into_iter()
+        .map(|c| ColumnMeta {
+            is_json: c.data_type == "json" || c.data_type == "jsonb",
+            fk: c.foreign_key,
+        })
+        .collect();
 
     let has_prev = page > 1;
     let has_next = rows.len() == per_page;
@@ -370,7 +391,7 @@ pub async fn table_data_preview(
         return Ok(Html("<div class='text-danger'>Invalid identifier.</div>".to_string()).into_response());
     }
 
-    match connect_pg(&state, &active).await {
+    match connect_pg_read(&state, &active).await {
         Ok(pg) => {
             let sql = format!(
                 "SELECT to_jsonb(t) as row FROM \"{}\".\"{}\" t WHERE \"{}\"::text = $1 LIMIT 1",
@@ -411,43 +432,35 @@ pub async fn table_data_preview(
     }
 }
 
-// Lazy load columns
-pub async fn table_columns(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Path((schema, name)): Path<(String, String)>,
-) -> Html<String> {
-    let active = match get_active_endpoint(&state, &jar).await {
-        Some(a) => a,
-        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
-    };
-
-    let pg = match connect_pg(&state, &active).await {
-        Ok(p) => p,
-        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct TableColumn {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub description: Option<String>,
+    pub is_primary_key: bool,
+    pub fk_table: Option<String>,
+    pub fk_column: Option<String>,
+    pub is_unique: bool,
+    pub is_indexed: bool,
+}
 
+/// Sloupce tabulky včetně klíčů a indexů; None, když tabulka neexistuje
+pub async fn fetch_table_columns(pg: &PgPool, schema: &str, name: &str) -> Result<Option<Vec<TableColumn>>, sqlx::Error> {
     // Nejdřív získej OID tabulky
-    let table_oid = match sqlx::query_scalar::<_, i64>(
+    let Some(table_oid) = sqlx::query_scalar::<_, i64>(
         "SELECT c.oid::bigint FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = $1 AND c.relname = $2"
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_optional(&pg)
-    .await
-    {
-        Ok(Some(oid)) => oid,
-        Ok(None) => {
-            tracing::warn!("Table {}.{} not found for columns", schema, name);
-            return Html("<div class='text-center text-muted py-5'>Table not found</div>".to_string());
-        }
-        Err(e) => {
-            tracing::error!("Failed to get table OID for {}.{}: {}", schema, name, e);
-            return Html(format!("<div class='alert alert-danger'>Failed to get table info: {}</div>", e));
-        }
+    .bind(schema)
+    .bind(name)
+    .fetch_optional(pg)
+    .await?
+    else {
+        return Ok(None);
     };
 
-    let cols = sqlx::query(
+    let rows = sqlx::query(
         r#"
         SELECT DISTINCT ON (a.attnum)
             a.attname as column_name,
@@ -497,59 +510,86 @@ pub async fn table_columns(
         "#,
     )
     .bind(table_oid)
-    .fetch_all(&pg)
-    .await;
+    .fetch_all(pg)
+    .await?;
+
+    let non_empty = |value: String| if value.is_empty() { None } else { Some(value) };
+    Ok(Some(
+        rows.iter()
+            .map(|row| TableColumn {
+                name: row.get("column_name"),
+                data_type: row.get("data_type"),
+                nullable: row.get::<String, _>("is_nullable") == "YES",
+                default: row.get::<Option<String>, _>("column_default").filter(|d| !d.is_empty()),
+                description: row.get::<Option<String>, _>("description").filter(|d| !d.is_empty()),
+                is_primary_key: row.get("is_primary_key"),
+                fk_table: non_empty(row.get("fk_table")),
+                fk_column: non_empty(row.get("fk_column")),
+                is_unique: row.get("is_unique"),
+                is_indexed: row.get("is_indexed"),
+            })
+            .collect(),
+    ))
+}
 
-    match cols {
-        Ok(rows) if rows.is_empty() => Html("<div class='text-center text-muted py-5'>No columns found</div>".to_string()),
-        Ok(rows) => {
-            tracing::debug!("Found {} columns for {}.{}", rows.len(), schema, name);
+// Lazy load columns
+pub async fn table_columns(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((schema, name)): Path<(String, String)>,
+) -> Html<String> {
+    let active = match get_active_endpoint(&state, &jar).await {
+        Some(a) => a,
+        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
+    };
+
+    let pg = match connect_pg_read(&state, &active).await {
+        Ok(p) => p,
+        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
+    };
+
+    match fetch_table_columns(&pg, &schema, &name).await {
+        Ok(None) => {
+            tracing::warn!("Table {}.{} not found for columns", schema, name);
+            Html("<div class='text-center text-muted py-5'>Table not found</div>".to_string())
+        }
+        Ok(Some(columns)) if columns.is_empty() => Html("<div class='text-center text-muted py-5'>No columns found</div>".to_string()),
+        Ok(Some(columns)) => {
+            tracing::debug!("Found {} columns for {}.{}", columns.len(), schema, name);
             let mut html = String::from("<div class='table-responsive'><table class='table table-vcenter'><thead><tr><th>Name</th><th>Type</th><th>Nullable</th><th>Default</th><th>Description</th><th>Constraints</th></tr></thead><tbody>");
             
-            for row in rows {
-                let col_name: String = row.get("column_name");
-                let data_type: String = row.get("data_type");
-                let nullable: String = row.get("is_nullable");
-                let default_val: String = row.try_get("column_default").unwrap_or_default();
-                let description: String = row.try_get("description").unwrap_or_default();
-                let is_pk: bool = row.get("is_primary_key");
-                let fk_table: String = row.get("fk_table");
-                let fk_column: String = row.get("fk_column");
-                let is_unique: bool = row.get("is_unique");
-                let is_indexed: bool = row.get("is_indexed");
+            for col in columns {
+                html.push_str(&format!("<tr><td><strong>{}</strong></td>", col.name));
+                html.push_str(&format!("<td><code>{}</code></td>", col.data_type));
                 
-                html.push_str(&format!("<tr><td><strong>{}</strong></td>", col_name));
-                html.push_str(&format!("<td><code>{}</code></td>", data_type));
-                
-                if nullable == "YES" {
+                if col.nullable {
                     html.push_str("<td><span class='badge bg-yellow text-yellow-fg'>NULL</span></td>");
                 } else {
                     html.push_str("<td><span class='badge bg-green text-green-fg'>NOT NULL</span></td>");
                 }
                 
-                if default_val.is_empty() {
-                    html.push_str("<td><span class='text-muted'>-</span></td>");
-                } else {
-                    html.push_str(&format!("<td><code class='text-muted small'>{}</code></td>", default_val));
+                match &col.default {
+                    Some(default_val) => html.push_str(&format!("<td><code class='text-muted small'>{}</code></td>", default_val)),
+                    None => html.push_str("<td><span class='text-muted'>-</span></td>"),
                 }
                 
-                if description.is_empty() {
-                    html.push_str("<td><span class='text-muted'>-</span></td>");
-                } else {
-                    html.push_str(&format!("<td>{}</td>", description));
+                match &col.description {
+                    Some(description) => html.push_str(&format!("<td>{}</td>", description)),
+                    None => html.push_str("<td><span class='text-muted'>-</span></td>"),
                 }
                 
                 html.push_str("<td>");
-                if is_pk {
+                if col.is_primary_key {
                     html.push_str("<span class='badge bg-blue text-blue-fg me-1'><i class='ti ti-key'></i> PK</span>");
                 }
-                if !fk_table.is_empty() {
+                if let Some(fk_table) = &col.fk_table {
+                    let fk_column = col.fk_column.as_deref().unwrap_or_default();
                     html.push_str(&format!("<span class='badge bg-purple text-purple-fg me-1' title='FK to {}.{}'><i class='ti ti-arrow-right'></i> FK → {}</span>", fk_table, fk_column, fk_table));
                 }
-                if is_unique {
+                if col.is_unique {
                     html.push_str("<span class='badge bg-cyan text-cyan-fg me-1'><i class='ti ti-check'></i> UNIQUE</span>");
                 }
-                if is_indexed {
+                if col.is_indexed {
                     html.push_str("<span class='badge bg-gray-lt text-gray-fg me-1'><i class='ti ti-sort-ascending'></i> IDX</span>");
                 }
                 html.push_str("</td></tr>");
@@ -565,33 +605,31 @@ pub async fn table_columns(
     }
 }
 
-// Lazy load indexes
-pub async fn table_indexes(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Path((schema, name)): Path<(String, String)>,
-) -> Html<String> {
-    let active = match get_active_endpoint(&state, &jar).await {
-        Some(a) => a,
-        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
-    };
-
-    let pg = match connect_pg(&state, &active).await {
-        Ok(p) => p,
-        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct TableIndex {
+    pub name: String,
+    pub columns: String,
+    pub index_type: String,
+    pub size_bytes: i64,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    pub definition: String,
+    pub scans: i64,
+    pub is_bloated: bool,
+}
 
+pub async fn fetch_table_indexes(pg: &PgPool, schema: &str, name: &str) -> Result<Vec<TableIndex>, sqlx::Error> {
     // Nejdřív získej počet řádků v tabulce pro bloat detection
     let table_rows = sqlx::query_scalar::<_, i64>(
         "SELECT COALESCE(NULLIF(s.n_live_tup, 0), NULLIF(c.reltuples, 0), 0)::bigint FROM pg_class c LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid JOIN pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = $1 AND c.relname = $2"
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_one(&pg)
+    .bind(schema)
+    .bind(name)
+    .fetch_one(pg)
     .await
     .unwrap_or(0);
 
-    let idxs = sqlx::query(
+    let rows = sqlx::query(
         r#"
         SELECT
             i.relname as index_name,
@@ -614,58 +652,83 @@ pub async fn table_indexes(
         ORDER BY ix.indisprimary DESC, ix.indisunique DESC, i.relname
         "#,
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_all(&pg)
-    .await;
-
-    match idxs {
-        Ok(rows) if rows.is_empty() => Html("<div class='text-center text-muted py-5'>No indexes found</div>".to_string()),
-        Ok(rows) => {
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let size_bytes: i64 = row.get("size_bytes");
+            // Bloat detection: pokud má index > 10 MB a více než 100 KB na řádek, je nafouklý
+            let is_bloated = if table_rows > 0 {
+                size_bytes > 10 * 1024 * 1024 && (size_bytes / table_rows.max(1)) > 100 * 1024
+            } else {
+                size_bytes > 100 * 1024 * 1024 // Pokud není žádný řádek a index je > 100 MB
+            };
+            TableIndex {
+                name: row.get("index_name"),
+                columns: row.get("columns"),
+                index_type: row.get("index_type"),
+                size_bytes,
+                is_unique: row.get("is_unique"),
+                is_primary: row.get("is_primary"),
+                definition: row.get("definition"),
+                scans: row.get("scans"),
+                is_bloated,
+            }
+        })
+        .collect())
+}
+
+// Lazy load indexes
+pub async fn table_indexes(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((schema, name)): Path<(String, String)>,
+) -> Html<String> {
+    let active = match get_active_endpoint(&state, &jar).await {
+        Some(a) => a,
+        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
+    };
+
+    let pg = match connect_pg_read(&state, &active).await {
+        Ok(p) => p,
+        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
+    };
+
+    match fetch_table_indexes(&pg, &schema, &name).await {
+        Ok(indexes) if indexes.is_empty() => Html("<div class='text-center text-muted py-5'>No indexes found</div>".to_string()),
+        Ok(indexes) => {
             let mut html = String::from("<div class='alert alert-info mb-3'><i class='ti ti-info-circle me-2'></i>Use REINDEX to rebuild bloated indexes. This operation locks the table.</div>");
             html.push_str("<div class='mb-3'><button class='btn btn-sm btn-warning' onclick='reindexAllIndexes()'><i class='ti ti-refresh me-1'></i>REINDEX All Indexes</button></div>");
             html.push_str("<div class='table-responsive'><table class='table table-vcenter'><thead><tr><th>Name</th><th>Columns</th><th>Type</th><th class='text-end'>Size</th><th class='text-end'>Scans</th><th>Attributes</th><th>Actions</th></tr></thead><tbody>");
             
-            for row in rows {
-                let idx_name: String = row.get("index_name");
-                let columns: String = row.get("columns");
-                let idx_type: String = row.get("index_type");
-                let size_bytes: i64 = row.get("size_bytes");
-                let is_unique: bool = row.get("is_unique");
-                let is_primary: bool = row.get("is_primary");
-                let definition: String = row.get("definition");
-                let scans: i64 = row.get("scans");
-
-                // Bloat detection: pokud má index > 10 MB a více než 100 KB na řádek, je nafouklý
-                let is_bloated = if table_rows > 0 {
-                    size_bytes > 10 * 1024 * 1024 && (size_bytes / table_rows.max(1)) > 100 * 1024
-                } else {
-                    size_bytes > 100 * 1024 * 1024 // Pokud není žádný řádek a index je > 100 MB
-                };
-
-                html.push_str(&format!("<tr><td><strong>{}</strong></td>", idx_name));
-                html.push_str(&format!("<td><code class='text-muted'>{}</code></td>", columns));
-                html.push_str(&format!("<td><span class='badge bg-gray-lt text-gray-fg'>{}</span></td>", idx_type));
+            for idx in indexes {
+                html.push_str(&format!("<tr><td><strong>{}</strong></td>", idx.name));
+                html.push_str(&format!("<td><code class='text-muted'>{}</code></td>", idx.columns));
+                html.push_str(&format!("<td><span class='badge bg-gray-lt text-gray-fg'>{}</span></td>", idx.index_type));
                 html.push_str("<td class='text-end'>");
-                if is_bloated {
+                if idx.is_bloated {
                     html.push_str("<span class='badge bg-orange-lt text-orange-fg me-2' title='Index is bloated. Consider REINDEX.'><i class='ti ti-alert-triangle'></i> bloated</span>");
                 }
-                html.push_str(&format!("{}</td>", bytes_to_human(size_bytes)));
-                html.push_str(&format!("<td class='text-end'>{}</td>", scans));
+                html.push_str(&format!("{}</td>", bytes_to_human(idx.size_bytes)));
+                html.push_str(&format!("<td class='text-end'>{}</td>", idx.scans));
                 
                 html.push_str("<td>");
-                if is_primary {
+                if idx.is_primary {
                     html.push_str("<span class='badge bg-blue text-blue-fg me-1'><i class='ti ti-key'></i> PRIMARY</span>");
                 }
-                if is_unique {
+                if idx.is_unique {
                     html.push_str("<span class='badge bg-cyan text-cyan-fg me-1'><i class='ti ti-check'></i> UNIQUE</span>");
                 }
                 html.push_str("</td>");
 
-                let def_escaped = definition.replace('`', "\\`").replace('\'', "\\'");
-                let idx_name_escaped = idx_name.replace('\'', "\\'");
+                let def_escaped = idx.definition.replace('`', "\\`").replace('\'', "\\'");
+                let idx_name_escaped = idx.name.replace('\'', "\\'");
                 html.push_str("<td>");
-                html.push_str(&format!("<button class='btn btn-sm btn-ghost-secondary me-1' onclick='showIndexDDL(\"{}\", `{}`)' title='Show DDL'><i class='ti ti-code'></i></button>", idx_name, def_escaped));
+                html.push_str(&format!("<button class='btn btn-sm btn-ghost-secondary me-1' onclick='showIndexDDL(\"{}\", `{}`)' title='Show DDL'><i class='ti ti-code'></i></button>", idx.name, def_escaped));
                 html.push_str(&format!("<button class='btn btn-sm btn-warning' onclick='reindexSingle(\"{}\", \"{}\")' title='REINDEX this index'><i class='ti ti-refresh'></i></button>", schema, idx_name_escaped));
                 html.push_str("</td></tr>");
             }
@@ -680,23 +743,20 @@ pub async fn table_indexes(
     }
 }
 
-// Lazy load triggers
-pub async fn table_triggers(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Path((schema, name)): Path<(String, String)>,
-) -> Html<String> {
-    let active = match get_active_endpoint(&state, &jar).await {
-        Some(a) => a,
-        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
-    };
-
-    let pg = match connect_pg(&state, &active).await {
-        Ok(p) => p,
-        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct TableTrigger {
+    pub name: String,
+    pub timing: String,
+    pub event: String,
+    pub level: String,
+    pub function_name: String,
+    pub definition: String,
+    pub comment: Option<String>,
+    pub enabled: bool,
+}
 
-    let triggers = sqlx::query(
+pub async fn fetch_table_triggers(pg: &PgPool, schema: &str, name: &str) -> Result<Vec<TableTrigger>, sqlx::Error> {
+    let rows = sqlx::query(
         r#"
         SELECT
             t.tgname as trigger_name,
@@ -732,63 +792,86 @@ pub async fn table_triggers(
         ORDER BY t.tgname
         "#,
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_all(&pg)
-    .await;
-
-    match triggers {
-        Ok(rows) if rows.is_empty() => Html("<div class='text-center text-muted py-5'>No triggers found</div>".to_string()),
-        Ok(rows) => {
-            tracing::debug!("Found {} triggers for {}.{}", rows.len(), schema, name);
-            let mut html = String::from("<div class='table-responsive'><table class='table table-vcenter'><thead><tr><th>Name</th><th>Timing</th><th>Event</th><th>Level</th><th>Function</th><th>Status</th><th>Actions</th></tr></thead><tbody>");
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TableTrigger {
+            name: row.get("trigger_name"),
+            timing: row.get("timing"),
+            event: row.get("event"),
+            level: row.get("level"),
+            function_name: row.get("function_name"),
+            definition: row.get("definition"),
+            comment: row.get("comment"),
+            enabled: row.get("enabled"),
+        })
+        .collect())
+}
+
+// Lazy load triggers
+pub async fn table_triggers(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((schema, name)): Path<(String, String)>,
+) -> Html<String> {
+    let active = match get_active_endpoint(&state, &jar).await {
+        Some(a) => a,
+        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
+    };
 
-            for row in rows {
-                let trigger_name: String = row.get("trigger_name");
-                let timing: String = row.get("timing");
-                let event: String = row.get("event");
-                let level: String = row.get("level");
-                let function_name: String = row.get("function_name");
-                let definition: String = row.get("definition");
-                let enabled: bool = row.get("enabled");
+    let pg = match connect_pg_read(&state, &active).await {
+        Ok(p) => p,
+        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
+    };
+
+    match fetch_table_triggers(&pg, &schema, &name).await {
+        Ok(triggers) if triggers.is_empty() => Html("<div class='text-center text-muted py-5'>No triggers found</div>".to_string()),
+        Ok(triggers) => {
+            tracing::debug!("Found {} triggers for {}.{}", triggers.len(), schema, name);
+            let mut html = String::from("<div class='table-responsive'><table class='table table-vcenter'><thead><tr><th>Name</th><th>Timing</th><th>Event</th><th>Level</th><th>Function</th><th>Status</th><th>Actions</th></tr></thead><tbody>");
 
-                html.push_str(&format!("<tr><td><strong>{}</strong></td>", trigger_name));
+            for trigger in triggers {
+                html.push_str(&format!("<tr><td><strong>{}</strong></td>", trigger.name));
 
                 // Timing badge
-                let timing_color = match timing.as_str() {
+                let timing_color = match trigger.timing.as_str() {
                     "BEFORE" => "blue",
                     "AFTER" => "green",
                     "INSTEAD OF" => "purple",
                     _ => "gray"
                 };
-                html.push_str(&format!("<td><span class='badge bg-{}-lt text-{}-fg'>{}</span></td>", timing_color, timing_color, timing));
+                html.push_str(&format!("<td><span class='badge bg-{}-lt text-{}-fg'>{}</span></td>", timing_color, timing_color, trigger.timing));
 
                 // Event badge
-                let event_color = match event.as_str() {
+                let event_color = match trigger.event.as_str() {
                     "INSERT" => "green",
                     "UPDATE" => "yellow",
                     "DELETE" => "red",
                     "TRUNCATE" => "orange",
                     _ => "gray"
                 };
-                html.push_str(&format!("<td><span class='badge bg-{}-lt text-{}-fg'>{}</span></td>", event_color, event_color, event));
+                html.push_str(&format!("<td><span class='badge bg-{}-lt text-{}-fg'>{}</span></td>", event_color, event_color, trigger.event));
 
                 // Level
-                html.push_str(&format!("<td><span class='badge bg-gray-lt text-gray-fg'>{}</span></td>", level));
+                html.push_str(&format!("<td><span class='badge bg-gray-lt text-gray-fg'>{}</span></td>", trigger.level));
 
                 // Function
-                html.push_str(&format!("<td><code class='text-muted'>{}</code></td>", function_name));
+                html.push_str(&format!("<td><code class='text-muted'>{}</code></td>", trigger.function_name));
 
                 // Status
-                if enabled {
+                if trigger.enabled {
                     html.push_str("<td><span class='badge bg-success-lt text-success-fg'><i class='ti ti-check'></i> Enabled</span></td>");
                 } else {
                     html.push_str("<td><span class='badge bg-danger-lt text-danger-fg'><i class='ti ti-x'></i> Disabled</span></td>");
                 }
 
                 // Actions - DDL viewer
-                let def_escaped = definition.replace('`', "\\`").replace('\'', "\\'");
-                html.push_str(&format!("<td><button class='btn btn-sm btn-ghost-secondary' onclick='showTriggerDDL(\"{}\", `{}`)' title='Show DDL'><i class='ti ti-code'></i></button></td></tr>", trigger_name, def_escaped));
+                let def_escaped = trigger.definition.replace('`', "\\`").replace('\'', "\\'");
+                html.push_str(&format!("<td><button class='btn btn-sm btn-ghost-secondary' onclick='showTriggerDDL(\"{}\", `{}`)' title='Show DDL'><i class='ti ti-code'></i></button></td></tr>", trigger.name, def_escaped));
             }
 
             html.push_str("</tbody></table></div>");
@@ -801,23 +884,18 @@ pub async fn table_triggers(
     }
 }
 
-// Lazy load partitions
-pub async fn table_partitions(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Path((schema, name)): Path<(String, String)>,
-) -> Html<String> {
-    let active = match get_active_endpoint(&state, &jar).await {
-        Some(a) => a,
-        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
-    };
-
-    let pg = match connect_pg(&state, &active).await {
-        Ok(p) => p,
-        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct TablePartition {
+    pub name: String,
+    pub schema: String,
+    pub rows: i64,
+    pub size_bytes: i64,
+    pub index_count: i64,
+    pub bound: Option<String>,
+}
 
-    let parts = sqlx::query(
+pub async fn fetch_table_partitions(pg: &PgPool, schema: &str, name: &str) -> Result<Vec<TablePartition>, sqlx::Error> {
+    let rows = sqlx::query(
         r#"
         SELECT
             c.relname as partition_name,
@@ -837,49 +915,60 @@ pub async fn table_partitions(
         ORDER BY c.relname
         "#,
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_all(&pg)
-    .await;
-
-    match parts {
-        Ok(rows) if rows.is_empty() => Html("<div class='text-center text-muted py-5'>No partitions found</div>".to_string()),
-        Ok(rows) => {
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TablePartition {
+            name: row.get("partition_name"),
+            schema: row.get("partition_schema"),
+            rows: row.get("rows"),
+            size_bytes: row.get("size_bytes"),
+            index_count: row.get("index_count"),
+            bound: row.try_get("partition_bound").unwrap_or_default(),
+        })
+        .collect())
+}
+
+// Lazy load partitions
+pub async fn table_partitions(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((schema, name)): Path<(String, String)>,
+) -> Html<String> {
+    let active = match get_active_endpoint(&state, &jar).await {
+        Some(a) => a,
+        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
+    };
+
+    let pg = match connect_pg_read(&state, &active).await {
+        Ok(p) => p,
+        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
+    };
+
+    match fetch_table_partitions(&pg, &schema, &name).await {
+        Ok(partitions) if partitions.is_empty() => Html("<div class='text-center text-muted py-5'>No partitions found</div>".to_string()),
+        Ok(partitions) => {
             let mut total_rows: i64 = 0;
             let mut total_size: i64 = 0;
             let mut total_indexes: i64 = 0;
 
-            let mut chart_labels = Vec::new();
-            let mut chart_data = Vec::new();
-
-            // Nejdřív projdi data pro chart
-            for row in &rows {
-                let part_name: String = row.get("partition_name");
-                let size_bytes: i64 = row.get("size_bytes");
-                chart_labels.push(part_name);
-                chart_data.push(size_bytes);
-            }
-
             let mut html = String::from("<div class='row'><div class='col-md-4'><canvas id='partition-chart' style='max-height: 300px;'></canvas></div><div class='col-md-8'><div class='table-responsive'><table class='table table-vcenter'><thead><tr><th>Partition</th><th>Schema</th><th class='text-end'>Rows</th><th class='text-end'>Size</th><th class='text-end'>Indexes</th><th>Bounds</th></tr></thead><tbody>");
 
-            for row in rows {
-                let part_name: String = row.get("partition_name");
-                let part_schema: String = row.get("partition_schema");
-                let part_rows: i64 = row.get("rows");
-                let size_bytes: i64 = row.get("size_bytes");
-                let idx_count: i64 = row.get("index_count");
-                let bounds: String = row.try_get("partition_bound").unwrap_or_default();
-                
-                total_rows += part_rows;
-                total_size += size_bytes;
-                total_indexes += idx_count;
-
-                html.push_str(&format!("<tr><td><strong>{}</strong></td>", part_name));
-                html.push_str(&format!("<td><span class='badge bg-azure-lt text-azure-fg'>{}</span></td>", part_schema));
-                html.push_str(&format!("<td class='text-end'>{}</td>", part_rows));
-                html.push_str(&format!("<td class='text-end'>{}</td>", bytes_to_human(size_bytes)));
-                html.push_str(&format!("<td class='text-end'>{}</td>", idx_count));
-                html.push_str(&format!("<td><code class='text-muted small'>{}</code></td></tr>", bounds));
+            for part in &partitions {
+                total_rows += part.rows;
+                total_size += part.size_bytes;
+                total_indexes += part.index_count;
+
+                html.push_str(&format!("<tr><td><strong>{}</strong></td>", part.name));
+                html.push_str(&format!("<td><span class='badge bg-azure-lt text-azure-fg'>{}</span></td>", part.schema));
+                html.push_str(&format!("<td class='text-end'>{}</td>", part.rows));
+                html.push_str(&format!("<td class='text-end'>{}</td>", bytes_to_human(part.size_bytes)));
+                html.push_str(&format!("<td class='text-end'>{}</td>", part.index_count));
+                html.push_str(&format!("<td><code class='text-muted small'>{}</code></td></tr>", part.bound.as_deref().unwrap_or_default()));
             }
 
             // Total row
@@ -891,8 +980,8 @@ pub async fn table_partitions(
             html.push_str("</tbody></table></div></div></div>");
 
             // Přidej data jako JSON do data atributů
-            let labels_json: Vec<String> = chart_labels.iter().map(|s| format!("\"{}\"", s.replace('"', "\\\""))).collect();
-            let data_json: Vec<String> = chart_data.iter().map(|n| n.to_string()).collect();
+            let labels_json: Vec<String> = partitions.iter().map(|p| format!("\"{}\"", p.name.replace('"', "\\\""))).collect();
+            let data_json: Vec<String> = partitions.iter().map(|p| p.size_bytes.to_string()).collect();
 
             html.push_str(&format!(
                 "<script type='application/json' id='partition-chart-data'>{{\"labels\":[{}],\"data\":[{}]}}</script>",
@@ -909,22 +998,26 @@ pub async fn table_partitions(
     }
 }
 
-// Lazy load relationships
-pub async fn table_relationships(
-    State(state): State<Arc<AppState>>,
-    jar: CookieJar,
-    Path((schema, name)): Path<(String, String)>,
-) -> Html<String> {
-    let active = match get_active_endpoint(&state, &jar).await {
-        Some(a) => a,
-        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
-    };
+/// Cizí klíč mezi tabulkami. U odchozích je `schema.table` odkazovaná tabulka, u příchozích
+/// tabulka, která odkazuje; `columns` jsou vždy sloupce na straně FK
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeyLink {
+    pub constraint_name: String,
+    pub schema: String,
+    pub table: String,
+    pub columns: String,
+    pub referenced_columns: String,
+}
 
-    let pg = match connect_pg(&state, &active).await {
-        Ok(p) => p,
-        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
-    };
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TableRelationships {
+    /// Tato tabulka odkazuje na jiné
+    pub outgoing: Vec<ForeignKeyLink>,
+    /// Jiné tabulky odkazují na tuto
+    pub incoming: Vec<ForeignKeyLink>,
+}
 
+pub async fn fetch_table_relationships(pg: &PgPool, schema: &str, name: &str) -> Result<TableRelationships, sqlx::Error> {
     // Get outgoing FK relationships (this table references other tables)
     let outgoing = sqlx::query(
         r#"
@@ -947,10 +1040,10 @@ pub async fn table_relationships(
         ORDER BY fc.relname
         "#,
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_all(&pg)
-    .await;
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
 
     // Get incoming FK relationships (other tables reference this table)
     let incoming = sqlx::query(
@@ -974,13 +1067,54 @@ pub async fn table_relationships(
         ORDER BY tc.relname
         "#,
     )
-    .bind(&schema)
-    .bind(&name)
-    .fetch_all(&pg)
-    .await;
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pg)
+    .await?;
+
+    Ok(TableRelationships {
+        outgoing: outgoing
+            .iter()
+            .map(|row| ForeignKeyLink {
+                constraint_name: row.get("constraint_name"),
+                schema: row.get("foreign_schema"),
+                table: row.get("foreign_table"),
+                columns: row.get("local_columns"),
+                referenced_columns: row.get("foreign_columns"),
+            })
+            .collect(),
+        incoming: incoming
+            .iter()
+            .map(|row| ForeignKeyLink {
+                constraint_name: row.get("constraint_name"),
+                schema: row.get("referencing_schema"),
+                table: row.get("referencing_table"),
+                columns: row.get("referencing_columns"),
+                referenced_columns: row.get("referenced_columns"),
+            })
+            .collect(),
+    })
+}
+
+// Lazy load relationships
+pub async fn table_relationships(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((schema, name)): Path<(String, String)>,
+) -> Html<String> {
+    let active = match get_active_endpoint(&state, &jar).await {
+        Some(a) => a,
+        None => return Html("<div class='text-muted'>No active connection</div>".to_string()),
+    };
 
-    let outgoing = outgoing.unwrap_or_default();
-    let incoming = incoming.unwrap_or_default();
+    let pg = match connect_pg_read(&state, &active).await {
+        Ok(p) => p,
+        Err(_) => return Html("<div class='text-muted'>Connection failed</div>".to_string()),
+    };
+
+    let relationships = fetch_table_relationships(&pg, &schema, &name).await.unwrap_or_default();
+    let outgoing = relationships.outgoing;
+    let incoming = relationships.incoming;
 
     if outgoing.is_empty() && incoming.is_empty() {
         return Html("<div class='text-center text-muted py-5'>No foreign key relationships found</div>".to_string());
@@ -992,30 +1126,20 @@ pub async fn table_relationships(
     let current_table = format!("{}.{}", schema, name);
 
     // Add outgoing relationships (this table -> foreign table)
-    for row in &outgoing {
-        let foreign_schema: String = row.get("foreign_schema");
-        let foreign_table: String = row.get("foreign_table");
-        let foreign_table_name = format!("{}.{}", foreign_schema, foreign_table);
-        let local_cols: String = row.get("local_columns");
-        let foreign_cols: String = row.get("foreign_columns");
-
+    for fk in &outgoing {
+        let foreign_table_name = format!("{}.{}", fk.schema, fk.table);
         mermaid.push_str(&format!(
             "    \"{}\" }}o--|| \"{}\" : \"{}.{} to {}.{}\"\n",
-            current_table, foreign_table_name, schema, local_cols, foreign_schema, foreign_cols
+            current_table, foreign_table_name, schema, fk.columns, fk.schema, fk.referenced_columns
         ));
     }
 
     // Add incoming relationships (referencing table -> this table)
-    for row in &incoming {
-        let ref_schema: String = row.get("referencing_schema");
-        let ref_table: String = row.get("referencing_table");
-        let ref_table_name = format!("{}.{}", ref_schema, ref_table);
-        let ref_cols: String = row.get("referencing_columns");
-        let referenced_cols: String = row.get("referenced_columns");
-
+    for fk in &incoming {
+        let ref_table_name = format!("{}.{}", fk.schema, fk.table);
         mermaid.push_str(&format!(
             "    \"{}\" }}o--|| \"{}\" : \"{}.{} to {}.{}\"\n",
-            ref_table_name, current_table, ref_schema, ref_cols, schema, referenced_cols
+            ref_table_name, current_table, fk.schema, fk.columns, schema, fk.referenced_columns
         ));
     }
 
@@ -1040,19 +1164,13 @@ pub async fn table_relationships(
         html.push_str("<h3 class='mb-3'><i class='ti ti-arrow-right me-2'></i>References (Outgoing FK)</h3>");
         html.push_str("<div class='table-responsive'><table class='table table-vcenter card'><thead><tr><th>Constraint</th><th>Foreign Table</th><th>Columns</th></tr></thead><tbody>");
 
-        for row in &outgoing {
-            let constraint_name: String = row.get("constraint_name");
-            let foreign_schema: String = row.get("foreign_schema");
-            let foreign_table: String = row.get("foreign_table");
-            let local_cols: String = row.get("local_columns");
-            let foreign_cols: String = row.get("foreign_columns");
-
-            html.push_str(&format!("<tr><td><code class='text-muted'>{}</code></td>", constraint_name));
+        for fk in &outgoing {
+            html.push_str(&format!("<tr><td><code class='text-muted'>{}</code></td>", fk.constraint_name));
             html.push_str(&format!(
                 "<td><a href='{}/tables/{}/{}/detail' class='text-decoration-none'><strong>{}.{}</strong></a></td>",
-                base_path, foreign_schema, foreign_table, foreign_schema, foreign_table
+                base_path, fk.schema, fk.table, fk.schema, fk.table
             ));
-            html.push_str(&format!("<td><code>{}</code> → <code>{}</code></td></tr>", local_cols, foreign_cols));
+            html.push_str(&format!("<td><code>{}</code> → <code>{}</code></td></tr>", fk.columns, fk.referenced_columns));
         }
 
         html.push_str("</tbody></table></div>");
@@ -1065,19 +1183,13 @@ pub async fn table_relationships(
         html.push_str("<h3 class='mb-3'><i class='ti ti-arrow-left me-2'></i>Referenced by (Incoming FK)</h3>");
         html.push_str("<div class='table-responsive'><table class='table table-vcenter card'><thead><tr><th>Constraint</th><th>Referencing Table</th><th>Columns</th></tr></thead><tbody>");
 
-        for row in &incoming {
-            let constraint_name: String = row.get("constraint_name");
-            let ref_schema: String = row.get("referencing_schema");
-            let ref_table: String = row.get("referencing_table");
-            let ref_cols: String = row.get("referencing_columns");
-            let referenced_cols: String = row.get("referenced_columns");
-
-            html.push_str(&format!("<tr><td><code class='text-muted'>{}</code></td>", constraint_name));
+        for fk in &incoming {
+            html.push_str(&format!("<tr><td><code class='text-muted'>{}</code></td>", fk.constraint_name));
             html.push_str(&format!(
                 "<td><a href='{}/tables/{}/{}/detail' class='text-decoration-none'><strong>{}.{}</strong></a></td>",
-                base_path, ref_schema, ref_table, ref_schema, ref_table
+                base_path, fk.schema, fk.table, fk.schema, fk.table
             ));
-            html.push_str(&format!("<td><code>{}</code> → <code>{}</code></td></tr>", ref_cols, referenced_cols));
+            html.push_str(&format!("<td><code>{}</code> → <code>{}</code></td></tr>", fk.columns, fk.referenced_columns));
         }
 
         html.push_str("</tbody></table></div>");
@@ -5,7 +5,9 @@ use serde::Deserialize;
 use std::sync::Arc;
 use std::time::Instant;
 
-use crate::handlers::{base_path_url, build_ctx_with_endpoint, connect_pg, get_active_endpoint, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, cache_key, AppState, CACHE_TTL, CacheEntry};
+use crate::handlers::db_session::in_current_session;
+use crate::handlers::replica::{connect_pg_read, note_served_by, with_served_by};
 use crate::templates::{TableModalTemplate, TableRow, TablesTemplate, TablesTableTemplate};
 use crate::utils::filter::{matches_simple_terms, parse_simple_terms};
 use crate::utils::format::bytes_to_human;
@@ -71,17 +73,17 @@ fn has_pattern_ops(value: &str) -> bool {
         || v.contains(" NOT ")
 }
 
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Clone, serde::Serialize)]
 pub struct TableRowDb {
-    schema: String,
-    name: String,
-    size_bytes: i64,
-    row_estimate: i64,
-    index_count: i64,
-    partitions: Option<Vec<String>>,
+    pub schema: String,
+    pub name: String,
+    pub size_bytes: i64,
+    pub row_estimate: i64,
+    pub index_count: i64,
+    pub partitions: Option<Vec<String>>,
 }
 
-async fn fetch_tables_from_db(
+pub async fn fetch_tables_from_db(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> Result<Vec<TableRowDb>, String> {
@@ -118,7 +120,7 @@ async fn fetch_tables_from_db(
             ORDER BY size_bytes DESC
             "#;
 
-    match connect_pg(state, active).await {
+    match connect_pg_read(state, active).await {
         Ok(pg) => match sqlx::query_as::<_, TableRowDb>(tables_sql).fetch_all(&pg).await {
             Ok(rows) => Ok(rows),
             Err(err) => Err(format!("Failed to load tables: {}", err)),
@@ -127,7 +129,8 @@ async fn fetch_tables_from_db(
     }
 }
 
-async fn get_cached_tables(
+/// Data z cache; prázdná cache spustí načtení na pozadí (druhá hodnota = právě se načítá)
+pub async fn get_cached_tables(
     state: &Arc<AppState>,
     active: &crate::db::models::Endpoint,
 ) -> (Vec<TableRowDb>, bool) {
@@ -135,32 +138,38 @@ async fn get_cached_tables(
     let mut should_refresh = false;
     let (data, fetching) = {
         let mut cache = state.tables_cache.write().await;
-        match cache.get_mut(&active.id) {
+        match cache.get_mut(&cache_key(active)) {
             Some(entry) => {
                 let stale = now.duration_since(entry.fetched_at) > CACHE_TTL;
                 if stale && !entry.fetching {
                     entry.fetching = true;
                     should_refresh = true;
                 }
+                state.metrics.cache_lookup("tables", true);
                 tracing::debug!(
                     "tables cache hit id={} stale={} fetching={}",
                     active.id,
                     stale,
                     entry.fetching
                 );
+                if let Some(label) = &entry.served_by {
+                    note_served_by(label.clone());
+                }
                 (entry.data.clone(), entry.fetching)
             }
             None => {
                 cache.insert(
-                    active.id,
+                    cache_key(active),
                     CacheEntry {
                         data: Vec::new(),
                         fetched_at: now,
                         fetching: true,
+                        served_by: None,
                     },
                 );
                 should_refresh = true;
                 tracing::debug!("tables cache miss id={}, scheduling refresh", active.id);
+                state.metrics.cache_lookup("tables", false);
                 (Vec::new(), true)
             }
         }
@@ -169,17 +178,18 @@ async fn get_cached_tables(
     if should_refresh {
         let state = state.clone();
         let active = active.clone();
-        tokio::spawn(async move {
-            let result = fetch_tables_from_db(&state, &active).await;
+        tokio::spawn(in_current_session(async move {
+            let (result, served_by) = with_served_by(fetch_tables_from_db(&state, &active)).await;
             let mut cache = state.tables_cache.write().await;
-            if let Some(entry) = cache.get_mut(&active.id) {
+            if let Some(entry) = cache.get_mut(&cache_key(&active)) {
                 if let Ok(rows) = result {
                     entry.data = rows;
+                    entry.served_by = served_by;
                     entry.fetched_at = Instant::now();
                 }
                 entry.fetching = false;
             }
-        });
+        }));
     }
 
     (data, fetching)
@@ -707,7 +717,7 @@ pub async fn table_modal(
         total: "-".to_string(),
     };
 
-    match connect_pg(&state, &active).await {
+    match connect_pg_read(&state, &active).await {
         Ok(pg) => {
             if schema == "*" {
                 let schemas = sqlx::query_scalar::<_, String>(
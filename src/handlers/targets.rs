@@ -0,0 +1,237 @@
+//! S3-kompatibilní cíle pro nahrání dumpů (správa jen pro administrátory)
+
+use anyhow::Context;
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::CurrentUser;
+use crate::db::models::{Endpoint, NewStorageTarget, StorageTarget};
+use crate::handlers::{audit, base_path_url, build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::templates::TargetsTemplate;
+use crate::utils::s3::{S3Client, S3Config};
+
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Cíl do výběru v export/import wizardu a plánech (bez přihlašovacích údajů)
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetOption {
+    pub id: i64,
+    pub name: String,
+    /// s3://bucket/prefix
+    pub location: String,
+}
+
+pub fn location(target: &StorageTarget) -> String {
+    match target.prefix.as_deref() {
+        Some(prefix) => format!("s3://{}/{}", target.bucket, prefix),
+        None => format!("s3://{}", target.bucket),
+    }
+}
+
+/// Klíč objektu: <prefix>/<jméno connection>/<soubor>
+pub fn object_key(target: &StorageTarget, endpoint: &Endpoint, file_name: &str) -> String {
+    let connection: String = endpoint
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    let key = format!("{}/{}", connection, file_name);
+    match target.prefix.as_deref() {
+        Some(prefix) => format!("{}/{}", prefix, key),
+        None => key,
+    }
+}
+
+pub async fn target_options(state: &AppState) -> Vec<TargetOption> {
+    let Some(db) = state.db.as_ref() else {
+        return Vec::new();
+    };
+    match db.get_storage_targets().await {
+        Ok(targets) => targets
+            .iter()
+            .map(|t| TargetOption { id: t.id, name: t.name.clone(), location: location(t) })
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Failed to load storage targets: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Cíl podle ID s klientem (dešifrovaný secret key)
+pub async fn client(state: &AppState, target_id: i64) -> anyhow::Result<(StorageTarget, S3Client)> {
+    let db = state.db.as_ref().context("Storage targets are not available in stateless mode")?;
+    let target = db
+        .get_storage_target(target_id)
+        .await?
+        .with_context(|| format!("Storage target {} does not exist", target_id))?;
+    let client = S3Client::new(S3Config {
+        endpoint_url: target.endpoint_url.clone(),
+        region: target.region.clone(),
+        bucket: target.bucket.clone(),
+        access_key: target.access_key.clone(),
+        secret_key: db.get_storage_target_secret(&target)?,
+        path_style: target.path_style,
+    })?;
+    Ok((target, client))
+}
+
+fn targets_db<'a>(state: &'a AppState, user: &CurrentUser) -> Result<&'a crate::db::Database, (StatusCode, String)> {
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, "Administrator role required".to_string()));
+    }
+    state
+        .db
+        .as_ref()
+        .ok_or((StatusCode::BAD_REQUEST, "Storage targets are not available in stateless mode".to_string()))
+}
+
+async fn render_targets(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+    user: &CurrentUser,
+    message: Option<String>,
+    error: Option<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = targets_db(state, user)?;
+    let targets = db
+        .get_storage_targets()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let active = get_active_endpoint(state, jar).await;
+    let status = if error.is_some() { StatusCode::BAD_REQUEST } else { StatusCode::OK };
+    let tpl = TargetsTemplate {
+        ctx: build_ctx_with_endpoint(state, active.as_ref()),
+        title: "Storage targets | Postgres Explorer".to_string(),
+        targets,
+        message,
+        error,
+    };
+    let html = tpl
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((status, Html(html)).into_response())
+}
+
+pub async fn targets_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Response, (StatusCode, String)> {
+    render_targets(&state, &jar, &user, None, None).await
+}
+
+#[derive(Deserialize)]
+pub struct CreateTargetForm {
+    pub name: String,
+    pub endpoint_url: String,
+    pub region: Option<String>,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    pub path_style: Option<String>,
+    pub delete_local: Option<String>,
+}
+
+pub async fn create_target(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<CreateTargetForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = targets_db(&state, &user)?;
+    let non_empty = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let target = NewStorageTarget {
+        name: form.name.trim().to_string(),
+        endpoint_url: form.endpoint_url.trim().trim_end_matches('/').to_string(),
+        region: non_empty(&form.region).unwrap_or_else(|| DEFAULT_REGION.to_string()),
+        bucket: form.bucket.trim().to_string(),
+        prefix: non_empty(&form.prefix).map(|p| p.trim_matches('/').to_string()).filter(|p| !p.is_empty()),
+        access_key: form.access_key.trim().to_string(),
+        secret_key: form.secret_key.trim().to_string(),
+        path_style: form.path_style.is_some(),
+        delete_local: form.delete_local.is_some(),
+    };
+    let error = if target.name.is_empty() || target.name.len() > 100 {
+        Some("Name is required (max. 100 characters)".to_string())
+    } else if target.bucket.is_empty() || target.access_key.is_empty() || target.secret_key.is_empty() {
+        Some("Bucket, access key and secret key are required".to_string())
+    } else {
+        S3Client::new(S3Config {
+            endpoint_url: target.endpoint_url.clone(),
+            region: target.region.clone(),
+            bucket: target.bucket.clone(),
+            access_key: target.access_key.clone(),
+            secret_key: target.secret_key.clone(),
+            path_style: target.path_style,
+        })
+        .err()
+        .map(|e| format!("{:#}", e))
+    };
+    let error = match error {
+        Some(error) => Some(error),
+        None => match db.get_storage_targets().await {
+            Ok(existing) if existing.iter().any(|t| t.name == target.name) => {
+                Some(format!("Storage target '{}' already exists", target.name))
+            }
+            Ok(_) => None,
+            Err(e) => Some(format!("{:#}", e)),
+        },
+    };
+    if let Some(error) = error {
+        return render_targets(&state, &jar, &user, None, Some(error)).await;
+    }
+    if let Err(e) = db.create_storage_target(&target).await {
+        return render_targets(&state, &jar, &user, None, Some(format!("{:#}", e))).await;
+    }
+    let command = format!("create storage target '{}' ({} at {})", target.name, target.bucket, target.endpoint_url);
+    audit::record_global(&state, &user, "storage", &command, None).await;
+
+    Ok(Redirect::to(&base_path_url(&state, "/targets")).into_response())
+}
+
+/// POST /targets/{id}/test - ověří přihlašovací údaje výpisem bucketu
+pub async fn test_target(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    targets_db(&state, &user)?;
+    let result = async {
+        let (target, client) = client(&state, id).await?;
+        client.check().await?;
+        Ok::<_, anyhow::Error>(target)
+    }
+    .await;
+    match result {
+        Ok(target) => render_targets(&state, &jar, &user, Some(format!("{}: connection OK", target.name)), None).await,
+        Err(e) => render_targets(&state, &jar, &user, None, Some(format!("{:#}", e))).await,
+    }
+}
+
+/// Smaže cíl; nahrané objekty v bucketu zůstávají
+pub async fn delete_target(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = targets_db(&state, &user)?;
+    let target = db
+        .get_storage_target(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Storage target not found".to_string()))?;
+    db.delete_storage_target(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    audit::record_global(&state, &user, "storage", &format!("delete storage target '{}'", target.name), None).await;
+    Ok(Redirect::to(&base_path_url(&state, "/targets")).into_response())
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use openssl::ssl::{SslConnectorBuilder, SslFiletype};
+use tokio::process::Command;
+
+use crate::db::models::Endpoint;
+use crate::handlers::conninfo::ConnInfo;
+use crate::handlers::AppState;
+
+/// Soubory s CA / klientským certifikátem pro jeden endpoint.
+/// Vložené PEM se zapíšou do privátního runtime adresáře, cesty se použijí přímo.
+#[derive(Debug, Clone, Default)]
+pub struct TlsFiles {
+    pub root_cert: Option<PathBuf>,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+}
+
+impl TlsFiles {
+    /// Přidá sslrootcert/sslcert/sslkey do connection stringu pro sqlx
+    pub fn apply_to(&self, info: &mut ConnInfo) {
+        for (name, path) in [
+            ("sslrootcert", &self.root_cert),
+            ("sslcert", &self.cert),
+            ("sslkey", &self.key),
+        ] {
+            if let Some(path) = path {
+                info.set_param(name, path.to_string_lossy());
+            }
+        }
+    }
+
+    /// Nastaví CA a klientský certifikát pro tokio-postgres (safe mode konzole)
+    pub fn configure_connector(&self, builder: &mut SslConnectorBuilder) -> Result<()> {
+        if let Some(root) = &self.root_cert {
+            builder
+                .set_ca_file(root)
+                .with_context(|| format!("Failed to load CA certificate {}", root.display()))?;
+        }
+        if let Some(cert) = &self.cert {
+            builder
+                .set_certificate_chain_file(cert)
+                .with_context(|| format!("Failed to load client certificate {}", cert.display()))?;
+        }
+        if let Some(key) = &self.key {
+            builder
+                .set_private_key_file(key, SslFiletype::PEM)
+                .with_context(|| format!("Failed to load client key {}", key.display()))?;
+            builder
+                .check_private_key()
+                .context("Client key does not match the client certificate")?;
+        }
+        Ok(())
+    }
+
+    /// PGSSL* proměnné pro pg_dump/psql/pg_restore
+    pub fn apply_env(&self, cmd: &mut Command, endpoint: &Endpoint) {
+        if let Some(mode) = ssl_mode(endpoint) {
+            cmd.env("PGSSLMODE", mode);
+        }
+        if let Some(root) = &self.root_cert {
+            cmd.env("PGSSLROOTCERT", root);
+        }
+        if let Some(cert) = &self.cert {
+            cmd.env("PGSSLCERT", cert);
+        }
+        if let Some(key) = &self.key {
+            cmd.env("PGSSLKEY", key);
+        }
+    }
+}
+
+/// sslmode endpointu se stejnou logikou jako apply_connection_params (insecure => require)
+pub fn ssl_mode(endpoint: &Endpoint) -> Option<String> {
+    let mode = endpoint
+        .ssl_mode
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty());
+    match mode {
+        Some(mode) => Some(mode.to_string()),
+        None if endpoint.insecure => Some("require".to_string()),
+        None => None,
+    }
+}
+
+/// Připraví TLS soubory endpointu (klíč se dešifruje a převede na PKCS#8, který potřebuje sqlx)
+pub async fn endpoint_tls_files(state: &AppState, endpoint: &Endpoint) -> Result<TlsFiles> {
+    let key = crate::handlers::endpoint_ssl_key(state, endpoint).await;
+    let root_cert = non_empty(endpoint.ssl_root_cert.as_deref());
+    let cert = non_empty(endpoint.ssl_cert.as_deref());
+    let key = non_empty(key.as_deref());
+
+    if root_cert.is_none() && cert.is_none() && key.is_none() {
+        return Ok(TlsFiles::default());
+    }
+    if cert.is_some() != key.is_some() {
+        anyhow::bail!("TLS client certificate and client key must be configured together");
+    }
+
+    let dir = endpoint_dir(endpoint.id)?;
+    let root_cert = match root_cert {
+        Some(value) => Some(materialize(&dir, "root.crt", value)?),
+        None => None,
+    };
+    let cert = match cert {
+        Some(value) => Some(materialize(&dir, "client.crt", value)?),
+        None => None,
+    };
+    let key = match key {
+        Some(value) => {
+            let pem = if is_pem(value) {
+                value.as_bytes().to_vec()
+            } else {
+                std::fs::read(expand_home(value))
+                    .with_context(|| format!("Failed to read client key {}", value))?
+            };
+            let pkey = openssl::pkey::PKey::private_key_from_pem(&pem)
+                .context("Client key is not a valid unencrypted PEM private key")?;
+            let pkcs8 = pkey.private_key_to_pem_pkcs8()?;
+            Some(write_private(&dir, "client.key", &pkcs8)?)
+        }
+        None => None,
+    };
+
+    Ok(TlsFiles { root_cert, cert, key })
+}
+
+/// Smaže runtime soubory endpointu (po úpravě nebo smazání)
+pub fn remove_endpoint_files(endpoint_id: i64) {
+    if let Ok(dir) = runtime_dir() {
+        let _ = std::fs::remove_dir_all(dir.join(format!("endpoint-{}", endpoint_id)));
+    }
+}
+
+/// TLS soubory leží v privátním runtime adresáři procesu (smaže se při ukončení)
+fn runtime_dir() -> Result<PathBuf> {
+    Ok(crate::utils::runtime::private_dir()?.join("tls"))
+}
+
+fn endpoint_dir(endpoint_id: i64) -> Result<PathBuf> {
+    let dir = runtime_dir()?.join(format!("endpoint-{}", endpoint_id));
+    crate::utils::runtime::create_private_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn is_pem(value: &str) -> bool {
+    value.starts_with("-----BEGIN")
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|h| h.join(rest)).unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Cesta zůstane cestou, vložené PEM se zapíše do souboru
+fn materialize(dir: &Path, name: &str, value: &str) -> Result<PathBuf> {
+    if is_pem(value) {
+        let mut pem = value.to_string();
+        if !pem.ends_with('\n') {
+            pem.push('\n');
+        }
+        write_private(dir, name, pem.as_bytes())
+    } else {
+        Ok(expand_home(value))
+    }
+}
+
+fn write_private(dir: &Path, name: &str, content: &[u8]) -> Result<PathBuf> {
+    let path = dir.join(name);
+    if std::fs::read(&path).map(|c| c == content).unwrap_or(false) {
+        return Ok(path);
+    }
+    // Zápis přes dočasný soubor, aby souběžné spojení nenačetlo polovinu souboru.
+    // Soubor vzniká rovnou s 0600 (libpq odmítne klíč čitelný pro ostatní).
+    let tmp = dir.join(format!("{}.{}.tmp", name, uuid::Uuid::new_v4().simple()));
+    crate::utils::runtime::write_new(&tmp, content, 0o600)?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,209 @@
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::tokens::{new_token, Scope, KIND_PERSONAL, KIND_SERVICE, SERVICE_USER_PREFIX};
+use crate::auth::{parse_groups, AuthMode, CurrentUser};
+use crate::db::models::{ApiToken, NewApiToken};
+use crate::handlers::audit;
+use crate::handlers::{base_path_url, build_ctx_with_endpoint, get_active_endpoint, AppState};
+use crate::templates::TokensTemplate;
+
+/// Nejdelší povolená platnost tokenu (dny)
+const MAX_EXPIRES_DAYS: i64 = 3650;
+
+#[derive(Deserialize)]
+pub struct CreateTokenForm {
+    pub name: String,
+    pub kind: Option<String>,
+    pub groups: Option<String>,
+    /// Počet dní nebo "never"
+    pub expires: String,
+    pub scope_read: Option<String>,
+    pub scope_maintenance: Option<String>,
+    pub scope_export: Option<String>,
+    pub scope_console: Option<String>,
+}
+
+impl CreateTokenForm {
+    fn scopes(&self) -> Vec<Scope> {
+        [
+            (Scope::Read, &self.scope_read),
+            (Scope::Maintenance, &self.scope_maintenance),
+            (Scope::Export, &self.scope_export),
+            (Scope::Console, &self.scope_console),
+        ]
+        .into_iter()
+        .filter(|(_, checked)| checked.is_some())
+        .map(|(scope, _)| scope)
+        .collect()
+    }
+}
+
+fn tokens_db(state: &Arc<AppState>) -> Result<&crate::db::Database, (StatusCode, String)> {
+    if state.auth_mode == AuthMode::None {
+        return Err((StatusCode::NOT_FOUND, "Authentication is disabled".to_string()));
+    }
+    state
+        .db
+        .as_ref()
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Stateless mode".to_string()))
+}
+
+/// Osobní tokeny vidí a ruší jejich vlastník, administrátor všechny
+fn owns(user: &CurrentUser, token: &ApiToken) -> bool {
+    user.is_admin
+        || (token.kind == KIND_PERSONAL
+            && match (token.user_id, user.id) {
+                (Some(owner), Some(id)) => owner == id,
+                (None, None) => token.username == user.username,
+                _ => false,
+            })
+}
+
+async fn render_tokens(
+    state: &Arc<AppState>,
+    jar: &CookieJar,
+    user: &CurrentUser,
+    created_token: Option<String>,
+    error: Option<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = tokens_db(state)?;
+    let tokens: Vec<ApiToken> = db
+        .get_api_tokens()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .filter(|t| owns(user, t))
+        .collect();
+    let active = get_active_endpoint(state, jar).await;
+    let status = if error.is_some() { StatusCode::BAD_REQUEST } else { StatusCode::OK };
+    let tpl = TokensTemplate {
+        ctx: build_ctx_with_endpoint(state, active.as_ref()),
+        title: "API Tokens | Postgres Explorer".to_string(),
+        tokens,
+        can_manage_all: user.is_admin,
+        created_token,
+        error,
+    };
+    let html = tpl
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((status, Html(html)).into_response())
+}
+
+pub async fn tokens_page(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+) -> Result<Response, (StatusCode, String)> {
+    render_tokens(&state, &jar, &user, None, None).await
+}
+
+pub async fn create_token(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    user: CurrentUser,
+    Form(form): Form<CreateTokenForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = tokens_db(&state)?;
+    let name = form.name.trim();
+    let kind = form.kind.as_deref().unwrap_or(KIND_PERSONAL);
+    let scopes = form.scopes();
+    let expires_in_days = match form.expires.trim() {
+        "never" => None,
+        days => match days.parse::<i64>() {
+            Ok(days) if (1..=MAX_EXPIRES_DAYS).contains(&days) => Some(days),
+            _ => return render_tokens(&state, &jar, &user, None, Some("Invalid expiry".to_string())).await,
+        },
+    };
+    let error = if name.is_empty() || name.len() > 100 {
+        Some("Token name is required (max. 100 characters)")
+    } else if scopes.is_empty() {
+        Some("Select at least one scope")
+    } else if kind != KIND_PERSONAL && kind != KIND_SERVICE {
+        Some("Invalid token kind")
+    } else if kind == KIND_SERVICE && !user.is_admin {
+        Some("Only administrators can create service tokens")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return render_tokens(&state, &jar, &user, None, Some(error.to_string())).await;
+    }
+
+    let (token_hash, token_prefix, token) =
+        new_token().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let scopes = scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(",");
+    let record = if kind == KIND_SERVICE {
+        let groups = parse_groups(form.groups.as_deref()).join(",");
+        NewApiToken {
+            name: name.to_string(),
+            kind: KIND_SERVICE.to_string(),
+            user_id: None,
+            username: format!("{}{}", SERVICE_USER_PREFIX, name),
+            groups: if groups.is_empty() { None } else { Some(groups) },
+            is_admin: false,
+            scopes,
+            token_hash,
+            token_prefix,
+            expires_in_days,
+            created_by: user.username.clone(),
+        }
+    } else {
+        // Uživatel z proxy nemá účet v SQLite, token si pamatuje jeho skupiny z doby vytvoření
+        NewApiToken {
+            name: name.to_string(),
+            kind: KIND_PERSONAL.to_string(),
+            user_id: user.id,
+            username: user.username.clone(),
+            groups: if user.groups.is_empty() { None } else { Some(user.groups.join(",")) },
+            is_admin: user.is_admin,
+            scopes,
+            token_hash,
+            token_prefix,
+            expires_in_days,
+            created_by: user.username.clone(),
+        }
+    };
+    db.create_api_token(&record)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let command = format!(
+        "create {} token '{}' for {} (scopes: {}, expires: {})",
+        record.kind,
+        record.name,
+        record.username,
+        record.scopes,
+        expires_in_days.map(|d| format!("{} days", d)).unwrap_or_else(|| "never".to_string())
+    );
+    audit::record_global(&state, &user, "token", &command, None).await;
+
+    render_tokens(&state, &jar, &user, Some(token), None).await
+}
+
+pub async fn revoke_token(
+    State(state): State<Arc<AppState>>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Response, (StatusCode, String)> {
+    let db = tokens_db(&state)?;
+    let token = db
+        .get_api_tokens()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .find(|t| t.id == id && owns(&user, t))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Token not found".to_string()))?;
+    db.delete_api_token(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let command = format!("revoke {} token '{}' of {}", token.kind, token.name, token.username);
+    audit::record_global(&state, &user, "token", &command, None).await;
+    Ok(Redirect::to(&base_path_url(&state, "/tokens")).into_response())
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+/// Jak dlouho čekáme na navázání tunelu
+pub const TUNNEL_START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Cesta k askpass skriptu v privátním runtime adresáři
+static ASKPASS_SCRIPT: StdMutex<Option<PathBuf>> = StdMutex::new(None);
+
+/// SSH nastavení endpointu (bastion)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshTunnelConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: Option<String>,
+    /// Bez klíče se použije ssh-agent (SSH_AUTH_SOCK) nebo ~/.ssh/config
+    pub key_path: Option<String>,
+    pub passphrase: Option<String>,
+}
+
+/// Kam tunel vede (Postgres host za bastionem)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelTarget {
+    pub host: String,
+    pub port: u16,
+}
+
+struct TunnelEntry {
+    child: Child,
+    /// Lokální port pro každý cíl (multi-host endpoint má víc forwardů v jednom ssh)
+    local_ports: Vec<u16>,
+    config: SshTunnelConfig,
+    targets: Vec<TunnelTarget>,
+    started_at: Instant,
+}
+
+/// Registry of `ssh -L` processes, one per endpoint
+#[derive(Clone, Default)]
+pub struct SshTunnelRegistry {
+    tunnels: Arc<Mutex<HashMap<i64, TunnelEntry>>>,
+}
+
+impl SshTunnelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Vrátí lokální porty tunelu (ve stejném pořadí jako cíle); pokud tunel neběží
+    /// (nebo se změnilo nastavení), spustí nový. Druhá hodnota říká, jestli byl tunel (znovu) spuštěn.
+    pub async fn local_ports(
+        &self,
+        endpoint_id: i64,
+        config: &SshTunnelConfig,
+        targets: &[TunnelTarget],
+    ) -> Result<(Vec<u16>, bool)> {
+        // Zámek držíme i během startu, aby dva requesty nespustily dva tunely
+        let mut guard = self.tunnels.lock().await;
+        let mut reuse_ports = Vec::new();
+        if let Some(entry) = guard.get_mut(&endpoint_id) {
+            let alive = matches!(entry.child.try_wait(), Ok(None));
+            if alive && entry.config == *config && entry.targets == targets {
+                return Ok((entry.local_ports.clone(), false));
+            }
+            if alive {
+                let _ = entry.child.start_kill();
+            } else {
+                tracing::warn!(
+                    "SSH tunnel for endpoint {} exited after {}s, restarting",
+                    endpoint_id,
+                    entry.started_at.elapsed().as_secs()
+                );
+                // Stejné porty, aby URL v existujících poolech zůstala platná
+                reuse_ports = entry.local_ports.clone();
+            }
+            guard.remove(&endpoint_id);
+        }
+
+        let entry = start_tunnel(endpoint_id, config, targets, &reuse_ports).await?;
+        let ports = entry.local_ports.clone();
+        guard.insert(endpoint_id, entry);
+        Ok((ports, true))
+    }
+
+    pub async fn close(&self, endpoint_id: i64) {
+        if let Some(mut entry) = self.tunnels.lock().await.remove(&endpoint_id) {
+            tracing::info!("Closing SSH tunnel for endpoint {}", endpoint_id);
+            let _ = entry.child.kill().await;
+        }
+    }
+
+    pub async fn close_all(&self) {
+        let mut guard = self.tunnels.lock().await;
+        for (endpoint_id, mut entry) in guard.drain() {
+            tracing::info!("Closing SSH tunnel for endpoint {}", endpoint_id);
+            let _ = entry.child.kill().await;
+        }
+    }
+}
+
+async fn free_local_port(preferred: Option<u16>) -> Result<u16> {
+    if let Some(port) = preferred
+        && let Ok(listener) = tokio::net::TcpListener::bind(("127.0.0.1", port)).await
+    {
+        return Ok(listener.local_addr()?.port());
+    }
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .context("Failed to find a free local port for SSH tunnel")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Skript pro SSH_ASKPASS, heslo ke klíči dostane přes env (nikdy není v argumentech ani na disku).
+/// Leží v privátním runtime adresáři a vytváří se jednou za běh procesu.
+fn askpass_script() -> Result<PathBuf> {
+    let mut guard = ASKPASS_SCRIPT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(path) = guard.as_ref() {
+        return Ok(path.clone());
+    }
+    let path = crate::utils::runtime::private_dir()?.join("askpass.sh");
+    crate::utils::runtime::write_new(
+        &path,
+        b"#!/bin/sh\nprintf '%s\\n' \"$PGX_SSH_PASSPHRASE\"\n",
+        0o700,
+    )
+    .context("Failed to write SSH askpass helper")?;
+    *guard = Some(path.clone());
+    Ok(path)
+}
+
+async fn start_tunnel(
+    endpoint_id: i64,
+    config: &SshTunnelConfig,
+    targets: &[TunnelTarget],
+    preferred_ports: &[u16],
+) -> Result<TunnelEntry> {
+    let mut local_ports = Vec::with_capacity(targets.len());
+    for i in 0..targets.len() {
+        local_ports.push(free_local_port(preferred_ports.get(i).copied()).await?);
+    }
+
+    let mut cmd = Command::new("ssh");
+    cmd.arg("-N");
+    for (local_port, target) in local_ports.iter().zip(targets) {
+        cmd.arg("-L")
+            .arg(format!("127.0.0.1:{}:{}:{}", local_port, target.host, target.port));
+    }
+    cmd.arg("-p")
+        .arg(config.port.to_string())
+        .arg("-o")
+        .arg("ExitOnForwardFailure=yes")
+        .arg("-o")
+        .arg("ServerAliveInterval=30")
+        .arg("-o")
+        .arg("StrictHostKeyChecking=accept-new");
+    if let Some(key) = &config.key_path {
+        cmd.arg("-i").arg(key).arg("-o").arg("IdentitiesOnly=yes");
+    }
+    match &config.passphrase {
+        Some(passphrase) => {
+            cmd.arg("-o").arg("PreferredAuthentications=publickey");
+            cmd.env("SSH_ASKPASS", askpass_script()?)
+                .env("SSH_ASKPASS_REQUIRE", "force")
+                .env("DISPLAY", std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string()))
+                .env("PGX_SSH_PASSPHRASE", passphrase);
+        }
+        None => {
+            cmd.arg("-o").arg("BatchMode=yes");
+        }
+    }
+    // "--" ukončí volby, host začínající "-" se tak nedá podstrčit jako volba ssh
+    cmd.arg("--");
+    match &config.user {
+        Some(user) => cmd.arg(format!("{}@{}", user, config.host)),
+        None => cmd.arg(&config.host),
+    };
+
+    let forwards: Vec<String> = local_ports
+        .iter()
+        .zip(targets)
+        .map(|(local_port, target)| format!("127.0.0.1:{} -> {}:{}", local_port, target.host, target.port))
+        .collect();
+    tracing::info!(
+        "Starting SSH tunnel for endpoint {}: {} via {}:{}",
+        endpoint_id,
+        forwards.join(", "),
+        config.host,
+        config.port
+    );
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to spawn ssh (is OpenSSH client installed?)")?;
+
+    let deadline = Instant::now() + TUNNEL_START_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let mut stderr = String::new();
+            if let Some(mut err) = child.stderr.take() {
+                let _ = err.read_to_string(&mut stderr).await;
+            }
+            anyhow::bail!(
+                "SSH tunnel to {} failed ({}): {}",
+                config.host,
+                status,
+                stderr.trim()
+            );
+        }
+        let mut ready = true;
+        for port in &local_ports {
+            if TcpStream::connect(("127.0.0.1", *port)).await.is_err() {
+                ready = false;
+                break;
+            }
+        }
+        if ready {
+            break;
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill().await;
+            anyhow::bail!("Timed out while opening SSH tunnel to {}", config.host);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // stderr musíme dál číst, jinak by se ssh mohlo zablokovat na plné rouře
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::warn!("ssh tunnel {}: {}", endpoint_id, line);
+            }
+        });
+    }
+
+    Ok(TunnelEntry {
+        child,
+        local_ports,
+        config: config.clone(),
+        targets: targets.to_vec(),
+        started_at: Instant::now(),
+    })
+}
@@ -1,15 +1,18 @@
+mod auth;
+mod cli;
 mod config;
 mod db;
 mod handlers;
 mod templates;
 mod utils;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use axum::routing::{get, put};
 use axum::Router;
 use axum::extract::DefaultBodyLimit;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_http::services::ServeDir;
@@ -40,10 +43,18 @@ struct Args {
     #[arg(long, default_value_t = false)]
     stateless: bool,
 
+    /// Endpoint config file (TOML or YAML) for stateless mode, implies --stateless
+    #[arg(long, env = "CONFIG_FILE")]
+    config: Option<std::path::PathBuf>,
+
     /// Connection name (shown in UI)
     #[arg(long, env = "CONF_NAME")]
     conf_name: Option<String>,
 
+    /// Environment of the connection (dev, test, staging, prod)
+    #[arg(long, env = "CONF_ENVIRONMENT")]
+    conf_environment: Option<String>,
+
     /// Postgres URL (e.g. postgres://host:5432/db)
     #[arg(long, env = "CONF_DB_URL")]
     conf_db_url: Option<String>,
@@ -68,6 +79,46 @@ struct Args {
     #[arg(long, env = "CONF_DB_SEARCH_PATH")]
     conf_db_search_path: Option<String>,
 
+    /// CA certificate for server verification (file path or PEM)
+    #[arg(long, env = "CONF_DB_SSL_ROOT_CERT")]
+    conf_db_ssl_root_cert: Option<String>,
+
+    /// TLS client certificate (file path or PEM)
+    #[arg(long, env = "CONF_DB_SSL_CERT")]
+    conf_db_ssl_cert: Option<String>,
+
+    /// TLS client private key (file path or PEM)
+    #[arg(long, env = "CONF_DB_SSL_KEY")]
+    conf_db_ssl_key: Option<String>,
+
+    /// SSH bastion host for tunneling the connection
+    #[arg(long, env = "CONF_SSH_HOST")]
+    conf_ssh_host: Option<String>,
+
+    /// SSH port (default 22)
+    #[arg(long, env = "CONF_SSH_PORT")]
+    conf_ssh_port: Option<u16>,
+
+    /// SSH user
+    #[arg(long, env = "CONF_SSH_USER")]
+    conf_ssh_user: Option<String>,
+
+    /// SSH private key path (ssh-agent is used when not set)
+    #[arg(long, env = "CONF_SSH_KEY_PATH")]
+    conf_ssh_key_path: Option<String>,
+
+    /// Passphrase for the SSH private key
+    #[arg(long, env = "CONF_SSH_PASSPHRASE")]
+    conf_ssh_passphrase: Option<String>,
+
+    /// Read replica connection strings (separated by ';')
+    #[arg(long, env = "CONF_DB_REPLICA_URLS", value_delimiter = ';')]
+    conf_db_replica_urls: Vec<String>,
+
+    /// Max replica lag in seconds before reads go to the primary (default 30)
+    #[arg(long, env = "CONF_DB_REPLICA_MAX_LAG")]
+    conf_db_replica_max_lag: Option<u32>,
+
     /// Enable Patroni cluster monitoring
     #[arg(long, env = "ENABLE_PATRONI", default_value_t = false)]
     enable_patroni: bool,
@@ -79,78 +130,199 @@ struct Args {
     /// Enable destructive commands in Dev Console (DROP, DELETE, TRUNCATE)
     #[arg(long, env = "ENABLE_DESTRUCTIVE_COMMANDS", default_value_t = false)]
     enable_destructive_commands: bool,
+
+    /// Authentication mode (default: local, or none in --stateless mode)
+    #[arg(long, env = "AUTH_MODE", value_enum)]
+    auth: Option<auth::AuthMode>,
+
+    /// Role for users without an explicit role on a connection
+    #[arg(long, env = "DEFAULT_ROLE", value_enum, default_value = "viewer")]
+    default_role: auth::Role,
+
+    /// Trusted proxy addresses or CIDR ranges for --auth proxy (comma-separated)
+    #[arg(long, env = "TRUSTED_PROXIES", default_value = "127.0.0.1,::1")]
+    trusted_proxies: String,
+
+    /// Header with the authenticated user name (--auth proxy)
+    #[arg(long, env = "PROXY_USER_HEADER", default_value = "X-Forwarded-User")]
+    proxy_user_header: String,
+
+    /// Header with comma-separated groups of the user (--auth proxy)
+    #[arg(long, env = "PROXY_GROUPS_HEADER", default_value = "X-Forwarded-Groups")]
+    proxy_groups_header: String,
+
+    /// Members of this group are administrators (--auth proxy)
+    #[arg(long, env = "PROXY_ADMIN_GROUP")]
+    proxy_admin_group: Option<String>,
+
+    /// Also collect per-connection stats for /metrics in the background (database size, connections, cache hit ratio)
+    #[arg(long, env = "METRICS_DATABASE_STATS", default_value_t = false)]
+    metrics_database_stats: bool,
+
+    /// Make /readyz also run a timed SELECT 1 against every connection
+    #[arg(long, env = "READYZ_ENDPOINTS", default_value_t = false)]
+    readyz_endpoints: bool,
+
+    /// Root directory for dumps, job logs and uploaded imports (one subdirectory per connection)
+    #[arg(long, env = "STORAGE_DIR", default_value = handlers::storage::DEFAULT_STORAGE_DIR)]
+    storage_dir: std::path::PathBuf,
+
+    /// Delete stored artifacts older than this many days
+    #[arg(long, env = "RETENTION_DAYS")]
+    retention_days: Option<u64>,
+
+    /// Keep at most this much data per connection, oldest artifacts are deleted first (e.g. 10G)
+    #[arg(long, env = "RETENTION_SIZE", value_parser = handlers::storage::parse_size)]
+    retention_size: Option<u64>,
+
+    /// Refuse new exports and uploads while the storage uses more than this (e.g. 50G)
+    #[arg(long, env = "STORAGE_QUOTA", value_parser = handlers::storage::parse_size)]
+    storage_quota: Option<u64>,
+
+    /// Master passphrase for the encryption key of stored passwords
+    #[arg(long, env = "MASTER_PASSPHRASE", hide_env_values = true)]
+    master_passphrase: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a new encryption key and re-encrypt all stored secrets
+    RotateKey {
+        /// Protect the new key with this master passphrase (default: keep the current one)
+        #[arg(long, env = "NEW_MASTER_PASSPHRASE", hide_env_values = true)]
+        new_passphrase: Option<String>,
+
+        /// Store the new key as a plain key file without a passphrase
+        #[arg(long, conflicts_with = "new_passphrase")]
+        remove_passphrase: bool,
+    },
+    #[command(flatten)]
+    Cli(cli::Command),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let _ = dotenvy::dotenv();
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
-        .init();
+    let mut args = Args::parse();
+    // Headless příkazy píšou výsledek na stdout, logy proto jdou na stderr
+    if matches!(args.command, Some(Command::Cli(_))) {
+        tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into()))
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+            .init();
+    }
 
-    let args = Args::parse();
+    if let Some(Command::RotateKey { new_passphrase, remove_passphrase }) = &args.command {
+        let new_passphrase = if *remove_passphrase {
+            None
+        } else {
+            new_passphrase.as_deref().or(args.master_passphrase.as_deref())
+        };
+        return rotate_key(args.master_passphrase.as_deref(), new_passphrase).await;
+    }
+    if args.config.is_some() {
+        args.stateless = true;
+    }
 
     let db = if args.stateless {
         None
     } else {
         config::init_directories()?;
-        Some(db::Database::new().await?)
+        let key = config::load_or_create_key(args.master_passphrase.as_deref())?;
+        Some(db::Database::new(key).await?)
     };
+    if let Some(Command::Cli(command)) = args.command.take() {
+        let state = Arc::new(app_state(&args, db, auth::AuthMode::None, None)?);
+        let result = cli::run(&state, command).await;
+        state.ssh_tunnels.close_all().await;
+        utils::runtime::remove_private_dir();
+        return result;
+    }
     let base_path = normalize_base_path(&args.base_path);
-    let stateless_endpoint = if args.stateless {
-        let url = args.conf_db_url.clone().ok_or_else(|| anyhow::anyhow!("--conf-db-url is required in --stateless mode"))?;
-        let name = args.conf_name.clone().unwrap_or_else(|| url.clone());
-        Some(db::models::Endpoint {
-            id: 0,
-            name,
-            url,
-            insecure: args.conf_db_insecure,
-            username: args.conf_db_username.clone(),
-            password_encrypted: None,
-            ssl_mode: args.conf_db_ssl_mode.clone(),
-            search_path: args.conf_db_search_path.clone(),
-            enable_blueprint: false,
-            created_at: String::new(),
-            updated_at: String::new(),
-        })
-    } else {
-        None
+    let auth_mode = match (args.auth, args.stateless) {
+        (Some(auth::AuthMode::Local), true) => {
+            anyhow::bail!("--auth local requires local storage and cannot be used with --stateless")
+        }
+        (Some(mode), _) => mode,
+        (None, true) => auth::AuthMode::None,
+        (None, false) => auth::AuthMode::Local,
     };
-
-    let patroni_urls = if args.enable_patroni {
-        args.patroni_urls.as_ref().map(|urls| {
-            urls.split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect()
-        })
+    let proxy_auth = if auth_mode == auth::AuthMode::Proxy {
+        let proxy = auth::ProxyAuth::new(
+            &args.trusted_proxies,
+            &args.proxy_user_header,
+            &args.proxy_groups_header,
+            args.proxy_admin_group.clone(),
+        )?;
+        if proxy.admin_group.is_none() {
+            tracing::warn!("--proxy-admin-group is not set, no proxy user will be an administrator");
+        }
+        Some(proxy)
     } else {
         None
     };
+    if auth_mode == auth::AuthMode::None {
+        tracing::warn!("Authentication is disabled, anyone who can reach the HTTP port has full access");
+    }
+    let state = Arc::new(app_state(&args, db, auth_mode, proxy_auth)?);
 
-    let state = Arc::new(handlers::AppState {
-        db,
-        base_path: base_path.clone(),
-        stateless_endpoint,
-        stateless_password: if args.stateless { args.conf_db_password.clone() } else { None },
-        active_override: Arc::new(std::sync::RwLock::new(None)),
-        active_override_password: Arc::new(std::sync::RwLock::new(None)),
-        databases_menu: Arc::new(std::sync::RwLock::new(HashMap::new())),
-        schemas_cache: Arc::new(RwLock::new(HashMap::new())),
-        tables_cache: Arc::new(RwLock::new(HashMap::new())),
-        indices_cache: Arc::new(RwLock::new(HashMap::new())),
-        export_jobs: Arc::new(RwLock::new(HashMap::new())),
-        patroni_urls,
-        enable_destructive_commands: args.enable_destructive_commands,
-    });
+    auth::reload_endpoint_roles(&state).await?;
+    if let Some(db) = &state.db {
+        let interrupted = db.mark_interrupted_jobs().await?;
+        if interrupted > 0 {
+            tracing::warn!("Marked {} jobs left running by the previous run as interrupted", interrupted);
+        }
+    }
+
+    state.pg_pools.spawn_eviction_task();
+    handlers::storage::spawn_cleanup_task(state.clone());
+    handlers::schedules::spawn_scheduler(state.clone());
+    if state.metrics_database_stats {
+        handlers::metrics::spawn_database_stats_task(state.clone());
+    }
 
     let router = Router::new()
+        .route("/login", get(handlers::auth::login_page).post(handlers::auth::login))
+        .route("/setup", axum::routing::post(handlers::auth::setup))
+        .route("/logout", axum::routing::post(handlers::auth::logout))
+        .route("/users", get(handlers::auth::users_page).post(handlers::auth::create_user))
+        .route("/users/{id}/password", axum::routing::post(handlers::auth::set_user_password))
+        .route("/users/{id}/delete", axum::routing::post(handlers::auth::delete_user))
+        .route("/users/{id}/groups", axum::routing::post(handlers::auth::set_user_groups))
+        .route("/users/roles", axum::routing::post(handlers::auth::set_endpoint_role))
+        .route("/users/roles/{id}/delete", axum::routing::post(handlers::auth::delete_endpoint_role))
+        .route("/tokens", get(handlers::tokens::tokens_page).post(handlers::tokens::create_token))
+        .route("/tokens/{id}/revoke", axum::routing::post(handlers::tokens::revoke_token))
+        .route("/jobs", get(handlers::jobs::jobs_page))
+        .route("/jobs/{job_id}/rerun", axum::routing::post(handlers::jobs::rerun))
+        .route("/jobs/{job_id}/cancel", axum::routing::post(handlers::jobs::cancel_from_page))
+        .route("/schedules", get(handlers::schedules::schedules_page).post(handlers::schedules::create_schedule))
+        .route("/schedules/{id}/run", axum::routing::post(handlers::schedules::run_now))
+        .route("/schedules/{id}/toggle", axum::routing::post(handlers::schedules::toggle_schedule))
+        .route("/schedules/{id}/delete", axum::routing::post(handlers::schedules::delete_schedule))
+        .route("/storage", get(handlers::storage::storage_page))
+        .route("/storage/delete", axum::routing::post(handlers::storage::delete_artifact))
+        .route("/targets", get(handlers::targets::targets_page).post(handlers::targets::create_target))
+        .route("/targets/{id}/test", axum::routing::post(handlers::targets::test_target))
+        .route("/targets/{id}/delete", axum::routing::post(handlers::targets::delete_target))
+        .route("/audit", get(handlers::audit::audit_page))
+        .route("/audit/export.csv", get(handlers::audit::export_csv))
         .route("/", get(handlers::dashboard::dashboard))
         .route("/analyze/{schema}/{table}", axum::routing::post(handlers::dashboard::analyze_table))
         .route("/databases", get(handlers::databases::list_databases))
         .route("/databases/activate", axum::routing::post(handlers::databases::activate_database))
         .route("/databases/reset", axum::routing::post(handlers::databases::reset_database_override))
         .route("/endpoints", get(handlers::endpoints::list_endpoints).post(handlers::endpoints::create_endpoint))
+        .route("/endpoints/export", axum::routing::post(handlers::catalog::export))
+        .route("/endpoints/import", get(handlers::catalog::import_page).post(handlers::catalog::import_apply))
+        .route("/endpoints/import/preview", axum::routing::post(handlers::catalog::import_preview))
         .route("/endpoints/{id}", put(handlers::endpoints::update_endpoint).delete(handlers::endpoints::delete_endpoint))
         .route("/endpoints/{id}/select", axum::routing::post(handlers::endpoints::select_endpoint))
         .route("/endpoints/{id}/test", axum::routing::post(handlers::endpoints::test_endpoint))
@@ -207,12 +379,16 @@ async fn main() -> Result<()> {
         .route("/maintenance/export/{job_id}/logs", get(handlers::export::stream_logs))
         .route("/maintenance/export/{job_id}/download", get(handlers::export::download_export))
         .route("/maintenance/export/{job_id}/download-log", get(handlers::export::download_log))
+        .route("/maintenance/export/{job_id}/cancel", axum::routing::post(handlers::jobs::cancel))
         .route("/import", get(handlers::export::import_wizard))
         .route("/maintenance/import/upload", axum::routing::post(handlers::export::upload_import_file))
+        .route("/maintenance/import/objects", get(handlers::export::list_import_objects))
+        .route("/maintenance/import/fetch", axum::routing::post(handlers::export::fetch_import_object))
         .route("/maintenance/import", axum::routing::post(handlers::export::start_import))
         .route("/maintenance/import/{job_id}/status", get(handlers::export::get_job_status))
         .route("/maintenance/import/{job_id}/logs", get(handlers::export::stream_logs))
         .route("/maintenance/import/{job_id}/download-log", get(handlers::export::download_log))
+        .route("/maintenance/import/{job_id}/cancel", axum::routing::post(handlers::jobs::cancel))
         .route(
             "/tables/{schema}/{table}/modal",
             get(handlers::tables::table_modal),
@@ -222,6 +398,7 @@ async fn main() -> Result<()> {
         .route("/dev/execute", axum::routing::post(handlers::console::execute_query))
         .route("/dev/check", axum::routing::post(handlers::console::check_destructive))
         .route("/dev/{job_id}/logs", get(handlers::console::stream_console_logs))
+        .route("/dev/{job_id}/cancel", axum::routing::post(handlers::jobs::cancel))
         .route("/dev/history", get(handlers::console::get_history))
         .route("/dev/history", axum::routing::post(handlers::console::save_history))
         .route("/dev/history", axum::routing::delete(handlers::console::clear_history))
@@ -230,18 +407,50 @@ async fn main() -> Result<()> {
         .route("/blueprint", get(handlers::blueprint::blueprint_wizard))
         .route("/blueprint/preview", axum::routing::post(handlers::blueprint::preview_blueprint))
         .route("/blueprint/execute", axum::routing::post(handlers::blueprint::execute_blueprint))
+        .route("/api/v1/endpoints", get(handlers::api::list_endpoints))
+        .route("/api/v1/endpoints/{endpoint}", get(handlers::api::get_endpoint))
+        .route("/api/v1/endpoints/{endpoint}/databases", get(handlers::api::list_databases))
+        .route("/api/v1/endpoints/{endpoint}/schemas", get(handlers::api::list_schemas))
+        .route("/api/v1/endpoints/{endpoint}/tables", get(handlers::api::list_tables))
+        .route("/api/v1/endpoints/{endpoint}/indices", get(handlers::api::list_indices))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/columns", get(handlers::api::table_columns))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/indexes", get(handlers::api::table_indexes))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/triggers", get(handlers::api::table_triggers))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/partitions", get(handlers::api::table_partitions))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/relationships", get(handlers::api::table_relationships))
+        .route("/api/v1/endpoints/{endpoint}/tables/{schema}/{table}/data", get(handlers::api::table_data))
+        .route("/api/v1/endpoints/{endpoint}/tuning", get(handlers::api::tuning))
+        .route("/api/v1/endpoints/{endpoint}/patroni", get(handlers::api::patroni))
         .nest_service("/static", axum::routing::get_service(ServeDir::new("static")))
+        .layer(axum::middleware::from_fn(handlers::replica::track_served_by))
+        .layer(axum::middleware::from_fn(handlers::db_session::scope_db_session))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth::require_auth))
         .layer(DefaultBodyLimit::max(2 * 1024 * 1024 * 1024)) // 2GB limit
         .with_state(state.clone());
 
-    // Health check endpoint - always available at root, not affected by base_path
+    // Health/readiness checky a metriky - vždy v rootu, bez base_path;
+    // rozpis readiness a metriky vyžadují přihlášení (metriky i API token se scope read)
     let app = Router::new()
         .route("/healthz", get(handlers::health::healthz))
+        .route("/readyz", get(handlers::health::readyz).with_state(state.clone()))
+        .route(
+            "/readyz/details",
+            get(handlers::health::readyz_details)
+                .with_state(state.clone())
+                .layer(axum::middleware::from_fn_with_state(state.clone(), auth::require_auth)),
+        )
+        .route(
+            "/metrics",
+            get(handlers::metrics::metrics)
+                .with_state(state.clone())
+                .layer(axum::middleware::from_fn_with_state(state.clone(), auth::require_auth)),
+        )
         .merge(if base_path == "/" {
             router
         } else {
             Router::new().nest(&base_path, router)
-        });
+        })
+        .layer(axum::middleware::from_fn_with_state(state.clone(), handlers::metrics::track_requests));
 
     let addr = format!("{}:{}", args.host, args.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -255,8 +464,183 @@ async fn main() -> Result<()> {
         }
     }
 
-    axum::serve(listener, app).await?;
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>());
+    tokio::select! {
+        result = server => result?,
+        _ = shutdown_signal() => {},
+    }
+
+    // ssh procesy by jinak přežily konec aplikace
+    state.ssh_tunnels.close_all().await;
+    utils::runtime::remove_private_dir();
+
+    Ok(())
+}
+
+/// Sdílený stav pro server i headless příkazy (endpointy z SQLite nebo stateless konfigurace)
+fn app_state(
+    args: &Args,
+    db: Option<db::Database>,
+    auth_mode: auth::AuthMode,
+    proxy_auth: Option<auth::ProxyAuth>,
+) -> Result<handlers::AppState> {
+    let stateless_endpoints = if let Some(path) = &args.config {
+        let endpoints = config::stateless::load(path)?.into_endpoints()?;
+        tracing::info!("Loaded {} endpoints from {}", endpoints.len(), path.display());
+        endpoints
+    } else if args.stateless {
+        let url = args.conf_db_url.clone().ok_or_else(|| anyhow::anyhow!("--conf-db-url is required in --stateless mode"))?;
+        let name = args.conf_name.clone().unwrap_or_else(|| url.clone());
+        if let Some(env) = &args.conf_environment
+            && !db::models::ENVIRONMENTS.contains(&env.as_str())
+        {
+            anyhow::bail!("--conf-environment must be one of {}", db::models::ENVIRONMENTS.join(", "));
+        }
+        let endpoint = db::models::Endpoint {
+            id: 0,
+            name,
+            url,
+            insecure: args.conf_db_insecure,
+            username: args.conf_db_username.clone(),
+            password_encrypted: None,
+            ssl_mode: args.conf_db_ssl_mode.clone(),
+            search_path: args.conf_db_search_path.clone(),
+            enable_blueprint: false,
+            ssh_host: args.conf_ssh_host.clone(),
+            ssh_port: args.conf_ssh_port.map(i64::from),
+            ssh_user: args.conf_ssh_user.clone(),
+            ssh_key_path: args.conf_ssh_key_path.clone(),
+            ssh_passphrase_encrypted: None,
+            ssl_root_cert: args.conf_db_ssl_root_cert.clone(),
+            ssl_cert: args.conf_db_ssl_cert.clone(),
+            ssl_key_encrypted: None,
+            group_name: None,
+            tags: None,
+            environment: args.conf_environment.clone(),
+            replica_urls: config::stateless::join_replicas(&args.conf_db_replica_urls, "--conf-db-replica-urls")?,
+            replica_max_lag: args.conf_db_replica_max_lag.map(i64::from),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        vec![config::stateless::StatelessEndpoint {
+            endpoint,
+            password: args.conf_db_password.clone(),
+            ssh_passphrase: args.conf_ssh_passphrase.clone(),
+            ssl_key: args.conf_db_ssl_key.clone(),
+            patroni_urls: Vec::new(),
+        }]
+    } else {
+        Vec::new()
+    };
+
+    let patroni_urls = if args.enable_patroni {
+        args.patroni_urls.as_ref().map(|urls| {
+            urls.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+    } else {
+        None
+    };
+
+    Ok(handlers::AppState {
+        db,
+        base_path: normalize_base_path(&args.base_path),
+        stateless_endpoints,
+        db_sessions: handlers::db_session::DbSessionStore::new(),
+        databases_menu: Arc::new(std::sync::RwLock::new(HashMap::new())),
+        schemas_cache: Arc::new(RwLock::new(HashMap::new())),
+        tables_cache: Arc::new(RwLock::new(HashMap::new())),
+        indices_cache: Arc::new(RwLock::new(HashMap::new())),
+        export_jobs: Arc::new(RwLock::new(HashMap::new())),
+        pg_pools: handlers::pool::PgPoolRegistry::new(),
+        ssh_tunnels: handlers::tunnel::SshTunnelRegistry::new(),
+        replicas: handlers::replica::ReplicaRegistry::new(),
+        patroni_urls,
+        enable_destructive_commands: args.enable_destructive_commands,
+        auth_mode,
+        proxy_auth,
+        default_role: args.default_role,
+        endpoint_roles: Arc::new(std::sync::RwLock::new(Vec::new())),
+        metrics: handlers::metrics::Metrics::new(),
+        metrics_database_stats: args.metrics_database_stats,
+        readyz_endpoints: args.readyz_endpoints,
+        readyz_cache: handlers::health::ReadyCache::default(),
+        storage: handlers::storage::Storage {
+            root: args.storage_dir.clone(),
+            max_age: args.retention_days.map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60)),
+            max_endpoint_size: args.retention_size,
+            quota: args.storage_quota,
+        },
+    })
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutting down");
+}
+
+/// Nový klíč pro db.key: přešifruje tajné údaje v jedné transakci, starý soubor zůstane jako záloha
+async fn rotate_key(passphrase: Option<&str>, new_passphrase: Option<&str>) -> Result<()> {
+    let key_path = config::get_key_path()?;
+    if !key_path.exists() {
+        anyhow::bail!("Encryption key {} does not exist, nothing to rotate", key_path.display());
+    }
+    let old_key = config::load_key(passphrase)?;
+    let db = db::Database::new(old_key).await?;
+    let (new_key, content) = config::generate_key(new_passphrase)?;
+
+    let backup_path = key_path.with_file_name(format!(
+        "db.key.{}.bak",
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    if backup_path.exists() {
+        anyhow::bail!("Key backup {} already exists", backup_path.display());
+    }
+    std::fs::copy(&key_path, &backup_path)
+        .with_context(|| format!("Failed to back up encryption key to {}", backup_path.display()))?;
+    let new_path = key_path.with_file_name("db.key.new");
+
+    // Nový klíč zapíšeme před commitem; po commitu už jen přejmenujeme
+    let count = db
+        .rotate_encryption_key(&new_key, || config::write_key_file(&new_path, &content))
+        .await
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&new_path);
+        })?;
+    std::fs::rename(&new_path, &key_path).with_context(|| {
+        format!(
+            "Secrets were re-encrypted but {} could not be moved to {}",
+            new_path.display(),
+            key_path.display()
+        )
+    })?;
 
+    tracing::info!(
+        "Re-encrypted {} secrets with a new key{}, old key saved to {}",
+        count,
+        if new_passphrase.is_some() { " protected by a master passphrase" } else { "" },
+        backup_path.display()
+    );
     Ok(())
 }
 
@@ -9,6 +9,18 @@ pub struct AppContext {
     pub in_memory_active: bool,
     pub show_patroni: bool,
     pub show_blueprint: bool,
+    pub can_operate: bool,
+    pub can_admin: bool,
+    pub current_user: String,
+    pub is_admin: bool,
+    pub auth_enabled: bool,
+    pub local_auth: bool,
+    pub show_users: bool,
+    pub show_audit: bool,
+    /// Prostředí aktivního endpointu (prázdné = bez banneru)
+    pub environment: String,
+    /// Uzel (primary/replika), který obsloužil čtecí dotazy stránky
+    pub served_by: String,
 }
 
 #[derive(Template)]
@@ -34,6 +46,9 @@ pub struct DashboardTemplate {
     pub databases: Vec<DatabaseInfo>,
     pub connection_states: Vec<ConnectionState>,
     pub conninfo: ConnectionInfo,
+    pub pg_pools: Vec<crate::handlers::pool::PoolStats>,
+    /// Plány exportů, jejichž poslední běh selhal
+    pub failed_schedules: Vec<crate::db::models::ExportSchedule>,
 }
 
 #[derive(Clone)]
@@ -176,7 +191,7 @@ pub struct TableDataTemplate {
     pub has_next: bool,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct FkMeta {
     pub schema: String,
     pub table: String,
@@ -283,15 +298,78 @@ pub struct SchemasTableTemplate {
 #[template(path = "endpoints.html")]
 pub struct EndpointsTemplate {
     pub ctx: AppContext,
-    pub endpoints: Vec<crate::db::models::Endpoint>,
+    pub groups: Vec<EndpointGroup>,
     pub active_id: i64,
+    pub can_manage: bool,
+    pub filtered: bool,
+    pub group_names: Vec<String>,
+    pub tag_names: Vec<String>,
+    pub environments: Vec<String>,
+    pub f_q: String,
+    pub f_group: String,
+    pub f_tag: String,
+    pub f_env: String,
 }
 
 #[derive(Template)]
 #[template(path = "endpoints_list.html")]
 pub struct EndpointsListTemplate {
-    pub endpoints: Vec<crate::db::models::Endpoint>,
+    pub groups: Vec<EndpointGroup>,
     pub active_id: i64,
+    pub can_manage: bool,
+    pub filtered: bool,
+}
+
+#[derive(Template)]
+#[template(path = "catalog_import.html")]
+pub struct CatalogImportTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub source: String,
+    pub content: String,
+    pub pgpass: String,
+    pub passphrase: String,
+    pub strategy: String,
+    pub use_local_files: bool,
+    pub preview: Vec<CatalogPreviewRow>,
+    pub can_apply: bool,
+    pub error: String,
+    pub message: String,
+    pub service_file: String,
+    pub pgpass_file: String,
+}
+
+#[derive(Clone)]
+pub struct CatalogPreviewRow {
+    pub name: String,
+    pub url: String,
+    pub username: String,
+    pub environment: String,
+    pub group_name: String,
+    pub has_password: bool,
+    pub has_ssh: bool,
+    pub has_client_cert: bool,
+    /// create | update | skip | invalid
+    pub action: String,
+    pub note: String,
+}
+
+/// Skupina endpointů v seznamu (prázdný název = bez skupiny)
+#[derive(Clone)]
+pub struct EndpointGroup {
+    pub name: String,
+    pub endpoints: Vec<crate::db::models::Endpoint>,
+}
+
+/// Barva (Tabler) pro prostředí endpointu
+pub fn environment_color(environment: &str) -> &'static str {
+    match environment {
+        "prod" => "red",
+        "staging" => "orange",
+        "test" => "azure",
+        "dev" => "green",
+        _ => "secondary",
+    }
 }
 
 #[derive(Clone)]
@@ -411,12 +489,18 @@ pub struct TuningTemplate {
 #[template(path = "export_wizard.html")]
 pub struct ExportWizardTemplate {
     pub ctx: AppContext,
+    /// Adresář dumpů aktivního endpointu (náhled příkazu)
+    pub export_dir: String,
+    /// Storage targety, kam lze dump po dokončení nahrát
+    pub targets: Vec<crate::handlers::targets::TargetOption>,
 }
 
 #[derive(Template)]
 #[template(path = "import_wizard.html")]
 pub struct ImportWizardTemplate {
     pub ctx: AppContext,
+    /// Storage targety, ze kterých lze dump stáhnout
+    pub targets: Vec<crate::handlers::targets::TargetOption>,
 }
 
 #[derive(Template)]
@@ -432,3 +516,110 @@ pub struct BlueprintWizardTemplate {
     pub ctx: AppContext,
     pub title: String,
 }
+
+#[derive(Template)]
+#[template(path = "login.html")]
+pub struct LoginTemplate {
+    pub base_path: String,
+    pub version: String,
+    pub setup_required: bool,
+    pub error: Option<String>,
+    pub next: String,
+    pub username: String,
+}
+
+#[derive(Template)]
+#[template(path = "users.html")]
+pub struct UsersTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub users: Vec<crate::db::models::User>,
+    pub current_user_id: i64,
+    pub error: Option<String>,
+    pub endpoints: Vec<crate::db::models::Endpoint>,
+    pub endpoint_roles: Vec<crate::db::models::EndpointRole>,
+    pub default_role: String,
+}
+
+#[derive(Template)]
+#[template(path = "tokens.html")]
+pub struct TokensTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub tokens: Vec<crate::db::models::ApiToken>,
+    /// Správce vidí všechny tokeny a může vytvářet service tokeny
+    pub can_manage_all: bool,
+    /// Právě vytvořený token, zobrazí se jen jednou
+    pub created_token: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "jobs.html")]
+pub struct JobsTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub jobs: Vec<crate::handlers::jobs::JobRow>,
+    pub kinds: Vec<String>,
+    pub statuses: Vec<String>,
+    pub f_kind: String,
+    pub f_status: String,
+    pub limit_reached: bool,
+}
+
+#[derive(Template)]
+#[template(path = "schedules.html")]
+pub struct SchedulesTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub schedules: Vec<crate::handlers::schedules::ScheduleRow>,
+    /// Nový plán se zakládá pro aktivní endpoint
+    pub active_endpoint: Option<String>,
+    pub can_create: bool,
+    pub targets: Vec<crate::handlers::targets::TargetOption>,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "storage.html")]
+pub struct StorageTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub root: String,
+    pub used: String,
+    pub quota: Option<String>,
+    pub quota_exceeded: bool,
+    pub max_age_days: Option<u64>,
+    pub max_endpoint_size: Option<String>,
+    pub artifacts: Vec<crate::handlers::storage::ArtifactRow>,
+}
+
+#[derive(Template)]
+#[template(path = "targets.html")]
+pub struct TargetsTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub targets: Vec<crate::db::models::StorageTarget>,
+    pub message: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "audit.html")]
+pub struct AuditTemplate {
+    pub ctx: AppContext,
+    pub title: String,
+    pub entries: Vec<crate::db::models::AuditEntry>,
+    pub endpoints: Vec<crate::db::models::Endpoint>,
+    pub usernames: Vec<String>,
+    pub actions: Vec<String>,
+    pub f_user: String,
+    pub f_endpoint_id: String,
+    pub f_action: String,
+    pub f_status: String,
+    pub f_from: String,
+    pub f_to: String,
+    pub f_q: String,
+    pub export_query: String,
+    pub limit_reached: bool,
+}
@@ -134,21 +134,27 @@ pub fn filter_names(names: &[String], expression: &str) -> Vec<String> {
 
     names
         .iter()
-        .filter(|name| {
-            // Musí matchovat alespoň jeden include pattern
-            let matches_include = includes.iter().any(|pattern| matches_pattern(name, pattern));
-            if !matches_include {
-                return false;
-            }
-
-            // Nesmí matchovat žádný exclude pattern
-            let matches_exclude = excludes.iter().any(|pattern| matches_pattern(name, pattern));
-            !matches_exclude
-        })
+        .filter(|name| matches_expression(name, &includes, &excludes))
         .cloned()
         .collect()
 }
 
+/// Testuje název proti rozparsované pattern expression.
+/// Musí matchovat alespoň jeden include; exclude bez wildcardu vyřadí i názvy,
+/// které ho jen obsahují (`-tmp` vyřadí `user_tmp`).
+pub fn matches_expression(name: &str, includes: &[String], excludes: &[String]) -> bool {
+    if !includes.iter().any(|pattern| matches_pattern(name, pattern)) {
+        return false;
+    }
+    !excludes.iter().any(|pattern| {
+        if pattern.contains('*') {
+            matches_pattern(name, pattern)
+        } else {
+            name.contains(pattern.as_str())
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
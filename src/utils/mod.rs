@@ -1,3 +1,5 @@
 pub mod browser;
 pub mod filter;
 pub mod format;
+pub mod runtime;
+pub mod s3;
@@ -0,0 +1,93 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+
+/// Privátní adresář procesu pro citlivé runtime soubory (askpass skript, TLS klíče)
+static PRIVATE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Vrátí (a při prvním volání vytvoří) adresář s náhodným jménem a právy 0700.
+/// Adresář se nikdy nepřebírá, takže ho nemůže předem připravit jiný uživatel.
+pub fn private_dir() -> Result<PathBuf> {
+    let mut guard = PRIVATE_DIR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = guard.as_ref() {
+        return Ok(dir.clone());
+    }
+    let dir = std::env::temp_dir().join(format!("postgres-explorer-{}", uuid::Uuid::new_v4().simple()));
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(&dir)
+        .with_context(|| format!("Failed to create runtime directory {}", dir.display()))?;
+    *guard = Some(dir.clone());
+    Ok(dir)
+}
+
+/// Vytvoří adresář (i s rodiči) uvnitř privátního adresáře, nové adresáře dostanou 0700
+pub fn create_private_dir_all(dir: &Path) -> Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(dir)
+        .with_context(|| format!("Failed to create runtime directory {}", dir.display()))
+}
+
+/// Zapíše nový soubor s daným módem; existující soubor se nepřepisuje
+pub fn write_new(path: &Path, content: &[u8], mode: u32) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Smaže privátní adresář (při ukončení aplikace)
+pub fn remove_private_dir() {
+    if let Some(dir) = PRIVATE_DIR.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_files() {
+        let dir = private_dir().unwrap();
+        assert_eq!(private_dir().unwrap(), dir);
+        let path = dir.join("test-write-new");
+        write_new(&path, b"secret", 0o600).unwrap();
+        assert!(write_new(&path, b"other", 0o600).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"secret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&path), 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -14,6 +14,8 @@
     // Explicitně nastav výchozí hodnoty pro nové formuláře
     form.querySelector('[name="ssl_mode"]').value = '';
     form.querySelector('[name="search_path"]').value = '';
+    form.querySelector('[name="environment"]').value = '';
+    form.querySelector('[name="ssl_key"]').placeholder = 'File path or paste PEM';
     title.textContent = 'Add Postgres Connection';
     submitBtn.innerHTML = '<i class="ti ti-check"></i> Save Connection';
   }
@@ -38,6 +40,24 @@
     form.querySelector('[name="ssl_mode"]').value = button.dataset.endpointSslMode || '';
     form.querySelector('[name="search_path"]').value = button.dataset.endpointSearchPath || '';
     form.querySelector('[name="enable_blueprint"]').checked = button.dataset.endpointEnableBlueprint === 'true';
+    form.querySelector('[name="ssh_host"]').value = button.dataset.endpointSshHost || '';
+    form.querySelector('[name="ssh_port"]').value = button.dataset.endpointSshPort || '';
+    form.querySelector('[name="ssh_user"]').value = button.dataset.endpointSshUser || '';
+    form.querySelector('[name="ssh_key_path"]').value = button.dataset.endpointSshKeyPath || '';
+    form.querySelector('[name="ssh_passphrase"]').value = '';
+    form.querySelector('[name="clear_ssh_passphrase"]').checked = false;
+    form.querySelector('[name="ssl_root_cert"]').value = button.dataset.endpointSslRootCert || '';
+    form.querySelector('[name="ssl_cert"]').value = button.dataset.endpointSslCert || '';
+    form.querySelector('[name="ssl_key"]').value = '';
+    form.querySelector('[name="ssl_key"]').placeholder = button.dataset.endpointSslKeySet === 'true'
+      ? 'Stored - leave blank to keep current'
+      : 'File path or paste PEM';
+    form.querySelector('[name="clear_ssl_key"]').checked = false;
+    form.querySelector('[name="group_name"]').value = button.dataset.endpointGroupName || '';
+    form.querySelector('[name="tags"]').value = button.dataset.endpointTags || '';
+    form.querySelector('[name="environment"]').value = button.dataset.endpointEnvironment || '';
+    form.querySelector('[name="replica_urls"]').value = button.dataset.endpointReplicaUrls || '';
+    form.querySelector('[name="replica_max_lag"]').value = button.dataset.endpointReplicaMaxLag || '';
     title.textContent = 'Edit Postgres Connection';
     submitBtn.innerHTML = '<i class="ti ti-check"></i> Update Connection';
 
@@ -116,9 +136,10 @@
   function bindEndpointEvents() {
     function refreshEndpointsList() {
       const base = window.__BASE_PATH__ || '';
-      // Add cache-busting timestamp to force fresh data
-      const cacheBuster = '?_=' + Date.now();
-      return fetch(`${base}/endpoints${cacheBuster}`, {
+      // Add cache-busting timestamp to force fresh data, keep the active filter
+      const params = new URLSearchParams(window.location.search);
+      params.set('_', Date.now());
+      return fetch(`${base}/endpoints?${params.toString()}`, {
         method: 'GET',
         headers: { 'Cache-Control': 'no-cache' }
       })
@@ -167,8 +188,36 @@
     if (endpointModal && !endpointModal.dataset.bound) {
       endpointModal.dataset.bound = 'true';
       endpointModal.addEventListener('hidden.bs.modal', resetEndpointForm);
+
+      // Nahraný certifikát/klíč se vloží jako PEM do textarea
+      endpointModal.querySelectorAll('input[data-pem-target]').forEach(input => {
+        input.addEventListener('change', function () {
+          const file = input.files && input.files[0];
+          if (!file) return;
+          file.text().then(text => {
+            const target = endpointModal.querySelector(`[name="${input.dataset.pemTarget}"]`);
+            if (target) target.value = text.trim();
+            input.value = '';
+          });
+        });
+      });
     }
 
+    // Nahraný soubor (katalog, pg_service.conf, .pgpass) se vloží do textarea
+    document.querySelectorAll('input[data-file-target]').forEach(input => {
+      if (input.dataset.bound) return;
+      input.dataset.bound = 'true';
+      input.addEventListener('change', function () {
+        const file = input.files && input.files[0];
+        if (!file) return;
+        file.text().then(text => {
+          const target = input.form && input.form.querySelector(`[name="${input.dataset.fileTarget}"]`);
+          if (target) target.value = text;
+          input.value = '';
+        });
+      });
+    });
+
     if (!document.body.dataset.endpointsAfterSwapBound) {
       document.body.dataset.endpointsAfterSwapBound = 'true';
       document.body.addEventListener('htmx:afterSwap', function (evt) {
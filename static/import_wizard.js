@@ -35,12 +35,16 @@
     if (uploadProgress) uploadProgress.style.display = 'none';
     const uploadSuccess = getEl('upload-success');
     if (uploadSuccess) uploadSuccess.style.display = 'none';
+    const objectList = getEl('object-list');
+    if (objectList) objectList.innerHTML = '';
 
     const errorAlerts = getEl('upload-success')?.parentElement?.querySelectorAll('.alert.alert-danger') || [];
     errorAlerts.forEach((el) => el.remove());
 
     const importStatus = getEl('import-status');
     if (importStatus) importStatus.style.display = 'none';
+    const cancelBtn = getEl('cancel-import');
+    if (cancelBtn) cancelBtn.style.display = '';
     const terminal = getEl('terminal-output-import');
     if (terminal) terminal.innerHTML = '';
     const jobStatus = getEl('job-status-import');
@@ -73,25 +77,90 @@
         const text = await response.text();
         throw new Error(`HTTP ${response.status}: ${text}`);
       }
-      const data = await response.json();
-      uploadedFilePath = data.file_path;
-      uploadedFormat = data.format;
-      getEl('upload-progress').style.display = 'none';
-      getEl('upload-success').style.display = 'block';
-      getEl('uploaded-filename').textContent = file.name;
-      getEl('uploaded-size').textContent = (data.file_size / 1024 / 1024).toFixed(2) + ' MB (' + data.format + ')';
-      const btnNext = getEl('btn-next-import');
-      if (btnNext) {
-        btnNext.disabled = false;
-        btnNext.removeAttribute('disabled');
+      fileReady(await response.json(), file.name);
+    } catch (err) {
+      uploadFailed(err);
+    }
+  }
+
+  function uploadFailed(err) {
+    getEl('upload-progress').style.display = 'none';
+    const errorDiv = document.createElement('div');
+    errorDiv.className = 'alert alert-danger mt-3';
+    errorDiv.innerHTML = '<i class="ti ti-alert-circle me-2"></i><strong>Upload failed:</strong> ' + err.message;
+    getEl('upload-success').parentElement.appendChild(errorDiv);
+    console.error('Upload error:', err);
+  }
+
+  function fileReady(data, name) {
+    uploadedFilePath = data.file_path;
+    uploadedFormat = data.format;
+    getEl('upload-progress').style.display = 'none';
+    getEl('upload-success').style.display = 'block';
+    getEl('uploaded-filename').textContent = name;
+    getEl('uploaded-size').textContent = (data.file_size / 1024 / 1024).toFixed(2) + ' MB (' + data.format + ')';
+    const btnNext = getEl('btn-next-import');
+    if (btnNext) {
+      btnNext.disabled = false;
+      btnNext.removeAttribute('disabled');
+    }
+  }
+
+  async function listObjects() {
+    const list = getEl('object-list');
+    const params = new URLSearchParams({ target_id: getEl('object-target').value, prefix: getEl('object-prefix').value });
+    list.innerHTML = '<div class="text-muted">Loading...</div>';
+    try {
+      const response = await fetch(`${basePath}/maintenance/import/objects?${params}`);
+      if (!response.ok) {
+        throw new Error(`HTTP ${response.status}: ${await response.text()}`);
       }
+      const objects = await response.json();
+      if (objects.length === 0) {
+        list.innerHTML = '<div class="text-muted">No objects</div>';
+        return;
+      }
+      const table = document.createElement('table');
+      table.className = 'table table-sm table-vcenter';
+      objects.forEach((object) => {
+        const row = table.insertRow();
+        row.insertCell().innerHTML = '<code></code>';
+        row.cells[0].firstChild.textContent = object.key;
+        row.insertCell().textContent = (object.size / 1024 / 1024).toFixed(2) + ' MB';
+        row.insertCell().textContent = object.last_modified;
+        const button = document.createElement('button');
+        button.type = 'button';
+        button.className = 'btn btn-sm btn-outline-primary';
+        button.textContent = 'Use';
+        button.addEventListener('click', () => fetchObject(object.key));
+        row.insertCell().appendChild(button);
+      });
+      list.replaceChildren(table);
     } catch (err) {
-      getEl('upload-progress').style.display = 'none';
+      list.innerHTML = '';
       const errorDiv = document.createElement('div');
-      errorDiv.className = 'alert alert-danger mt-3';
-      errorDiv.innerHTML = '<i class="ti ti-alert-circle me-2"></i><strong>Upload failed:</strong> ' + err.message;
-      getEl('upload-success').parentElement.appendChild(errorDiv);
-      console.error('Upload error:', err);
+      errorDiv.className = 'alert alert-danger';
+      errorDiv.textContent = err.message;
+      list.appendChild(errorDiv);
+    }
+  }
+
+  async function fetchObject(key) {
+    if (!key) return;
+    getEl('upload-progress').style.display = 'block';
+    getEl('upload-success').style.display = 'none';
+    try {
+      const response = await fetch(`${basePath}/maintenance/import/fetch`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ target_id: parseInt(getEl('object-target').value, 10), key: key.trim() }),
+      });
+      if (!response.ok) {
+        throw new Error(`HTTP ${response.status}: ${await response.text()}`);
+      }
+      fileReady(await response.json(), key.trim());
+    } catch (err) {
+      uploadFailed(err);
     }
   }
 
@@ -180,11 +249,19 @@
       const data = await response.json();
       const statusEl = getEl('job-status-import');
       statusEl.textContent = data.status;
+      if (data.status !== 'Running') getEl('cancel-import').style.display = 'none';
       if (data.status === 'Completed') { if (durationInterval) clearInterval(durationInterval); statusEl.style.color = '#27c93f'; }
+      else if (data.status === 'Cancelled') { if (durationInterval) clearInterval(durationInterval); statusEl.style.color = '#ffbd2e'; }
       else if (data.status === 'Failed') { if (durationInterval) clearInterval(durationInterval); statusEl.style.color = '#ff5f56'; if (data.error) appendTerminalImport('FAILED: ' + data.error, '#ff5f56'); }
     } catch (err) { console.error('Failed to check status:', err); }
   }
 
+  async function cancelImport() {
+    if (!jobId || !confirm('Cancel the running import? The target database may be left partially restored.')) return;
+    const response = await fetch(`${basePath}/maintenance/import/${jobId}/cancel`, { method: 'POST' });
+    if (!response.ok) appendTerminalImport('Cancel failed: ' + await response.text(), '#ff5f56');
+  }
+
   function appendTerminalImport(text, color = '#0f0') {
     const terminal = getEl('terminal-output-import');
     const line = document.createElement('div');
@@ -259,9 +336,12 @@
 
   window.initImportWizard = initImportWizard;
   window.handleFileUpload = handleFileUpload;
+  window.listObjects = listObjects;
+  window.fetchObject = fetchObject;
   window.updateStepImport = updateStepImport;
   window.updateCommandPreviewImport = updateCommandPreviewImport;
   window.startImport = startImport;
+  window.cancelImport = cancelImport;
   window.copyLogsImport = copyLogsImport;
   window.saveLogsImport = saveLogsImport;
 